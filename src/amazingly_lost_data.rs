@@ -1,27 +1,127 @@
 use crate::player;
 
-use crate::maze_generator::SMALL_MAZE;
+use amazingly_lost::maze_analysis::{Difficulty, MazeRating};
+use amazingly_lost::maze_chunks::{ChunkPosition, InfiniteMaze};
+use amazingly_lost::maze_floors::{FloorPosition, MultiFloorMaze};
+use amazingly_lost::maze_generator::{MazeAlgorithmType, MazeSizePreset};
+use amazingly_lost::maze_level::{LevelItem, MazeLevel};
+use amazingly_lost::maze_mask::MazeMask;
+use amazingly_lost::maze_placement::PlacementStrategy;
+use amazingly_lost::maze_rooms::RoomSettings;
+use amazingly_lost::maze_topology::{CellMaze, GridTopology};
 
+use crate::game_save::SaveGame;
 use crate::tile_factory::GameTileHandlers;
 
 use bevy::prelude::*;
 use player::Player;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 
 pub const DEFAULT_THEME: &str = "default";
+pub const DEFAULT_LOOPINESS: u8 = 10;
 
-// To be sure we don't go over a certain size for the maze -> minus 100
-pub const MAX_MAZE_SIZE_X_OR_Y: u16 = u16::MAX - 100;
+// Tile (x, y, floor) the player has been on. Tiles of the endless maze can be negative
+// and in a maze with hexagonal or triangular cells it's the cell.
+pub type VisitedPosition = (i64, i64, usize);
+
+// How much of the maze the player can see
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum LightMode {
+    // The whole maze is visible
+    Day,
+    // Only the tiles in the light of the torch
+    Night,
+    // Like Night, but the tiles that were in the light once stay visible
+    Fog,
+    // Darkness around the player, the rest of the maze is visible
+    Inverted,
+}
+
+impl LightMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            LightMode::Day => "Day",
+            LightMode::Night => "Night",
+            LightMode::Fog => "Fog",
+            LightMode::Inverted => "Inverted",
+        }
+    }
+
+    // Used to go through all light modes in the game menu
+    pub fn next(&self) -> Self {
+        match self {
+            LightMode::Day => LightMode::Night,
+            LightMode::Night => LightMode::Fog,
+            LightMode::Fog => LightMode::Inverted,
+            LightMode::Inverted => LightMode::Day,
+        }
+    }
+}
 
 //Important game
 pub struct AmazinglyLostData {
+    // Same seed and maze_size always give the same maze
+    pub seed: u64,
+    // Width and height, these don't need to be the same
     pub maze_size: (u16, u16),
-    pub maze_solution: Vec<(usize, usize)>,
+    // Maze from a level file (--level or --ascii), used instead of a generated maze
+    // until there is a new seed
+    pub level: Option<MazeLevel>,
+    // Items of the level that are still in the maze and the ones the player picked up
+    pub items: Vec<LevelItem>,
+    pub collected_items: Vec<LevelItem>,
+    // Shape of the maze, None is a rectangle
+    pub maze_mask: Option<MazeMask>,
+    pub maze_algorithm: MazeAlgorithmType,
+    // Square cells or hexagonal and triangular cells (these don't use maze_algorithm)
+    pub grid_topology: GridTopology,
+    // The maze with hexagonal or triangular cells, None for square cells
+    pub cell_maze: Option<CellMaze>,
+    // Rooms connected by corridors instead of a normal maze, None for a normal maze
+    pub room_settings: Option<RoomSettings>,
+    // Endless maze that is generated in chunks around the player
+    pub is_endless: bool,
+    pub infinite_maze: Option<InfiniteMaze>,
+    // Entities of the chunks that are painted now
+    pub loaded_chunks: HashMap<ChunkPosition, Vec<Entity>>,
+    // Chunk the camera is in, we load new chunks when this changes
+    pub current_chunk: Option<ChunkPosition>,
+    // Where the START and EXIT go, only for a normal maze with one floor
+    pub placement: PlacementStrategy,
+    // Chance in % that a dead end is opened up to make a loop
+    pub loopiness: u8,
+    // Requested difficulty for a new maze, None is any difficulty
+    pub difficulty: Option<Difficulty>,
+    pub maze_rating: Option<MazeRating>,
+    // Number of floors for a new maze, with more than one the player uses ladders
+    pub floor_count: usize,
+    // All floors of the current maze, only the current floor is painted
+    pub maze_floors: Option<MultiFloorMaze>,
+    pub current_floor: usize,
+    pub maze_solution: Vec<FloorPosition>,
     pub starting_point_sprites: (f32, f32),
-    pub exit_point_game: (usize, usize),
+    pub exit_point_game: FloorPosition,
     pub player: Player,
+    // Seconds played in this maze, it stops when the game is paused
+    pub play_time: f32,
+    // Tiles (or cells) the player walked in this maze
+    pub steps_walked: usize,
+    // The player looked at the solution in this maze
+    pub is_solution_shown: bool,
+    // The solution is visible now, P shows and hides it (see game_light)
+    pub is_solution_visible: bool,
+    pub visited: HashSet<VisitedPosition>,
+    // Tiles that were in the light of the torch, on the grid of the tiles (see
+    // game_maze::get_world_position), also for the walls of hexagonal and triangular cells
+    pub revealed: HashSet<VisitedPosition>,
+    // Saved game that is played again, the maze is rebuilt from it in the Loading state
+    pub saved_game: Option<SaveGame>,
     pub entity_player: Entity,
     // TODO:RG used settings??
     pub theme: String,
+    pub light_mode: LightMode,
     pub game_tile_handlers: GameTileHandlers,
     pub is_generating_maze: bool,
     pub is_loading_assets: bool,
@@ -31,16 +131,55 @@ impl AmazinglyLostData {
     // Initialize game data
     pub fn new() -> Self {
         AmazinglyLostData {
-            maze_size: (SMALL_MAZE, SMALL_MAZE),
-            maze_solution: Vec::<(usize, usize)>::new(),
+            seed: rand::thread_rng().gen(),
+            maze_size: MazeSizePreset::Small.get_size(),
+            level: None,
+            items: Vec::new(),
+            collected_items: Vec::new(),
+            maze_mask: None,
+            maze_algorithm: MazeAlgorithmType::RecursiveBacktracker,
+            grid_topology: GridTopology::Square,
+            cell_maze: None,
+            room_settings: None,
+            is_endless: false,
+            infinite_maze: None,
+            loaded_chunks: HashMap::new(),
+            current_chunk: None,
+            placement: PlacementStrategy::FarthestExit,
+            loopiness: DEFAULT_LOOPINESS,
+            difficulty: None,
+            maze_rating: None,
+            floor_count: 1,
+            maze_floors: None,
+            current_floor: 0,
+            maze_solution: Vec::<FloorPosition>::new(),
             starting_point_sprites: (0.0, 0.0),
-            exit_point_game: (0, 0),
+            exit_point_game: (0, 0, 0),
             player: Player::new(),
+            play_time: 0.0,
+            steps_walked: 0,
+            is_solution_shown: false,
+            is_solution_visible: false,
+            visited: HashSet::new(),
+            revealed: HashSet::new(),
+            saved_game: None,
             entity_player: Entity::new(0),
             theme: DEFAULT_THEME.to_string(),
+            light_mode: LightMode::Day,
             game_tile_handlers: GameTileHandlers::new(),
             is_generating_maze: false,
             is_loading_assets: false,
         }
     }
+
+    // Every new maze gets a new seed, unless we want to play the same maze again
+    pub fn new_seed(&mut self) {
+        self.seed = rand::thread_rng().gen();
+        self.level = None;
+    }
+
+    // False until the first maze is generated
+    pub fn has_maze(&self) -> bool {
+        self.maze_floors.is_some() || self.cell_maze.is_some() || self.infinite_maze.is_some()
+    }
 }
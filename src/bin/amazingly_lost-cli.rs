@@ -0,0 +1,448 @@
+// Generate mazes from the command line, without Bevy (no window or GPU needed).
+// Uses the same maze library as the game, so the same seed gives the same maze.
+//
+// amazingly_lost-cli --width 41 --height 21 --seed 1234 --format png --output maze.png
+// amazingly_lost-cli --count 100 --algorithm wilson --loops 20
+use amazingly_lost::maze::Maze;
+use amazingly_lost::maze_analysis::MazeRating;
+use amazingly_lost::maze_ascii;
+use amazingly_lost::maze_braid::MAX_LOOPINESS;
+use amazingly_lost::maze_generator::{self, MazeAlgorithmType, SMALL_MAZE};
+use amazingly_lost::maze_image::{self, DEFAULT_TILE_SIZE};
+use amazingly_lost::maze_level::{LevelFormat, MazeLevel};
+use amazingly_lost::maze_placement::PlacementStrategy;
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::{Duration, Instant};
+
+const DEFAULT_LOOPINESS: u8 = 10;
+// Same theme as the game, so a level from the tool looks the same in the game
+const DEFAULT_THEME: &str = "default";
+
+const USAGE: &str = "Usage: amazingly_lost-cli [options]
+
+Options:
+  --width <tiles>        width of the maze (default 33)
+  --height <tiles>       height of the maze (default 33)
+  --size <tiles>         width and height of the maze
+  --seed <number>        seed of the (first) maze, random if not given
+  --algorithm <name>     recursive-backtracker, kruskal, prim, wilson, eller, hunt-and-kill,
+                         growing-tree, sidewinder, binary-tree, recursive-division or weave
+  --loops <percent>      chance that a dead end gets opened up, 0 is a perfect maze (default 10)
+  --format <format>      ascii, json, ron, png or svg (default ascii)
+  --output <file>        write the maze to this file instead of the screen
+  --tile-size <pixels>   size of a tile in a png or svg (default 10)
+  --solution             also draw the solution in a png or svg
+  --count <number>       generate this many mazes (seed, seed + 1, ...) and show stats,
+                         without --output the mazes go to the screen and the stats to stderr
+  --stats                show the stats, also for one maze
+  --help                 show this";
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum OutputFormat {
+    Ascii,
+    Level(LevelFormat),
+    Png,
+    Svg,
+}
+
+impl OutputFormat {
+    fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "ascii" | "txt" => Some(OutputFormat::Ascii),
+            "json" => Some(OutputFormat::Level(LevelFormat::Json)),
+            "ron" => Some(OutputFormat::Level(LevelFormat::Ron)),
+            "png" => Some(OutputFormat::Png),
+            "svg" => Some(OutputFormat::Svg),
+            _ => None,
+        }
+    }
+
+    fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Ascii => "txt",
+            OutputFormat::Level(LevelFormat::Json) => "json",
+            OutputFormat::Level(LevelFormat::Ron) => "ron",
+            OutputFormat::Png => "png",
+            OutputFormat::Svg => "svg",
+        }
+    }
+}
+
+struct Options {
+    width: usize,
+    height: usize,
+    seed: u64,
+    algorithm: MazeAlgorithmType,
+    loopiness: u8,
+    format: OutputFormat,
+    output: Option<PathBuf>,
+    tile_size: u32,
+    show_solution: bool,
+    count: usize,
+    show_stats: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            width: SMALL_MAZE as usize,
+            height: SMALL_MAZE as usize,
+            seed: rand::thread_rng().gen(),
+            algorithm: MazeAlgorithmType::RecursiveBacktracker,
+            loopiness: DEFAULT_LOOPINESS,
+            format: OutputFormat::Ascii,
+            output: None,
+            tile_size: DEFAULT_TILE_SIZE,
+            show_solution: false,
+            count: 1,
+            show_stats: false,
+        }
+    }
+}
+
+// What the stats need of a maze, so the maze itself doesn't have to be kept
+struct MazeStats {
+    seed: u64,
+    rating: MazeRating,
+    duration: Duration,
+}
+
+// One generated maze with what we need for the output and the stats
+struct GeneratedMaze {
+    maze: Maze,
+    solution: Vec<(usize, usize)>,
+    stats: MazeStats,
+}
+
+fn main() {
+    let options = match parse_arguments(env::args().skip(1)) {
+        Ok(Some(options)) => options,
+        Ok(None) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(error) => {
+            eprintln!("{}\n\n{}", error, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(error) = run(&options) {
+        eprintln!("{}", error);
+        process::exit(1);
+    }
+}
+
+fn run(options: &Options) -> Result<(), String> {
+    // Without a file the mazes themselves go to the screen
+    let mazes_to_stdout = options.output.is_none();
+    let mut maze_stats = Vec::<MazeStats>::with_capacity(options.count);
+
+    for number in 0..options.count {
+        let generated_maze = generate_maze(options, options.seed.wrapping_add(number as u64));
+
+        if let Some(output) = &options.output {
+            let path = get_output_path(output, number, options);
+            fs::write(&path, get_output(options, &generated_maze)?)
+                .map_err(|error| format!("Can't write {}: {}", path.display(), error))?;
+        } else {
+            let mut stdout = io::stdout();
+            // An empty line between the mazes
+            if number > 0 {
+                stdout.write_all(b"\n").map_err(|error| error.to_string())?;
+            }
+            stdout
+                .write_all(&get_output(options, &generated_maze)?)
+                .map_err(|error| error.to_string())?;
+        }
+        maze_stats.push(generated_maze.stats);
+    }
+
+    if options.count > 1 || options.show_stats {
+        let stats = get_stats(options, &maze_stats);
+        // Don't mix the stats with the mazes on the screen
+        if mazes_to_stdout {
+            eprint!("{}", stats);
+        } else {
+            print!("{}", stats);
+        }
+    }
+    Ok(())
+}
+
+fn generate_maze(options: &Options, seed: u64) -> GeneratedMaze {
+    let started = Instant::now();
+    // Same as the game: all randomness for a maze comes from its seed
+    let mut rng = StdRng::seed_from_u64(seed);
+    let (maze, solution, rating) = maze_generator::create_maze_with_difficulty(
+        options.width,
+        options.height,
+        &None,
+        &options.algorithm,
+        options.loopiness,
+        &None,
+        &PlacementStrategy::FarthestExit,
+        &mut rng,
+    );
+
+    GeneratedMaze {
+        maze,
+        solution,
+        stats: MazeStats {
+            seed,
+            rating,
+            duration: started.elapsed(),
+        },
+    }
+}
+
+fn get_output(options: &Options, generated_maze: &GeneratedMaze) -> Result<Vec<u8>, String> {
+    let solution = if options.show_solution {
+        Some(generated_maze.solution.as_slice())
+    } else {
+        None
+    };
+
+    match options.format {
+        OutputFormat::Ascii => Ok(maze_ascii::maze_to_ascii(&generated_maze.maze).into_bytes()),
+        OutputFormat::Level(level_format) => MazeLevel::from_maze(
+            &generated_maze.maze,
+            &generated_maze.solution,
+            generated_maze.stats.seed,
+            options.algorithm,
+            DEFAULT_THEME,
+        )
+        .to_text(&level_format)
+        .map(|text| (text + "\n").into_bytes())
+        .map_err(|error| error.to_string()),
+        OutputFormat::Png => {
+            maze_image::maze_to_png(&generated_maze.maze, solution, options.tile_size)
+                .map_err(|error| error.to_string())
+        }
+        OutputFormat::Svg => {
+            maze_image::maze_to_svg(&generated_maze.maze, solution, options.tile_size)
+                .map(String::into_bytes)
+                .map_err(|error| error.to_string())
+        }
+    }
+}
+
+// With more mazes every maze gets its own file: maze.png becomes maze_1.png, maze_2.png, ...
+fn get_output_path(output: &Path, number: usize, options: &Options) -> PathBuf {
+    if options.count == 1 {
+        return output.to_path_buf();
+    }
+
+    let stem = output
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("maze");
+    let extension = output
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .unwrap_or_else(|| options.format.extension());
+    output.with_file_name(format!("{}_{}.{}", stem, number + 1, extension))
+}
+
+fn get_stats(options: &Options, maze_stats: &[MazeStats]) -> String {
+    let mut stats = format!(
+        "{} maze(s) of {}x{}, algorithm: {}, loops: {}%\n",
+        maze_stats.len(),
+        options.width,
+        options.height,
+        options.algorithm.get_algorithm().name(),
+        options.loopiness
+    );
+    stats.push_str(&format!(
+        "{:<20} {:>9} {:>10} {:>10} {:<16} {:>7}\n",
+        "seed", "solution", "dead ends", "junctions", " difficulty", "time"
+    ));
+
+    for stats_of_maze in maze_stats {
+        let rating = &stats_of_maze.rating;
+        stats.push_str(&format!(
+            "{:<20} {:>9} {:>10} {:>10} {:>6.1} {:<9} {:>5}ms\n",
+            stats_of_maze.seed,
+            rating.solution_length,
+            rating.dead_end_count,
+            rating.junction_count,
+            rating.difficulty_score,
+            rating.difficulty().name(),
+            stats_of_maze.duration.as_millis()
+        ));
+    }
+
+    if maze_stats.len() > 1 {
+        let solution_lengths: Vec<f32> = maze_stats
+            .iter()
+            .map(|stats_of_maze| stats_of_maze.rating.solution_length as f32)
+            .collect();
+        let dead_ends: Vec<f32> = maze_stats
+            .iter()
+            .map(|stats_of_maze| stats_of_maze.rating.dead_end_count as f32)
+            .collect();
+        let scores: Vec<f32> = maze_stats
+            .iter()
+            .map(|stats_of_maze| stats_of_maze.rating.difficulty_score)
+            .collect();
+        let total_duration: Duration = maze_stats
+            .iter()
+            .map(|stats_of_maze| stats_of_maze.duration)
+            .sum();
+
+        stats.push_str(&format!(
+            "solution length  {}\ndead ends        {}\ndifficulty       {}\n",
+            get_min_average_max(&solution_lengths),
+            get_min_average_max(&dead_ends),
+            get_min_average_max(&scores)
+        ));
+        stats.push_str(&format!(
+            "total time       {}ms ({:.1}ms per maze)\n",
+            total_duration.as_millis(),
+            total_duration.as_secs_f64() * 1000.0 / maze_stats.len() as f64
+        ));
+        stats.push_str(&format!(
+            "difficulties     {}\n",
+            get_difficulty_counts(maze_stats)
+        ));
+    }
+    stats
+}
+
+// How many mazes there are of every difficulty, so we can see what an algorithm gives
+fn get_difficulty_counts(maze_stats: &[MazeStats]) -> String {
+    let mut counts = Vec::<(&str, usize)>::new();
+
+    for stats_of_maze in maze_stats {
+        let name = stats_of_maze.rating.difficulty().name();
+        match counts
+            .iter_mut()
+            .find(|(counted_name, _)| *counted_name == name)
+        {
+            Some((_, count)) => *count += 1,
+            None => counts.push((name, 1)),
+        }
+    }
+
+    counts
+        .iter()
+        .map(|(name, count)| format!("{}: {}", name, count))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+fn get_min_average_max(values: &[f32]) -> String {
+    let min = values.iter().copied().fold(f32::MAX, f32::min);
+    let max = values.iter().copied().fold(f32::MIN, f32::max);
+    let average = values.iter().sum::<f32>() / values.len().max(1) as f32;
+
+    format!("min {:.1}, average {:.1}, max {:.1}", min, average, max)
+}
+
+// None when only the help is asked
+fn parse_arguments<I: Iterator<Item = String>>(
+    mut arguments: I,
+) -> Result<Option<Options>, String> {
+    let mut options = Options::default();
+    let mut format_is_given = false;
+
+    while let Some(argument) = arguments.next() {
+        match argument.as_str() {
+            "--help" | "-h" => return Ok(None),
+            "--solution" => options.show_solution = true,
+            "--stats" => options.show_stats = true,
+            "--width" | "--height" | "--size" | "--seed" | "--algorithm" | "--loops"
+            | "--braid" | "--format" | "--output" | "--tile-size" | "--count" => {
+                let value = arguments
+                    .next()
+                    .ok_or(format!("No value given for {}", argument))?;
+
+                match argument.as_str() {
+                    "--width" => options.width = parse_number(&argument, &value)?,
+                    "--height" => options.height = parse_number(&argument, &value)?,
+                    "--size" => {
+                        options.width = parse_number(&argument, &value)?;
+                        options.height = options.width;
+                    }
+                    "--seed" => options.seed = parse_number(&argument, &value)?,
+                    "--algorithm" => {
+                        options.algorithm = get_algorithm_by_name(&value)
+                            .ok_or(format!("Unknown algorithm: {}", value))?
+                    }
+                    "--loops" | "--braid" => {
+                        options.loopiness = parse_number(&argument, &value)?;
+                        if options.loopiness > MAX_LOOPINESS {
+                            return Err(format!("Loops can be at most {}%", MAX_LOOPINESS));
+                        }
+                    }
+                    "--format" => {
+                        options.format = OutputFormat::from_name(&value)
+                            .ok_or(format!("Unknown format: {}", value))?;
+                        format_is_given = true;
+                    }
+                    "--output" => options.output = Some(PathBuf::from(value)),
+                    "--tile-size" => options.tile_size = parse_number(&argument, &value)?,
+                    _ => options.count = parse_number(&argument, &value)?,
+                }
+            }
+            _ => return Err(format!("Unknown option: {}", argument)),
+        }
+    }
+
+    // maze.png is a png, unless another format is asked
+    if let (false, Some(output)) = (format_is_given, &options.output) {
+        if let Some(format) = output
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .and_then(OutputFormat::from_name)
+        {
+            options.format = format;
+        }
+    }
+    if options.count == 0 {
+        return Err("Count needs to be at least 1".to_string());
+    }
+    // Images after each other on the screen are not one image anymore
+    if options.count > 1
+        && options.output.is_none()
+        && matches!(options.format, OutputFormat::Png | OutputFormat::Svg)
+    {
+        return Err("More than one png or svg needs --output".to_string());
+    }
+    // Same size as the game would use
+    let (width, height) = maze_generator::get_valid_maze_size(options.width, options.height);
+    options.width = width;
+    options.height = height;
+
+    Ok(Some(options))
+}
+
+fn parse_number<T: std::str::FromStr>(argument: &str, value: &str) -> Result<T, String> {
+    value
+        .parse::<T>()
+        .map_err(|_| format!("Not a valid number for {}: {}", argument, value))
+}
+
+// Names like in the game, without the case and with - or _ for the spaces
+fn get_algorithm_by_name(name: &str) -> Option<MazeAlgorithmType> {
+    let name = name.to_lowercase().replace(&['-', '_'][..], " ");
+    let first_algorithm = MazeAlgorithmType::RecursiveBacktracker;
+    let mut algorithm = first_algorithm;
+
+    loop {
+        if algorithm.get_algorithm().name().to_lowercase() == name {
+            return Some(algorithm);
+        }
+        algorithm = algorithm.next();
+        if algorithm == first_algorithm {
+            return None;
+        }
+    }
+}
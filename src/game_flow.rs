@@ -1,8 +1,10 @@
 use crate::amazingly_lost_data::AmazinglyLostData;
+use crate::game_maze::{ChangeFloorEvent, GameTileEntity};
 use crate::game_state::{ChangeGameStateEvent, GameState};
-use crate::maze_generator::GameTile;
 
-use crate::{maze_generator, tile_factory};
+use crate::{game_maze, game_save, tile_factory, GAME_TITLE};
+use amazingly_lost::maze_chunks;
+use amazingly_lost::maze_placement::PlacementStrategy;
 use bevy::prelude::*;
 use bevy::render::camera::Camera;
 
@@ -10,7 +12,10 @@ pub struct GameFlowPlugin;
 
 impl Plugin for GameFlowPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_system(init_game_flow.system());
+        app.add_system(init_game_flow.system())
+            .add_system(change_floor.system())
+            .add_system(stream_chunks.system())
+            .add_system(count_play_time.system());
     }
 }
 
@@ -18,15 +23,21 @@ fn init_game_flow(
     mut commands: Commands,
     mut amazing_data: ResMut<AmazinglyLostData>,
     mut camera_query: Query<(&mut Transform, &Camera)>,
-    mut game_tile_query: Query<(Entity, (With<GameTile>, Without<Camera>))>,
+    mut game_tile_query: Query<GameTileEntity>,
     mut asset_server: Res<AssetServer>,
     mut materials: ResMut<Assets<ColorMaterial>>,
     game_state: ResMut<State<GameState>>,
     mut change_game_state: EventWriter<ChangeGameStateEvent>,
     mut only_camera: Query<(Entity, With<Camera>)>,
+    mut windows: ResMut<Windows>,
 ) {
     match game_state.current() {
-        GameState::StartMenu => {}
+        GameState::StartMenu => {
+            // Another theme was chosen in the menu, the next maze uses its textures
+            if amazing_data.game_tile_handlers.theme != amazing_data.theme {
+                tile_factory::load_all_assets(&mut amazing_data, &mut asset_server, &mut materials);
+            }
+        }
         GameState::PlayingGame => {
             amazing_data.is_generating_maze = false;
 
@@ -43,24 +54,60 @@ fn init_game_flow(
             if !amazing_data.is_generating_maze {
                 amazing_data.is_generating_maze = true;
                 println!("GeneratingGame");
+                // The theme can also be changed in the settings during the game
+                if amazing_data.game_tile_handlers.theme != amazing_data.theme {
+                    tile_factory::load_all_assets(
+                        &mut amazing_data,
+                        &mut asset_server,
+                        &mut materials,
+                    );
+                }
                 // First clear the game field
-                maze_generator::clear_maze_tiles(&mut commands, &mut game_tile_query);
+                game_maze::clear_maze_tiles(&mut commands, &mut game_tile_query);
                 // Now create a new maze
-                maze_generator::create_new_maze(
-                    &mut commands,
-                    &mut amazing_data,
-                    &mut camera_query,
-                );
+                game_maze::create_new_maze(&mut commands, &mut amazing_data, &mut camera_query);
+                // Show the seed, so the maze can be shared and played again
+                if let Some(window) = windows.get_primary_mut() {
+                    window.set_title(get_window_title(&amazing_data));
+                }
                 change_game_state.send(ChangeGameStateEvent(GameState::PlayingGame));
             } else {
                 // println!("Tried to generate the maze twice");
             }
         }
-        GameState::Settings => {
-            println!("Settings");
-        }
-        GameState::Save => {
-            println!("save");
+        // The menus are in game_ui and saving is done in game_save
+        GameState::Pause | GameState::Win | GameState::Settings | GameState::Save => {}
+        GameState::Loading => {
+            // The saved game is taken, so it is only loaded once
+            if let Some(save_game) = amazing_data.saved_game.take() {
+                println!("Loading");
+                amazing_data.theme = save_game.theme.clone();
+                if amazing_data.game_tile_handlers.theme != amazing_data.theme {
+                    tile_factory::load_all_assets(
+                        &mut amazing_data,
+                        &mut asset_server,
+                        &mut materials,
+                    );
+                }
+                match game_save::resume_saved_game(
+                    &mut commands,
+                    &mut amazing_data,
+                    &mut camera_query,
+                    &mut game_tile_query,
+                    save_game,
+                ) {
+                    Ok(()) => {
+                        if let Some(window) = windows.get_primary_mut() {
+                            window.set_title(get_window_title(&amazing_data));
+                        }
+                        change_game_state.send(ChangeGameStateEvent(GameState::PlayingGame));
+                    }
+                    Err(error) => {
+                        println!("{}", error);
+                        change_game_state.send(ChangeGameStateEvent(GameState::StartMenu));
+                    }
+                }
+            }
         }
         GameState::LoadingAssets => {
             // Just in case there is a delay with Bevy and LoadingAssets is triggerd twice
@@ -68,8 +115,148 @@ fn init_game_flow(
             if !amazing_data.is_loading_assets {
                 amazing_data.is_loading_assets = true;
                 tile_factory::load_all_assets(&mut amazing_data, &mut asset_server, &mut materials);
-                change_game_state.send(ChangeGameStateEvent(GameState::GenerateNewGame));
+                change_game_state.send(ChangeGameStateEvent(GameState::StartMenu));
+            }
+        }
+    }
+}
+
+// Only the time the player is really playing, not in the menus
+fn count_play_time(
+    time: Res<Time>,
+    mut amazing_data: ResMut<AmazinglyLostData>,
+    game_state: Res<State<GameState>>,
+) {
+    if *game_state.current() == GameState::PlayingGame {
+        amazing_data.play_time += time.delta_seconds();
+    }
+}
+
+// Paint the floor the player went to with a ladder
+fn change_floor(
+    mut commands: Commands,
+    mut amazing_data: ResMut<AmazinglyLostData>,
+    mut camera_query: Query<(&mut Transform, &Camera)>,
+    mut game_tile_query: Query<GameTileEntity>,
+    mut change_floor: EventReader<ChangeFloorEvent>,
+    mut windows: ResMut<Windows>,
+) {
+    if let Some(ChangeFloorEvent(position)) = change_floor.iter().last() {
+        println!("Floor: {}", position.2 + 1);
+        game_maze::clear_maze_tiles(&mut commands, &mut game_tile_query);
+        game_maze::show_floor(
+            &mut commands,
+            &mut amazing_data,
+            &mut camera_query,
+            position,
+        );
+
+        if let Some(window) = windows.get_primary_mut() {
+            window.set_title(get_window_title(&amazing_data));
+        }
+    }
+}
+
+// Paint the chunks of the endless maze around the camera when it goes to another chunk
+fn stream_chunks(
+    mut commands: Commands,
+    mut amazing_data: ResMut<AmazinglyLostData>,
+    camera_query: Query<&Transform, With<Camera>>,
+    game_state: Res<State<GameState>>,
+    mut windows: ResMut<Windows>,
+) {
+    if *game_state.current() != GameState::PlayingGame || amazing_data.infinite_maze.is_none() {
+        return;
+    }
+
+    if let Some(camera_transform) = camera_query.iter().next() {
+        let chunk = maze_chunks::chunk_of_position(&game_maze::get_world_position(
+            &camera_transform.translation,
+        ));
+
+        if amazing_data.current_chunk != Some(chunk) {
+            game_maze::load_chunks_around(&mut commands, &mut amazing_data, &chunk);
+
+            if let Some(window) = windows.get_primary_mut() {
+                window.set_title(get_window_title(&amazing_data));
             }
         }
     }
 }
+
+fn get_window_title(amazing_data: &AmazinglyLostData) -> String {
+    let mut title = format!(
+        "{} - seed: {} - {} - loops: {}%",
+        GAME_TITLE,
+        amazing_data.seed,
+        amazing_data.maze_algorithm.get_algorithm().name(),
+        amazing_data.loopiness
+    );
+
+    // Seed, algorithm and loops are not used for a maze from a level file
+    if let Some(level) = &amazing_data.level {
+        let name = if level.name.is_empty() {
+            "maze from a file"
+        } else {
+            &level.name
+        };
+        title = format!(
+            "{} - {}: {}x{}",
+            GAME_TITLE, name, amazing_data.maze_size.0, amazing_data.maze_size.1
+        );
+        if let Some(maze_rating) = &amazing_data.maze_rating {
+            title.push_str(&format!(
+                " - difficulty: {:.0} ({})",
+                maze_rating.difficulty_score,
+                maze_rating.difficulty().name()
+            ));
+        }
+        return title;
+    }
+
+    if let Some(infinite_maze) = &amazing_data.infinite_maze {
+        let (chunk_x, chunk_y) = amazing_data.current_chunk.unwrap_or((0, 0));
+        let (exit_x, exit_y) = infinite_maze.exit_chunk();
+        title.push_str(&format!(
+            " - endless, chunk: ({}, {}), exit: ({}, {})",
+            chunk_x, chunk_y, exit_x, exit_y
+        ));
+        return title;
+    }
+
+    if amazing_data.room_settings.is_some()
+        && amazing_data.floor_count == 1
+        && amazing_data.cell_maze.is_none()
+    {
+        title.push_str(" - rooms");
+    }
+
+    if amazing_data.placement != PlacementStrategy::FarthestExit
+        && amazing_data.floor_count == 1
+        && amazing_data.cell_maze.is_none()
+    {
+        title.push_str(&format!(
+            " - {}",
+            amazing_data.placement.name().to_lowercase()
+        ));
+    }
+
+    if amazing_data.cell_maze.is_some() {
+        title.push_str(&format!(" - cells: {}", amazing_data.grid_topology.name()));
+    } else if amazing_data.floor_count > 1 {
+        title.push_str(&format!(
+            " - floor: {}/{}",
+            amazing_data.current_floor + 1,
+            amazing_data.floor_count
+        ));
+    }
+
+    if let Some(maze_rating) = &amazing_data.maze_rating {
+        title.push_str(&format!(
+            " - difficulty: {:.0} ({})",
+            maze_rating.difficulty_score,
+            maze_rating.difficulty().name()
+        ));
+    }
+    title
+}
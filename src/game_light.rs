@@ -0,0 +1,97 @@
+use crate::amazingly_lost_data::{AmazinglyLostData, LightMode};
+use crate::game_maze::{self, GameTile, PlayerTile, SolutionPath, SolutionTile, SPRITE_SIZE_MAZE};
+use crate::game_settings::GameSettings;
+
+use bevy::prelude::*;
+
+// What the tiles were made visible for the last time, nothing changes until one of these does
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct LitArea {
+    // Tile the player is on (or almost on), in tiles from the bottom left of the maze
+    player_tile: (i64, i64),
+    current_floor: usize,
+    light_mode: LightMode,
+    light_radius: f32,
+    is_solution_visible: bool,
+}
+
+// A tile with what we need to know to show or hide it
+type LightedTile<'a> = (
+    &'a Transform,
+    &'a mut Visible,
+    Option<&'a SolutionTile>,
+    Option<&'a SolutionPath>,
+);
+
+pub struct GameLightPlugin;
+
+impl Plugin for GameLightPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(update_tile_visibility.system());
+    }
+}
+
+// The player carries a torch. At night only the tiles in its light are visible and with
+// the inverted light it's the other way around, the rest of the maze is visible.
+// In the Fog the tiles that were in the light once stay visible.
+// The solution (press P) is also shown and hidden here.
+fn update_tile_visibility(
+    mut amazing_data: ResMut<AmazinglyLostData>,
+    game_settings: Res<GameSettings>,
+    player_query: Query<&Transform, With<PlayerTile>>,
+    mut game_tile_query: Query<LightedTile, (With<GameTile>, Without<PlayerTile>)>,
+    new_tile_query: Query<Entity, Added<GameTile>>,
+    mut last_lit_area: Local<Option<LitArea>>,
+) {
+    let player_translation = match player_query.single() {
+        Ok(player_transform) => player_transform.translation,
+        Err(_) => return,
+    };
+
+    let lit_area = LitArea {
+        player_tile: (
+            (player_translation.x / SPRITE_SIZE_MAZE as f32).round() as i64,
+            (player_translation.y / SPRITE_SIZE_MAZE as f32).round() as i64,
+        ),
+        current_floor: amazing_data.current_floor,
+        light_mode: amazing_data.light_mode,
+        light_radius: game_settings.light_radius,
+        is_solution_visible: amazing_data.is_solution_visible,
+    };
+    // New tiles (another maze, floor or chunk) also need to be lit
+    if *last_lit_area == Some(lit_area) && new_tile_query.iter().next().is_none() {
+        return;
+    }
+    *last_lit_area = Some(lit_area);
+
+    let light_distance = lit_area.light_radius * SPRITE_SIZE_MAZE as f32;
+    for (transform, mut visible, solution_tile, solution_path) in game_tile_query.iter_mut() {
+        let is_in_light = transform
+            .translation
+            .truncate()
+            .distance(player_translation.truncate())
+            <= light_distance;
+        let (tile_x, tile_y) = game_maze::get_world_position(&transform.translation);
+        let tile = (tile_x, tile_y, lit_area.current_floor);
+        if is_in_light && !amazing_data.revealed.contains(&tile) {
+            amazing_data.revealed.insert(tile);
+        }
+
+        let is_lit = match lit_area.light_mode {
+            LightMode::Day => true,
+            LightMode::Night => is_in_light,
+            LightMode::Fog => is_in_light || amazing_data.revealed.contains(&tile),
+            LightMode::Inverted => !is_in_light,
+        };
+        // The solution path and the ground under it are never visible together
+        let is_shown = match solution_tile {
+            Some(_) => solution_path.is_some() == lit_area.is_solution_visible,
+            None => true,
+        };
+
+        // Only a changed Visible, so Bevy doesn't see every tile as changed
+        if visible.is_visible != (is_lit && is_shown) {
+            visible.is_visible = is_lit && is_shown;
+        }
+    }
+}
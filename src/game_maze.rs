@@ -0,0 +1,902 @@
+use crate::amazingly_lost_data::AmazinglyLostData;
+
+use amazingly_lost::maze::Maze;
+use amazingly_lost::maze_analysis;
+use amazingly_lost::maze_chunks::{
+    self, ChunkPosition, InfiniteMaze, WorldPosition, CHUNK_TILES, DEFAULT_EXIT_DISTANCE,
+};
+use amazingly_lost::maze_floors::{FloorPosition, MultiFloorMaze};
+use amazingly_lost::maze_generator;
+use amazingly_lost::maze_level::LevelItem;
+use amazingly_lost::maze_tile::{MazeTile, TileType};
+use amazingly_lost::maze_topology::{CellMaze, GridDirection, GridTopology};
+
+use bevy::prelude::*;
+use bevy::render::camera::Camera;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+pub const SPRITE_SIZE_MAZE: usize = 100;
+// Hexagonal and triangular cells have no WALL tiles, so they can be bigger
+pub const CELL_SIZE_MAZE: f32 = 2.0 * SPRITE_SIZE_MAZE as f32;
+const CELL_WALL_THICKNESS: f32 = 16.0;
+// Items are smaller than a tile, so the ground around them is still visible
+const ITEM_SIZE: f32 = SPRITE_SIZE_MAZE as f32 / 2.0;
+// Chunks this far away from the camera are painted in the endless maze.
+// Chunks that are one more chunk away are removed again.
+pub const CHUNK_LOAD_RADIUS: i32 = 2;
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CollisionType {
+    Wall,
+    Exit,
+    Start,
+    // Where the player ends up when taking the ladder
+    Ladder(FloorPosition),
+    // The player can only walk straight over a bridge
+    Bridge,
+}
+
+// Collision structs
+#[derive(Clone, Debug, PartialEq)]
+pub struct CollisionTile {
+    pub collision_type: CollisionType,
+}
+
+// A MovingTile and LockedTile are both a GameTile
+pub struct GameTile;
+pub struct SolutionTile;
+// The SolutionTile with the solution material, the other one is the ground under it
+pub struct SolutionPath;
+pub struct PlayerTile;
+// An item of the level that the player can pick up
+pub struct ItemTile(pub LevelItem);
+
+// Every tile of the maze, but never the camera
+pub type GameTileEntity = (Entity, (With<GameTile>, Without<Camera>));
+
+// The player took a ladder and goes to this position on another floor
+pub struct ChangeFloorEvent(pub FloorPosition);
+
+pub fn create_new_maze(
+    commands: &mut Commands,
+    amazing_data: &mut ResMut<AmazinglyLostData>,
+    camera_query: &mut Query<(&mut Transform, &Camera)>,
+) {
+    // Everything random for this maze comes from the seed, so the same seed
+    // and maze size always give the same maze (and the same decorations)
+    let mut rng = StdRng::seed_from_u64(amazing_data.seed);
+    amazing_data.play_time = 0.0;
+    amazing_data.steps_walked = 0;
+    amazing_data.is_solution_shown = false;
+    amazing_data.is_solution_visible = false;
+    amazing_data.visited.clear();
+    amazing_data.revealed.clear();
+    println!("Seed: {}", amazing_data.seed);
+    println!(
+        "Algorithm: {}",
+        amazing_data.maze_algorithm.get_algorithm().name()
+    );
+
+    // Even sizes are made odd, we need to know the size the maze really has
+    let (width, height) = maze_generator::get_valid_maze_size(
+        amazing_data.maze_size.0 as usize,
+        amazing_data.maze_size.1 as usize,
+    );
+    amazing_data.maze_size = (width as u16, height as u16);
+
+    // All tiles are gone, so the chunks of the endless maze are too
+    amazing_data.loaded_chunks.clear();
+    amazing_data.current_chunk = None;
+    amazing_data.infinite_maze = None;
+    // A level from a file is played until there is a new seed.
+    // A level that is not a valid maze is dropped and a new maze is generated instead.
+    let level_maze = match amazing_data.level.as_ref().map(|level| level.to_maze()) {
+        Some(Ok(level_maze)) => Some(level_maze),
+        Some(Err(error)) => {
+            println!("{}", error);
+            amazing_data.level = None;
+            None
+        }
+        None => None,
+    };
+    // Only a level has items, they are all back in a new maze
+    amazing_data.items = match (&level_maze, &amazing_data.level) {
+        (Some(_), Some(level)) => level.items.clone(),
+        _ => Vec::new(),
+    };
+    amazing_data.collected_items.clear();
+    if amazing_data.is_endless && level_maze.is_none() {
+        create_new_infinite_maze(commands, amazing_data, camera_query);
+        return;
+    }
+
+    if amazing_data.grid_topology != GridTopology::Square && level_maze.is_none() {
+        create_new_cell_maze(commands, amazing_data, camera_query, &mut rng);
+        return;
+    }
+    amazing_data.cell_maze = None;
+
+    let (maze_floors, solution) = if let Some((maze, solution)) = level_maze {
+        amazing_data.maze_size = (maze.width() as u16, maze.height() as u16);
+        amazing_data.maze_rating = Some(maze_analysis::analyse_maze(&maze, &solution));
+
+        let solution = solution.iter().map(|(x, y)| (*x, *y, 0)).collect();
+        (MultiFloorMaze::from_floors(vec![maze]), solution)
+    } else if amazing_data.floor_count > 1 {
+        let (maze_floors, solution) = maze_generator::create_multi_floor_maze(
+            width,
+            height,
+            amazing_data.floor_count,
+            &amazing_data.maze_mask,
+            &amazing_data.maze_algorithm,
+            amazing_data.loopiness,
+            &mut rng,
+        );
+        // We can only rate a maze with one floor
+        amazing_data.maze_rating = None;
+        (maze_floors, solution)
+    } else if let Some(room_settings) = amazing_data.room_settings {
+        let (maze, solution) = maze_generator::create_rooms_maze(
+            width,
+            height,
+            &amazing_data.maze_mask,
+            &room_settings,
+            amazing_data.loopiness,
+            &amazing_data.placement,
+            &mut rng,
+        );
+        // We don't look for a difficulty with rooms, but we can still rate the maze
+        amazing_data.maze_rating = Some(maze_analysis::analyse_maze(&maze, &solution));
+
+        let solution = solution.iter().map(|(x, y)| (*x, *y, 0)).collect();
+        (MultiFloorMaze::from_floors(vec![maze]), solution)
+    } else {
+        let (maze, solution, maze_rating) = maze_generator::create_maze_with_difficulty(
+            width,
+            height,
+            &amazing_data.maze_mask,
+            &amazing_data.maze_algorithm,
+            amazing_data.loopiness,
+            &amazing_data.difficulty,
+            &amazing_data.placement,
+            &mut rng,
+        );
+        amazing_data.maze_rating = Some(maze_rating);
+
+        let solution = solution.iter().map(|(x, y)| (*x, *y, 0)).collect();
+        (MultiFloorMaze::from_floors(vec![maze]), solution)
+    };
+
+    let starting_point = *solution.first().unwrap();
+    amazing_data.exit_point_game = *solution.last().unwrap();
+    amazing_data.maze_floors = Some(maze_floors);
+    // set solution for this maze
+    amazing_data.maze_solution = solution;
+
+    // Make the floor with the start visible for the player.
+    // Place player and camera on the same position.
+    show_floor(commands, amazing_data, camera_query, &starting_point);
+
+    // Set player starting position here. Is needed for camera/player placement
+    amazing_data.player.position_x = starting_point.0 as f32;
+    amazing_data.player.position_y = starting_point.1 as f32;
+}
+
+// The endless maze, only the chunks around the start are painted now.
+// The other chunks are painted when the camera gets close to them.
+fn create_new_infinite_maze(
+    commands: &mut Commands,
+    amazing_data: &mut ResMut<AmazinglyLostData>,
+    camera_query: &mut Query<(&mut Transform, &Camera)>,
+) {
+    let infinite_maze = InfiniteMaze::new(
+        amazing_data.seed,
+        amazing_data.maze_algorithm,
+        amazing_data.loopiness,
+        DEFAULT_EXIT_DISTANCE,
+    );
+    println!("Exit in chunk: {:?}", infinite_maze.exit_chunk());
+    let start_position = infinite_maze.start_position();
+
+    show_infinite_maze(
+        commands,
+        amazing_data,
+        camera_query,
+        infinite_maze,
+        &start_position,
+    );
+}
+
+// Paint the endless maze around the player, who stands on position
+pub fn show_infinite_maze(
+    commands: &mut Commands,
+    amazing_data: &mut ResMut<AmazinglyLostData>,
+    camera_query: &mut Query<(&mut Transform, &Camera)>,
+    infinite_maze: InfiniteMaze,
+    position: &WorldPosition,
+) {
+    // There is no solution or rating for a maze without an end
+    amazing_data.maze_rating = None;
+    amazing_data.maze_floors = None;
+    amazing_data.cell_maze = None;
+    amazing_data.current_floor = 0;
+    amazing_data.maze_solution = Vec::new();
+    amazing_data.infinite_maze = Some(infinite_maze);
+
+    load_chunks_around(
+        commands,
+        amazing_data,
+        &maze_chunks::chunk_of_position(position),
+    );
+
+    amazing_data.starting_point_sprites = get_world_sprite_position(position);
+    place_camera_on_starting_tile(camera_query, amazing_data);
+    place_player_in_maze(commands, amazing_data);
+}
+
+// Paint the chunks around this chunk that are not painted yet and remove the ones
+// that are too far away
+pub fn load_chunks_around(
+    commands: &mut Commands,
+    amazing_data: &mut ResMut<AmazinglyLostData>,
+    center_chunk: &ChunkPosition,
+) {
+    let infinite_maze = match &amazing_data.infinite_maze {
+        Some(infinite_maze) => infinite_maze.clone(),
+        None => return,
+    };
+    amazing_data.current_chunk = Some(*center_chunk);
+
+    for chunk in maze_chunks::chunks_around(center_chunk, CHUNK_LOAD_RADIUS) {
+        if !amazing_data.loaded_chunks.contains_key(&chunk) {
+            let entities = paint_chunk(commands, &infinite_maze, &chunk, amazing_data);
+            amazing_data.loaded_chunks.insert(chunk, entities);
+        }
+    }
+
+    let far_away_chunks: Vec<ChunkPosition> = amazing_data
+        .loaded_chunks
+        .keys()
+        .filter(|chunk| {
+            (chunk.0 - center_chunk.0).abs() > CHUNK_LOAD_RADIUS + 1
+                || (chunk.1 - center_chunk.1).abs() > CHUNK_LOAD_RADIUS + 1
+        })
+        .copied()
+        .collect();
+    for chunk in far_away_chunks {
+        if let Some(entities) = amazing_data.loaded_chunks.remove(&chunk) {
+            for entity in entities {
+                commands.entity(entity).despawn();
+            }
+        }
+    }
+}
+
+// A chunk owns the WALLS on its west and south side, so we don't paint its last row
+// and column. Those are painted by the chunks next to it.
+fn paint_chunk(
+    commands: &mut Commands,
+    infinite_maze: &InfiniteMaze,
+    chunk: &ChunkPosition,
+    amazing_data: &mut ResMut<AmazinglyLostData>,
+) -> Vec<Entity> {
+    let maze = infinite_maze.generate_chunk(chunk);
+    let (origin_x, origin_y) = maze_chunks::chunk_origin(chunk);
+    // Same decorations every time the chunk is painted again
+    let mut rng = StdRng::seed_from_u64(infinite_maze.chunk_seed(chunk));
+    let mut entities = Vec::<Entity>::new();
+
+    for y in 0..CHUNK_TILES {
+        for x in 0..CHUNK_TILES {
+            if let Some(maze_tile) = maze.get(x, y) {
+                let (pos_x, pos_y) =
+                    get_world_sprite_position(&(origin_x + x as i64, origin_y + y as i64));
+
+                entities.extend(spawn_maze_tile(
+                    commands,
+                    maze_tile,
+                    &(x, y, 0),
+                    &Vec3::new(pos_x, pos_y, 1.0),
+                    amazing_data,
+                    &mut rng,
+                ));
+            }
+        }
+    }
+    entities
+}
+
+// Same as get_sprite_position, but tiles in the endless maze can also be negative
+pub fn get_world_sprite_position(position: &WorldPosition) -> (f32, f32) {
+    (
+        ((position.0 + 1) * SPRITE_SIZE_MAZE as i64) as f32,
+        ((position.1 + 1) * SPRITE_SIZE_MAZE as i64) as f32,
+    )
+}
+
+// Tile in the endless maze that is on this position of the screen
+pub fn get_world_position(translation: &Vec3) -> WorldPosition {
+    (
+        (translation.x / SPRITE_SIZE_MAZE as f32).round() as i64 - 1,
+        (translation.y / SPRITE_SIZE_MAZE as f32).round() as i64 - 1,
+    )
+}
+
+// Hexagonal and triangular mazes, these only have one floor
+fn create_new_cell_maze(
+    commands: &mut Commands,
+    amazing_data: &mut ResMut<AmazinglyLostData>,
+    camera_query: &mut Query<(&mut Transform, &Camera)>,
+    rng: &mut StdRng,
+) {
+    let topology = amazing_data.grid_topology;
+    let (cell_maze, solution) = maze_generator::create_cell_maze(
+        amazing_data.maze_size.0 as usize,
+        amazing_data.maze_size.1 as usize,
+        topology,
+        amazing_data.loopiness,
+        rng,
+    );
+    let starting_point = *solution.first().unwrap();
+
+    show_cell_maze(
+        commands,
+        amazing_data,
+        camera_query,
+        cell_maze,
+        &solution,
+        &starting_point,
+        rng,
+    );
+}
+
+// Paint the whole maze with hexagonal or triangular cells, the player stands on cell
+pub fn show_cell_maze(
+    commands: &mut Commands,
+    amazing_data: &mut ResMut<AmazinglyLostData>,
+    camera_query: &mut Query<(&mut Transform, &Camera)>,
+    cell_maze: CellMaze,
+    solution: &[(usize, usize)],
+    cell: &(usize, usize),
+    rng: &mut StdRng,
+) {
+    // We can only rate a maze with square cells
+    amazing_data.maze_rating = None;
+    amazing_data.maze_floors = None;
+    amazing_data.current_floor = 0;
+
+    paint_the_cell_maze(commands, &cell_maze, solution, amazing_data, rng);
+
+    let exit_point = *solution.last().unwrap();
+    amazing_data.starting_point_sprites = get_cell_position(&cell_maze.topology(), cell);
+    amazing_data.player.cell_position = *cell;
+
+    place_camera_on_starting_tile(camera_query, amazing_data);
+    place_player_in_maze(commands, amazing_data);
+
+    amazing_data.exit_point_game = (exit_point.0, exit_point.1, 0);
+    amazing_data.maze_solution = solution.iter().map(|(x, y)| (*x, *y, 0)).collect();
+    amazing_data.cell_maze = Some(cell_maze);
+}
+
+// Only the floor the player is on is painted. The player stands on (x, y) of that floor.
+pub fn show_floor(
+    commands: &mut Commands,
+    amazing_data: &mut ResMut<AmazinglyLostData>,
+    camera_query: &mut Query<(&mut Transform, &Camera)>,
+    position: &FloorPosition,
+) {
+    let (x, y, floor) = *position;
+    let mut maze = match amazing_data
+        .maze_floors
+        .as_ref()
+        .and_then(|maze_floors| maze_floors.floor(floor))
+    {
+        Some(maze) => maze.clone(),
+        None => return,
+    };
+    amazing_data.current_floor = floor;
+
+    let floor_solution: Vec<(usize, usize)> = amazing_data
+        .maze_solution
+        .iter()
+        .filter(|(_, _, solution_floor)| *solution_floor == floor)
+        .map(|(solution_x, solution_y, _)| (*solution_x, *solution_y))
+        .collect();
+
+    // Same decorations every time we come back to this floor
+    let mut rng = StdRng::seed_from_u64(amazing_data.seed.wrapping_add(floor as u64));
+    paint_the_maze(
+        commands,
+        &floor_solution,
+        &mut maze,
+        floor,
+        amazing_data,
+        &mut rng,
+    );
+    paint_items(commands, amazing_data);
+
+    amazing_data.starting_point_sprites = get_sprite_position(x, y);
+    place_camera_on_starting_tile(camera_query, amazing_data);
+    place_player_in_maze(commands, amazing_data);
+}
+
+// Items lie on the first floor, a level has only one floor
+fn paint_items(commands: &mut Commands, amazing_data: &mut ResMut<AmazinglyLostData>) {
+    if amazing_data.current_floor != 0 {
+        return;
+    }
+
+    for item in amazing_data.items.clone() {
+        let (pos_x, pos_y) = get_sprite_position(item.position.0, item.position.1);
+        // Above the ground of the tile
+        if let Some(mut item_texture_handle) = amazing_data
+            .game_tile_handlers
+            .get_game_item(&Vec3::new(pos_x, pos_y, 1.5))
+        {
+            item_texture_handle.sprite = Sprite::new(Vec2::new(ITEM_SIZE, ITEM_SIZE));
+
+            commands
+                .spawn_bundle(item_texture_handle)
+                .insert(GameTile)
+                .insert(ItemTile(item));
+        }
+    }
+}
+
+// The first tile is painted at SPRITE_SIZE_MAZE, not at 0
+pub fn get_sprite_position(x: usize, y: usize) -> (f32, f32) {
+    (
+        ((x + 1) * SPRITE_SIZE_MAZE) as f32,
+        ((y + 1) * SPRITE_SIZE_MAZE) as f32,
+    )
+}
+
+pub fn paint_the_maze(
+    commands: &mut Commands,
+    solution: &Vec<(usize, usize)>,
+    maze: &mut Maze,
+    floor: usize,
+    amazing_data: &mut ResMut<AmazinglyLostData>,
+    rng: &mut StdRng,
+) {
+    // TODO:RG WALLS also part of solution and dont overpaint
+    for (x, y) in solution {
+        if let Some(maze_tile) = maze.get_mut(*x, *y) {
+            maze_tile.part_of_solution = true;
+        }
+    }
+
+    // z-axis is always 0.0, we don't use depth
+
+    let mut pos_y = SPRITE_SIZE_MAZE as f32; // / 2.0;
+
+    for (y, maze_row) in maze.rows().enumerate() {
+        let mut pos_x = SPRITE_SIZE_MAZE as f32; // / 2.0;
+
+        for (x, maze_tile) in maze_row.iter().enumerate() {
+            spawn_maze_tile(
+                commands,
+                maze_tile,
+                &(x, y, floor),
+                &Vec3::new(pos_x, pos_y, 1.0),
+                amazing_data,
+                rng,
+            );
+
+            pos_x += SPRITE_SIZE_MAZE as f32;
+        }
+        pos_y += SPRITE_SIZE_MAZE as f32;
+    }
+}
+
+// Spawn the sprites for one tile of the maze, position is where it goes on the screen.
+// Gives all entities we spawned, a tile on the solution has two of them.
+pub fn spawn_maze_tile(
+    commands: &mut Commands,
+    maze_tile: &MazeTile,
+    tile_position: &FloorPosition,
+    position: &Vec3,
+    amazing_data: &mut ResMut<AmazinglyLostData>,
+    rng: &mut StdRng,
+) -> Vec<Entity> {
+    let (x, y, floor) = *tile_position;
+    let (pos_x, pos_y) = (position.x, position.y);
+    let mut entities = Vec::<Entity>::new();
+
+    match &maze_tile.tile_type {
+        TileType::Border | TileType::Wall => {
+            if let Some(mut border_texture_handle) = amazing_data
+                .game_tile_handlers
+                .get_random_game_tile(&maze_tile.tile_type, &Vec3::new(pos_x, pos_y, 1.0), rng)
+            {
+                border_texture_handle.sprite =
+                    Sprite::new(Vec2::new(SPRITE_SIZE_MAZE as f32, SPRITE_SIZE_MAZE as f32));
+
+                let flip_it: bool = rng.gen_range(0..2) == 1;
+
+                border_texture_handle.sprite.flip_x = flip_it;
+
+                entities.push(
+                    commands
+                        .spawn_bundle(border_texture_handle.clone())
+                        .insert(GameTile)
+                        .insert(CollisionTile {
+                            collision_type: CollisionType::Wall,
+                        })
+                        .id(),
+                );
+            }
+        }
+        TileType::Start => {
+            if let Some(mut start_texture_handle) = amazing_data
+                .game_tile_handlers
+                .get_game_start(&Vec3::new(pos_x, pos_y, 1.0))
+            {
+                start_texture_handle.sprite =
+                    Sprite::new(Vec2::new(SPRITE_SIZE_MAZE as f32, SPRITE_SIZE_MAZE as f32));
+
+                entities.push(
+                    commands
+                        .spawn_bundle(start_texture_handle.clone())
+                        .insert(GameTile)
+                        .insert(CollisionTile {
+                            collision_type: CollisionType::Start,
+                        })
+                        .id(),
+                );
+            }
+        }
+        TileType::Exit => {
+            if let Some(mut exit_texture_handle) = amazing_data
+                .game_tile_handlers
+                .get_game_exit(&Vec3::new(pos_x, pos_y, 1.0))
+            {
+                exit_texture_handle.sprite =
+                    Sprite::new(Vec2::new(SPRITE_SIZE_MAZE as f32, SPRITE_SIZE_MAZE as f32));
+
+                entities.push(
+                    commands
+                        .spawn_bundle(exit_texture_handle.clone())
+                        .insert(GameTile)
+                        .insert(CollisionTile {
+                            collision_type: CollisionType::Exit,
+                        })
+                        .id(),
+                );
+            }
+        }
+        // Looks like the EXIT, but the player can walk over it
+        TileType::DecoyExit => {
+            if let Some(mut exit_texture_handle) = amazing_data
+                .game_tile_handlers
+                .get_game_exit(&Vec3::new(pos_x, pos_y, 1.0))
+            {
+                exit_texture_handle.sprite =
+                    Sprite::new(Vec2::new(SPRITE_SIZE_MAZE as f32, SPRITE_SIZE_MAZE as f32));
+
+                entities.push(
+                    commands
+                        .spawn_bundle(exit_texture_handle)
+                        .insert(GameTile)
+                        .id(),
+                );
+            }
+        }
+        TileType::LadderUp | TileType::LadderDown => {
+            let destination = if maze_tile.tile_type == TileType::LadderUp {
+                (x, y, floor + 1)
+            } else {
+                (x, y, floor.saturating_sub(1))
+            };
+
+            if let Some(mut ladder_texture_handle) = amazing_data
+                .game_tile_handlers
+                .get_game_ladder(&maze_tile.tile_type, &Vec3::new(pos_x, pos_y, 1.0))
+            {
+                ladder_texture_handle.sprite =
+                    Sprite::new(Vec2::new(SPRITE_SIZE_MAZE as f32, SPRITE_SIZE_MAZE as f32));
+
+                entities.push(
+                    commands
+                        .spawn_bundle(ladder_texture_handle)
+                        .insert(GameTile)
+                        .insert(CollisionTile {
+                            collision_type: CollisionType::Ladder(destination),
+                        })
+                        .id(),
+                );
+            }
+        }
+        TileType::Bridge => {
+            if let Some(mut bridge_texture_handle) = amazing_data
+                .game_tile_handlers
+                .get_game_bridge(&Vec3::new(pos_x, pos_y, 1.0))
+            {
+                bridge_texture_handle.sprite =
+                    Sprite::new(Vec2::new(SPRITE_SIZE_MAZE as f32, SPRITE_SIZE_MAZE as f32));
+
+                let mut bridge_tile = commands.spawn_bundle(bridge_texture_handle.clone());
+                bridge_tile.insert(GameTile).insert(CollisionTile {
+                    collision_type: CollisionType::Bridge,
+                });
+                if maze_tile.part_of_solution {
+                    bridge_tile.insert(SolutionTile);
+                }
+                entities.push(bridge_tile.id());
+
+                // The solution goes over the bridge, the collision stays on the bridge itself
+                if maze_tile.part_of_solution {
+                    let mut solution_texture_handle = bridge_texture_handle;
+                    solution_texture_handle.material =
+                        amazing_data.game_tile_handlers.solution.clone();
+                    solution_texture_handle.visible.is_visible = false;
+                    entities.push(
+                        commands
+                            .spawn_bundle(solution_texture_handle)
+                            .insert(GameTile)
+                            .insert(SolutionTile)
+                            .insert(SolutionPath)
+                            .id(),
+                    );
+                }
+            }
+        }
+        // Rooms get their own ground, get_random_game_tile takes care of that
+        TileType::Open | TileType::Room => {
+            if let Some(mut open_texture_handle) = amazing_data
+                .game_tile_handlers
+                .get_random_game_tile(&maze_tile.tile_type, &Vec3::new(pos_x, pos_y, 1.0), rng)
+            {
+                open_texture_handle.sprite =
+                    Sprite::new(Vec2::new(SPRITE_SIZE_MAZE as f32, SPRITE_SIZE_MAZE as f32));
+
+                if maze_tile.part_of_solution {
+                    // Both get SolutionTile
+                    entities.push(
+                        commands
+                            .spawn_bundle(open_texture_handle.clone())
+                            .insert(GameTile)
+                            .insert(SolutionTile)
+                            .id(),
+                    );
+
+                    // Only different material
+                    let mut solution_texture_handle = open_texture_handle.clone();
+                    solution_texture_handle.material =
+                        amazing_data.game_tile_handlers.solution.clone();
+                    solution_texture_handle.visible.is_visible = false;
+                    entities.push(
+                        commands
+                            .spawn_bundle(solution_texture_handle)
+                            .insert(GameTile)
+                            .insert(SolutionTile)
+                            .insert(SolutionPath)
+                            .id(),
+                    );
+                } else {
+                    // Only GameTile if this is not the solution path
+                    entities.push(
+                        commands
+                            .spawn_bundle(open_texture_handle.clone())
+                            .insert(GameTile)
+                            .id(),
+                    );
+                }
+            }
+        }
+    }
+    entities
+}
+
+// Every cell gets a ground sprite and every closed side of a cell gets a thin wall
+pub fn paint_the_cell_maze(
+    commands: &mut Commands,
+    cell_maze: &CellMaze,
+    solution: &[(usize, usize)],
+    amazing_data: &mut ResMut<AmazinglyLostData>,
+    rng: &mut StdRng,
+) {
+    let topology = cell_maze.topology();
+    // The biggest square that fits in the cell
+    let ground_size = get_cell_apothem(&topology) * 2.0_f32.sqrt();
+
+    for cell in cell_maze.cells() {
+        let (pos_x, pos_y) = get_cell_position(&topology, &cell);
+
+        let ground = if cell == solution[0] {
+            amazing_data
+                .game_tile_handlers
+                .get_game_start(&Vec3::new(pos_x, pos_y, 1.0))
+        } else if Some(&cell) == solution.last() {
+            amazing_data
+                .game_tile_handlers
+                .get_game_exit(&Vec3::new(pos_x, pos_y, 1.0))
+        } else {
+            amazing_data.game_tile_handlers.get_random_game_tile(
+                &TileType::Open,
+                &Vec3::new(pos_x, pos_y, 0.0),
+                rng,
+            )
+        };
+
+        if let Some(mut ground_texture_handle) = ground {
+            ground_texture_handle.sprite = Sprite::new(Vec2::new(ground_size, ground_size));
+
+            if solution.contains(&cell) {
+                commands
+                    .spawn_bundle(ground_texture_handle.clone())
+                    .insert(GameTile)
+                    .insert(SolutionTile);
+
+                let mut solution_texture_handle = ground_texture_handle.clone();
+                solution_texture_handle.material = amazing_data.game_tile_handlers.solution.clone();
+                solution_texture_handle.visible.is_visible = false;
+                commands
+                    .spawn_bundle(solution_texture_handle)
+                    .insert(GameTile)
+                    .insert(SolutionTile)
+                    .insert(SolutionPath);
+            } else {
+                commands
+                    .spawn_bundle(ground_texture_handle)
+                    .insert(GameTile);
+            }
+        }
+
+        for direction in cell_maze.directions(&cell) {
+            // A wall between two cells is painted only once, by the cell with the lowest row
+            // (or column). Sides without another cell are the border of the maze.
+            let paint_wall = match cell_maze.neighbour(&cell, &direction) {
+                Some(next_cell) => {
+                    !cell_maze.is_open(&cell, &direction)
+                        && (cell.1, cell.0) < (next_cell.1, next_cell.0)
+                }
+                None => true,
+            };
+            if !paint_wall {
+                continue;
+            }
+
+            let angle = get_cell_side_angle(&topology, &cell, &direction).to_radians();
+            let apothem = get_cell_apothem(&topology);
+            let wall_position = Vec3::new(
+                pos_x + apothem * angle.cos(),
+                pos_y + apothem * angle.sin(),
+                1.0,
+            );
+
+            if let Some(mut wall_texture_handle) = amazing_data
+                .game_tile_handlers
+                .get_random_game_tile(&TileType::Wall, &wall_position, rng)
+            {
+                wall_texture_handle.sprite = Sprite::new(Vec2::new(
+                    get_cell_side_length(&topology) + CELL_WALL_THICKNESS,
+                    CELL_WALL_THICKNESS,
+                ));
+                // The wall goes along the side, so at a right angle of the direction
+                wall_texture_handle.transform.rotation =
+                    Quat::from_rotation_z(angle + std::f32::consts::FRAC_PI_2);
+
+                commands.spawn_bundle(wall_texture_handle).insert(GameTile);
+            }
+        }
+    }
+}
+
+// Middle of the cell on the screen. The first cell is not painted at 0, just like the tiles.
+pub fn get_cell_position(topology: &GridTopology, cell: &(usize, usize)) -> (f32, f32) {
+    let (column, row) = (cell.0 as f32, cell.1 as f32);
+
+    match topology {
+        GridTopology::Square => (
+            (column + 1.0) * CELL_SIZE_MAZE,
+            (row + 1.0) * CELL_SIZE_MAZE,
+        ),
+        GridTopology::Hexagonal => {
+            // Odd rows are half a cell more to the east, rows overlap for a quarter
+            let shift = if CellMaze::is_odd_row(cell.1) {
+                0.5
+            } else {
+                0.0
+            };
+            let row_height = CELL_SIZE_MAZE * 3.0_f32.sqrt() / 2.0;
+            (
+                (column + 1.0 + shift) * CELL_SIZE_MAZE,
+                (row + 1.0) * row_height,
+            )
+        }
+        GridTopology::Triangular => {
+            // The middle of a triangle is a third of the height from its flat side
+            let row_height = CELL_SIZE_MAZE * 3.0_f32.sqrt() / 2.0;
+            let middle = if CellMaze::is_triangle_up(cell) {
+                row_height / 3.0
+            } else {
+                row_height * 2.0 / 3.0
+            };
+            (
+                (column + 2.0) * CELL_SIZE_MAZE / 2.0,
+                (row + 1.0) * row_height + middle,
+            )
+        }
+    }
+}
+
+// Distance from the middle of a cell to the middle of its sides
+fn get_cell_apothem(topology: &GridTopology) -> f32 {
+    match topology {
+        GridTopology::Square | GridTopology::Hexagonal => CELL_SIZE_MAZE / 2.0,
+        GridTopology::Triangular => CELL_SIZE_MAZE * 3.0_f32.sqrt() / 6.0,
+    }
+}
+
+fn get_cell_side_length(topology: &GridTopology) -> f32 {
+    match topology {
+        GridTopology::Square | GridTopology::Triangular => CELL_SIZE_MAZE,
+        GridTopology::Hexagonal => CELL_SIZE_MAZE / 3.0_f32.sqrt(),
+    }
+}
+
+// Angle in degrees from the middle of the cell to the side in this direction (east is 0)
+fn get_cell_side_angle(
+    topology: &GridTopology,
+    cell: &(usize, usize),
+    direction: &GridDirection,
+) -> f32 {
+    let is_triangle_up = CellMaze::is_triangle_up(cell);
+
+    match (topology, direction) {
+        (GridTopology::Triangular, GridDirection::East) if is_triangle_up => 30.0,
+        (GridTopology::Triangular, GridDirection::West) if is_triangle_up => 150.0,
+        (GridTopology::Triangular, GridDirection::East) => -30.0,
+        (GridTopology::Triangular, GridDirection::West) => 210.0,
+        (_, GridDirection::East) => 0.0,
+        (_, GridDirection::NorthEast) => 60.0,
+        (_, GridDirection::North) => 90.0,
+        (_, GridDirection::NorthWest) => 120.0,
+        (_, GridDirection::West) => 180.0,
+        (_, GridDirection::SouthWest) => 240.0,
+        (_, GridDirection::South) => 270.0,
+        (_, GridDirection::SouthEast) => 300.0,
+    }
+}
+
+fn place_player_in_maze(commands: &mut Commands, amazing_data: &mut ResMut<AmazinglyLostData>) {
+    if let Some(mut player_texture_handle) =
+        amazing_data.game_tile_handlers.get_game_player(&Vec3::new(
+            // Place the player at the starting point
+            amazing_data.starting_point_sprites.0,
+            amazing_data.starting_point_sprites.1,
+            1.0,
+        ))
+    {
+        player_texture_handle.sprite =
+            Sprite::new(Vec2::new(SPRITE_SIZE_MAZE as f32, SPRITE_SIZE_MAZE as f32));
+        player_texture_handle.sprite.flip_x = amazing_data.player.direction.is_eastward();
+        amazing_data.entity_player = commands
+            .spawn_bundle(player_texture_handle)
+            .insert(GameTile)
+            .insert(PlayerTile)
+            .insert(amazing_data.player.clone())
+            .id();
+    }
+}
+
+fn place_camera_on_starting_tile(
+    camera_query: &mut Query<(&mut Transform, &Camera)>,
+    amazing_data: &mut ResMut<AmazinglyLostData>,
+) {
+    for mut camera in camera_query.iter_mut() {
+        // Place Camera on the same x, y as the Player
+        camera.0.translation.x = amazing_data.starting_point_sprites.0;
+        camera.0.translation.y = amazing_data.starting_point_sprites.1;
+    }
+}
+
+pub fn clear_maze_tiles(commands: &mut Commands, game_tile_query: &mut Query<GameTileEntity>) {
+    // Despawn every GameTile, but NOT the camera. Or we will see a black screen
+    for (entity, _game_tile) in game_tile_query.iter_mut() {
+        commands.entity(entity).despawn();
+    }
+}
@@ -0,0 +1,479 @@
+use crate::amazingly_lost_data::{AmazinglyLostData, LightMode, VisitedPosition};
+use crate::game_maze::{self, GameTileEntity, PlayerTile};
+use crate::game_state::{ChangeGameStateEvent, GameState};
+use crate::player::{Directions, Player};
+
+use amazingly_lost::maze_analysis::{self, Difficulty};
+use amazingly_lost::maze_ascii::{self, AsciiError};
+use amazingly_lost::maze_chunks::InfiniteMaze;
+use amazingly_lost::maze_floors::{FloorPosition, MultiFloorMaze};
+use amazingly_lost::maze_generator::MazeAlgorithmType;
+use amazingly_lost::maze_level::LevelItem;
+use amazingly_lost::maze_mask::MazeMask;
+use amazingly_lost::maze_placement::PlacementStrategy;
+use amazingly_lost::maze_rooms::RoomSettings;
+use amazingly_lost::maze_topology::{CellMaze, GridTopology};
+
+use bevy::prelude::*;
+use bevy::render::camera::Camera;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+// In the data directory of the user, e.g. ~/.local/share/amazingly_lost on Linux
+const SAVE_DIR: &str = "amazingly_lost";
+const SAVE_FILE: &str = "save.ron";
+// The shape of the maze is saved as text, one character for every pixel of the mask
+const MASK_CARVABLE: char = '.';
+const MASK_CLOSED: char = '#';
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io(std::io::Error),
+    Ron(ron::Error),
+    // The save has tiles that are not a valid maze
+    Tiles(AsciiError),
+    // The saved shape of the maze has lines of different lengths or unknown characters
+    Mask,
+    // There is no directory for user data on this system
+    NoDataDir,
+    // Nothing was saved yet, or the save is for another kind of maze
+    NoMaze,
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SaveError::Io(error) => write!(f, "Can't read or write the saved game: {}", error),
+            SaveError::Ron(error) => write!(f, "Not a valid saved game: {}", error),
+            SaveError::Tiles(error) => write!(f, "The saved maze is not valid: {}", error),
+            SaveError::Mask => write!(f, "The saved shape of the maze is not valid"),
+            SaveError::NoDataDir => write!(f, "There is no directory to save the game in"),
+            SaveError::NoMaze => write!(f, "There is no maze to save or play again"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
+
+impl From<std::io::Error> for SaveError {
+    fn from(error: std::io::Error) -> Self {
+        SaveError::Io(error)
+    }
+}
+
+impl From<ron::Error> for SaveError {
+    fn from(error: ron::Error) -> Self {
+        SaveError::Ron(error)
+    }
+}
+
+impl From<AsciiError> for SaveError {
+    fn from(error: AsciiError) -> Self {
+        SaveError::Tiles(error)
+    }
+}
+
+// Everything that is needed to go on with a maze later, also after the game was closed.
+// Normal mazes are saved tile by tile (see maze_ascii), so the maze doesn't need to be
+// generated again. The endless maze has no end, so only its seed is saved.
+// The settings the maze was generated with are saved too, so Restart and Replay
+// generate the same maze again.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SaveGame {
+    pub seed: u64,
+    pub maze_size: (u16, u16),
+    pub algorithm: MazeAlgorithmType,
+    pub loopiness: u8,
+    pub theme: String,
+    pub light_mode: LightMode,
+    pub grid_topology: GridTopology,
+    pub room_settings: Option<RoomSettings>,
+    pub placement: PlacementStrategy,
+    pub difficulty: Option<Difficulty>,
+    // Rows of the mask from the top of the image, see MASK_CARVABLE and MASK_CLOSED
+    pub maze_mask: Option<Vec<String>>,
+    // Tiles of every floor, one line for every row from the north side
+    pub floors: Vec<Vec<String>>,
+    // Only for a maze with hexagonal or triangular cells
+    pub cell_maze: Option<CellMaze>,
+    pub is_endless: bool,
+    // In chunks, only for the endless maze
+    pub exit_distance: u32,
+    pub solution: Vec<FloorPosition>,
+    pub exit: FloorPosition,
+    // Tile (or cell) and floor the player is on
+    pub player_position: VisitedPosition,
+    pub facing: Directions,
+    pub play_time: f32,
+    pub steps_walked: usize,
+    pub is_solution_shown: bool,
+    pub visited: Vec<VisitedPosition>,
+    // Older saves have no items and nothing revealed
+    #[serde(default)]
+    pub items: Vec<LevelItem>,
+    #[serde(default)]
+    pub collected_items: Vec<LevelItem>,
+    #[serde(default)]
+    pub revealed: Vec<VisitedPosition>,
+}
+
+impl SaveGame {
+    pub fn from_game(
+        amazing_data: &AmazinglyLostData,
+        player_position: &VisitedPosition,
+        facing: Directions,
+    ) -> Result<Self, SaveError> {
+        let floors: Vec<Vec<String>> = match &amazing_data.maze_floors {
+            Some(maze_floors) => maze_floors
+                .floors()
+                .iter()
+                .map(|maze| {
+                    maze_ascii::maze_to_ascii(maze)
+                        .lines()
+                        .map(String::from)
+                        .collect()
+                })
+                .collect(),
+            None => Vec::new(),
+        };
+        if floors.is_empty()
+            && amazing_data.cell_maze.is_none()
+            && amazing_data.infinite_maze.is_none()
+        {
+            return Err(SaveError::NoMaze);
+        }
+
+        // Sorted, so the same game always gives the same file
+        let mut visited: Vec<VisitedPosition> = amazing_data.visited.iter().copied().collect();
+        visited.sort_unstable();
+        let mut revealed: Vec<VisitedPosition> = amazing_data.revealed.iter().copied().collect();
+        revealed.sort_unstable();
+
+        Ok(Self {
+            seed: amazing_data.seed,
+            maze_size: amazing_data.maze_size,
+            algorithm: amazing_data.maze_algorithm,
+            loopiness: amazing_data.loopiness,
+            theme: amazing_data.theme.clone(),
+            light_mode: amazing_data.light_mode,
+            grid_topology: amazing_data.grid_topology,
+            room_settings: amazing_data.room_settings,
+            placement: amazing_data.placement,
+            difficulty: amazing_data.difficulty,
+            maze_mask: amazing_data.maze_mask.as_ref().map(mask_to_lines),
+            floors,
+            cell_maze: amazing_data.cell_maze.clone(),
+            is_endless: amazing_data.infinite_maze.is_some(),
+            exit_distance: amazing_data
+                .infinite_maze
+                .as_ref()
+                .map_or(0, |infinite_maze| infinite_maze.exit_distance()),
+            solution: amazing_data.maze_solution.clone(),
+            exit: amazing_data.exit_point_game,
+            player_position: *player_position,
+            facing,
+            play_time: amazing_data.play_time,
+            steps_walked: amazing_data.steps_walked,
+            is_solution_shown: amazing_data.is_solution_shown,
+            visited,
+            items: amazing_data.items.clone(),
+            collected_items: amazing_data.collected_items.clone(),
+            revealed,
+        })
+    }
+
+    // None for the endless maze and mazes with hexagonal or triangular cells. The START
+    // and EXIT of a maze with more floors are on different floors, see floors_from_ascii.
+    pub fn maze_floors(&self) -> Result<Option<MultiFloorMaze>, SaveError> {
+        if self.floors.is_empty() {
+            return Ok(None);
+        }
+
+        let floors: Vec<String> = self.floors.iter().map(|tiles| tiles.join("\n")).collect();
+        Ok(Some(maze_ascii::floors_from_ascii(&floors)?))
+    }
+}
+
+pub struct GameSavePlugin;
+
+impl Plugin for GameSavePlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system_set(SystemSet::on_enter(GameState::Save).with_system(save_game.system()))
+            .add_system_set(
+                SystemSet::on_enter(GameState::Win).with_system(remove_finished_save.system()),
+            );
+    }
+}
+
+pub fn get_save_file_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|data_dir| data_dir.join(SAVE_DIR).join(SAVE_FILE))
+}
+
+pub fn has_save_file() -> bool {
+    get_save_file_path().is_some_and(|path| path.exists())
+}
+
+pub fn load_save_file() -> Result<SaveGame, SaveError> {
+    let path = get_save_file_path().ok_or(SaveError::NoDataDir)?;
+
+    Ok(ron::de::from_str(&fs::read_to_string(path)?)?)
+}
+
+pub fn save_save_file(save_game: &SaveGame) -> Result<PathBuf, SaveError> {
+    let path = get_save_file_path().ok_or(SaveError::NoDataDir)?;
+    if let Some(save_dir) = path.parent() {
+        fs::create_dir_all(save_dir)?;
+    }
+
+    fs::write(
+        &path,
+        ron::ser::to_string_pretty(save_game, ron::ser::PrettyConfig::new())?,
+    )?;
+    Ok(path)
+}
+
+// Saves the game and goes back to the pause menu
+fn save_game(
+    player_query: Query<(&Transform, &Player), With<PlayerTile>>,
+    amazing_data: Res<AmazinglyLostData>,
+    mut change_game_state: EventWriter<ChangeGameStateEvent>,
+) {
+    if let Ok((player_transform, player)) = player_query.single() {
+        let save_game = SaveGame::from_game(
+            &amazing_data,
+            &get_player_position(&amazing_data, player_transform, player),
+            player.direction,
+        );
+
+        match save_game.and_then(|save_game| save_save_file(&save_game)) {
+            Ok(path) => println!("Game saved: {}", path.display()),
+            Err(error) => println!("{}", error),
+        }
+    }
+    change_game_state.send(ChangeGameStateEvent(GameState::Pause));
+}
+
+// A player that is walking is saved on the tile (or cell) they walk to
+fn get_player_position(
+    amazing_data: &AmazinglyLostData,
+    player_transform: &Transform,
+    player: &Player,
+) -> VisitedPosition {
+    let (x, y) = if amazing_data.cell_maze.is_some() {
+        let cell = if player.moving == Directions::None {
+            player.cell_position
+        } else {
+            player.next_cell_position
+        };
+        (cell.0 as i64, cell.1 as i64)
+    } else if player.moving == Directions::None {
+        game_maze::get_world_position(&player_transform.translation)
+    } else {
+        game_maze::get_world_position(&Vec3::new(
+            player.next_position_x,
+            player.next_position_y,
+            0.0,
+        ))
+    };
+    (x, y, amazing_data.current_floor)
+}
+
+fn mask_to_lines(maze_mask: &MazeMask) -> Vec<String> {
+    (0..maze_mask.height())
+        .map(|y| {
+            (0..maze_mask.width())
+                .map(|x| {
+                    if maze_mask.is_carvable(x, y) {
+                        MASK_CARVABLE
+                    } else {
+                        MASK_CLOSED
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn mask_from_lines(lines: &[String]) -> Result<MazeMask, SaveError> {
+    let width = lines.first().map_or(0, |line| line.chars().count());
+    if width == 0 || lines.iter().any(|line| line.chars().count() != width) {
+        return Err(SaveError::Mask);
+    }
+
+    let carvable = lines
+        .iter()
+        .flat_map(|line| line.chars())
+        .map(|character| match character {
+            MASK_CARVABLE => Ok(true),
+            MASK_CLOSED => Ok(false),
+            _ => Err(SaveError::Mask),
+        })
+        .collect::<Result<Vec<bool>, SaveError>>()?;
+    Ok(MazeMask::new(width, lines.len(), carvable))
+}
+
+// A finished maze can't be played again from the save
+fn remove_finished_save(amazing_data: Res<AmazinglyLostData>) {
+    let is_finished_maze = load_save_file().is_ok_and(|save_game| {
+        save_game.seed == amazing_data.seed && save_game.solution == amazing_data.maze_solution
+    });
+
+    if is_finished_maze {
+        if let Some(path) = get_save_file_path() {
+            if let Err(error) = fs::remove_file(path) {
+                println!("{}", error);
+            }
+        }
+    }
+}
+
+// Build the maze of the saved game again and put the player where it was, this is used
+// instead of game_maze::create_new_maze
+pub fn resume_saved_game(
+    commands: &mut Commands,
+    amazing_data: &mut ResMut<AmazinglyLostData>,
+    camera_query: &mut Query<(&mut Transform, &Camera)>,
+    game_tile_query: &mut Query<GameTileEntity>,
+    save_game: SaveGame,
+) -> Result<(), SaveError> {
+    let floors = save_game.maze_floors()?;
+    let maze_mask = save_game
+        .maze_mask
+        .as_deref()
+        .map(mask_from_lines)
+        .transpose()?;
+
+    game_maze::clear_maze_tiles(commands, game_tile_query);
+    amazing_data.loaded_chunks.clear();
+    amazing_data.current_chunk = None;
+    amazing_data.infinite_maze = None;
+    amazing_data.cell_maze = None;
+    amazing_data.level = None;
+    amazing_data.items = save_game.items;
+    amazing_data.collected_items = save_game.collected_items;
+
+    amazing_data.seed = save_game.seed;
+    amazing_data.maze_size = save_game.maze_size;
+    amazing_data.maze_algorithm = save_game.algorithm;
+    amazing_data.loopiness = save_game.loopiness;
+    amazing_data.light_mode = save_game.light_mode;
+    amazing_data.grid_topology = save_game.grid_topology;
+    amazing_data.room_settings = save_game.room_settings;
+    amazing_data.placement = save_game.placement;
+    amazing_data.difficulty = save_game.difficulty;
+    amazing_data.maze_mask = maze_mask;
+    amazing_data.is_endless = save_game.is_endless;
+    amazing_data.play_time = save_game.play_time;
+    amazing_data.steps_walked = save_game.steps_walked;
+    amazing_data.is_solution_shown = save_game.is_solution_shown;
+    amazing_data.is_solution_visible = false;
+    amazing_data.visited = save_game.visited.iter().copied().collect();
+    amazing_data.revealed = save_game.revealed.iter().copied().collect();
+    amazing_data.player.direction = save_game.facing;
+    amazing_data.player.moving = Directions::None;
+    amazing_data.player.ladder_destination = None;
+    amazing_data.player.crossing_bridge = None;
+
+    let (player_x, player_y, player_floor) = save_game.player_position;
+    if save_game.is_endless {
+        let infinite_maze = InfiniteMaze::new(
+            save_game.seed,
+            save_game.algorithm,
+            save_game.loopiness,
+            save_game.exit_distance,
+        );
+        game_maze::show_infinite_maze(
+            commands,
+            amazing_data,
+            camera_query,
+            infinite_maze,
+            &(player_x, player_y),
+        );
+    } else if let Some(cell_maze) = save_game.cell_maze {
+        let solution: Vec<(usize, usize)> = save_game
+            .solution
+            .iter()
+            .map(|(x, y, _)| (*x, *y))
+            .collect();
+        // Same decorations for the same seed
+        let mut rng = StdRng::seed_from_u64(save_game.seed);
+        game_maze::show_cell_maze(
+            commands,
+            amazing_data,
+            camera_query,
+            cell_maze,
+            &solution,
+            &(player_x as usize, player_y as usize),
+            &mut rng,
+        );
+    } else if let Some(floors) = floors {
+        amazing_data.floor_count = floors.floor_count();
+        amazing_data.maze_rating = match floors.floors() {
+            // We can only rate a maze with one floor
+            [maze] => {
+                let solution: Vec<(usize, usize)> = save_game
+                    .solution
+                    .iter()
+                    .map(|(x, y, _)| (*x, *y))
+                    .collect();
+                Some(maze_analysis::analyse_maze(maze, &solution))
+            }
+            _ => None,
+        };
+        amazing_data.maze_floors = Some(floors);
+        amazing_data.maze_solution = save_game.solution;
+        amazing_data.exit_point_game = save_game.exit;
+        game_maze::show_floor(
+            commands,
+            amazing_data,
+            camera_query,
+            &(player_x as usize, player_y as usize, player_floor),
+        );
+    } else {
+        return Err(SaveError::NoMaze);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use amazingly_lost::maze_generator;
+
+    #[test]
+    fn multi_floor_maze_survives_save() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let (maze, solution) = maze_generator::create_multi_floor_maze(
+            15,
+            15,
+            3,
+            &None,
+            &MazeAlgorithmType::RecursiveBacktracker,
+            0,
+            &mut rng,
+        );
+        let mut amazing_data = AmazinglyLostData::new();
+        amazing_data.floor_count = maze.floor_count();
+        amazing_data.maze_floors = Some(maze.clone());
+        amazing_data.exit_point_game = *solution.last().unwrap();
+        amazing_data.maze_solution = solution;
+        amazing_data.collected_items = vec![LevelItem {
+            name: "key".to_string(),
+            position: (1, 1),
+        }];
+        amazing_data.revealed = [(1, 1, 0), (1, 2, 1)].iter().copied().collect();
+
+        let save_game = SaveGame::from_game(&amazing_data, &(1, 1, 0), Directions::North).unwrap();
+        let ron = ron::ser::to_string_pretty(&save_game, ron::ser::PrettyConfig::new()).unwrap();
+        let loaded: SaveGame = ron::de::from_str(&ron).unwrap();
+
+        assert_eq!(loaded.maze_floors().unwrap(), Some(maze));
+        assert_eq!(loaded.solution, amazing_data.maze_solution);
+        assert_eq!(loaded.collected_items, amazing_data.collected_items);
+        assert_eq!(loaded.revealed, vec![(1, 1, 0), (1, 2, 1)]);
+    }
+}
@@ -0,0 +1,423 @@
+use crate::amazingly_lost_data::{LightMode, DEFAULT_THEME};
+
+use amazingly_lost::maze_generator::MazeSizePreset;
+use bevy::prelude::*;
+use bevy::window::WindowMode;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::PathBuf;
+
+// In the config directory of the user, e.g. ~/.config/amazingly_lost on Linux
+const SETTINGS_DIR: &str = "amazingly_lost";
+const SETTINGS_FILE: &str = "settings.ron";
+
+// Resolutions to choose from in the settings menu, the settings file can have any other one
+pub const RESOLUTIONS: [(u32, u32); 5] = [
+    (1280, 720),
+    (1440, 900),
+    (1600, 900),
+    (1920, 1080),
+    (2560, 1440),
+];
+pub const MAX_VOLUME: u8 = 100;
+const VOLUME_STEP: u8 = 10;
+// Radius of the light of the torch in tiles, for the Night, Fog and Inverted light modes
+const MIN_LIGHT_RADIUS: f32 = 2.0;
+const MAX_LIGHT_RADIUS: f32 = 8.0;
+const DEFAULT_LIGHT_RADIUS: f32 = 3.5;
+
+#[derive(Debug)]
+pub enum SettingsError {
+    Io(std::io::Error),
+    Ron(ron::Error),
+    // There is no directory for config files on this system
+    NoConfigDir,
+    // The key and the two actions it is used for
+    KeyConflict(KeyCode, &'static str, &'static str),
+}
+
+impl fmt::Display for SettingsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SettingsError::Io(error) => write!(f, "Can't read or write the settings: {}", error),
+            SettingsError::Ron(error) => write!(f, "Not a valid settings file: {}", error),
+            SettingsError::NoConfigDir => {
+                write!(f, "There is no directory to save the settings in")
+            }
+            SettingsError::KeyConflict(key, action, other_action) => write!(
+                f,
+                "The key {:?} is used for {} and for {}",
+                key, action, other_action
+            ),
+        }
+    }
+}
+
+impl std::error::Error for SettingsError {}
+
+impl From<std::io::Error> for SettingsError {
+    fn from(error: std::io::Error) -> Self {
+        SettingsError::Io(error)
+    }
+}
+
+impl From<ron::Error> for SettingsError {
+    fn from(error: ron::Error) -> Self {
+        SettingsError::Ron(error)
+    }
+}
+
+// Bevy's WindowMode can't be saved, so we have our own
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum ScreenMode {
+    Windowed,
+    BorderlessFullscreen,
+    // Fullscreen with the resolution from the settings
+    Fullscreen,
+}
+
+impl ScreenMode {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScreenMode::Windowed => "Windowed",
+            ScreenMode::BorderlessFullscreen => "Borderless fullscreen",
+            ScreenMode::Fullscreen => "Fullscreen",
+        }
+    }
+
+    // Used to go through all screen modes in the settings menu
+    pub fn next(&self) -> Self {
+        match self {
+            ScreenMode::Windowed => ScreenMode::BorderlessFullscreen,
+            ScreenMode::BorderlessFullscreen => ScreenMode::Fullscreen,
+            ScreenMode::Fullscreen => ScreenMode::Windowed,
+        }
+    }
+
+    pub fn to_window_mode(self) -> WindowMode {
+        match self {
+            ScreenMode::Windowed => WindowMode::Windowed,
+            ScreenMode::BorderlessFullscreen => WindowMode::BorderlessFullscreen,
+            ScreenMode::Fullscreen => WindowMode::Fullscreen { use_size: true },
+        }
+    }
+}
+
+// Keys for the game itself, every action can have more than one key.
+// The menus always use the arrow keys, Enter and Esc. There is no key to quit,
+// that is only done in the start and pause menu so a maze is never left by accident.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct KeyBindings {
+    pub north: Vec<KeyCode>,
+    pub south: Vec<KeyCode>,
+    pub east: Vec<KeyCode>,
+    pub west: Vec<KeyCode>,
+    // Only for hexagonal cells, these have no neighbour to the north or south
+    pub north_west: Vec<KeyCode>,
+    pub north_east: Vec<KeyCode>,
+    pub south_west: Vec<KeyCode>,
+    pub south_east: Vec<KeyCode>,
+    pub pause: Vec<KeyCode>,
+    pub new_maze: Vec<KeyCode>,
+    pub show_solution: Vec<KeyCode>,
+    pub zoom_in: Vec<KeyCode>,
+    pub zoom_out: Vec<KeyCode>,
+    // The same seed with other settings, so we can see the difference
+    pub next_algorithm: Vec<KeyCode>,
+    pub more_loops: Vec<KeyCode>,
+    pub less_loops: Vec<KeyCode>,
+    pub next_cell_shape: Vec<KeyCode>,
+    pub toggle_rooms: Vec<KeyCode>,
+    pub next_placement: Vec<KeyCode>,
+    pub toggle_endless: Vec<KeyCode>,
+    pub more_floors: Vec<KeyCode>,
+    // A new seed
+    pub next_difficulty: Vec<KeyCode>,
+    pub print_maze: Vec<KeyCode>,
+    pub save_level: Vec<KeyCode>,
+    // Size of the maze, these also make a new seed
+    pub bigger_maze: Vec<KeyCode>,
+    pub smaller_maze: Vec<KeyCode>,
+    pub wider_maze: Vec<KeyCode>,
+    pub narrower_maze: Vec<KeyCode>,
+    pub taller_maze: Vec<KeyCode>,
+    pub lower_maze: Vec<KeyCode>,
+    pub small_maze: Vec<KeyCode>,
+    pub medium_maze: Vec<KeyCode>,
+    pub large_maze: Vec<KeyCode>,
+    pub very_very_large_maze: Vec<KeyCode>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        Self {
+            north: vec![KeyCode::Up, KeyCode::W],
+            south: vec![KeyCode::Down, KeyCode::S],
+            east: vec![KeyCode::Right, KeyCode::D],
+            west: vec![KeyCode::Left, KeyCode::A],
+            // Around W, A, S and D, so the same hand can walk in all directions
+            north_west: vec![KeyCode::Q],
+            north_east: vec![KeyCode::E],
+            south_west: vec![KeyCode::Z],
+            south_east: vec![KeyCode::X],
+            pause: vec![KeyCode::Escape],
+            new_maze: vec![KeyCode::N],
+            show_solution: vec![KeyCode::P],
+            zoom_in: vec![KeyCode::I],
+            zoom_out: vec![KeyCode::O],
+            next_algorithm: vec![KeyCode::G],
+            more_loops: vec![KeyCode::RBracket],
+            less_loops: vec![KeyCode::LBracket],
+            next_cell_shape: vec![KeyCode::T],
+            toggle_rooms: vec![KeyCode::R],
+            next_placement: vec![KeyCode::K],
+            toggle_endless: vec![KeyCode::U],
+            more_floors: vec![KeyCode::F],
+            next_difficulty: vec![KeyCode::H],
+            print_maze: vec![KeyCode::C],
+            save_level: vec![KeyCode::L],
+            bigger_maze: vec![KeyCode::PageUp],
+            smaller_maze: vec![KeyCode::PageDown],
+            wider_maze: vec![KeyCode::End],
+            narrower_maze: vec![KeyCode::Home],
+            taller_maze: vec![KeyCode::Insert],
+            lower_maze: vec![KeyCode::Delete],
+            small_maze: vec![KeyCode::Key1],
+            medium_maze: vec![KeyCode::Key2],
+            large_maze: vec![KeyCode::Key3],
+            very_very_large_maze: vec![KeyCode::Key4],
+        }
+    }
+}
+
+impl KeyBindings {
+    // Every action with its name (as in the settings file) and keys
+    fn actions(&self) -> Vec<(&'static str, &[KeyCode])> {
+        vec![
+            ("north", &self.north),
+            ("south", &self.south),
+            ("east", &self.east),
+            ("west", &self.west),
+            ("north_west", &self.north_west),
+            ("north_east", &self.north_east),
+            ("south_west", &self.south_west),
+            ("south_east", &self.south_east),
+            ("pause", &self.pause),
+            ("new_maze", &self.new_maze),
+            ("show_solution", &self.show_solution),
+            ("zoom_in", &self.zoom_in),
+            ("zoom_out", &self.zoom_out),
+            ("next_algorithm", &self.next_algorithm),
+            ("more_loops", &self.more_loops),
+            ("less_loops", &self.less_loops),
+            ("next_cell_shape", &self.next_cell_shape),
+            ("toggle_rooms", &self.toggle_rooms),
+            ("next_placement", &self.next_placement),
+            ("toggle_endless", &self.toggle_endless),
+            ("more_floors", &self.more_floors),
+            ("next_difficulty", &self.next_difficulty),
+            ("print_maze", &self.print_maze),
+            ("save_level", &self.save_level),
+            ("bigger_maze", &self.bigger_maze),
+            ("smaller_maze", &self.smaller_maze),
+            ("wider_maze", &self.wider_maze),
+            ("narrower_maze", &self.narrower_maze),
+            ("taller_maze", &self.taller_maze),
+            ("lower_maze", &self.lower_maze),
+            ("small_maze", &self.small_maze),
+            ("medium_maze", &self.medium_maze),
+            ("large_maze", &self.large_maze),
+            ("very_very_large_maze", &self.very_very_large_maze),
+        ]
+    }
+
+    // A key can only be used for one action, so one key press never does two things
+    pub fn check_conflicts(&self) -> Result<(), SettingsError> {
+        let actions = self.actions();
+
+        for (index, (action, keys)) in actions.iter().enumerate() {
+            for (other_action, other_keys) in &actions[index + 1..] {
+                if let Some(key) = keys.iter().find(|key| other_keys.contains(key)) {
+                    return Err(SettingsError::KeyConflict(*key, action, other_action));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Read at the start of the game and saved when they are changed in the settings menu.
+// Missing values in the file get their default value.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GameSettings {
+    pub screen_mode: ScreenMode,
+    pub resolution: (u32, u32),
+    pub vsync: bool,
+    // Volume of the sounds in %
+    pub volume: u8,
+    pub theme: String,
+    // Size of the first maze
+    pub maze_size: (u16, u16),
+    pub light_mode: LightMode,
+    // In tiles, a cell of a hexagonal or triangular maze is about two tiles
+    pub light_radius: f32,
+    pub key_bindings: KeyBindings,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            screen_mode: ScreenMode::Windowed,
+            resolution: (1440, 900),
+            vsync: true,
+            volume: MAX_VOLUME,
+            theme: DEFAULT_THEME.to_string(),
+            maze_size: MazeSizePreset::Small.get_size(),
+            light_mode: LightMode::Day,
+            light_radius: DEFAULT_LIGHT_RADIUS,
+            key_bindings: KeyBindings::default(),
+        }
+    }
+}
+
+impl GameSettings {
+    pub fn next_resolution(&mut self) {
+        let next = RESOLUTIONS
+            .iter()
+            .position(|(width, _)| *width == self.resolution.0)
+            .map_or(0, |index| (index + 1) % RESOLUTIONS.len());
+        self.resolution = RESOLUTIONS[next];
+    }
+
+    // Louder until the maximum, then back to no sound
+    pub fn next_volume(&mut self) {
+        self.volume = if self.volume >= MAX_VOLUME {
+            0
+        } else {
+            (self.volume + VOLUME_STEP).min(MAX_VOLUME)
+        };
+    }
+
+    // A bigger light until the maximum, then back to the smallest one
+    pub fn next_light_radius(&mut self) {
+        self.light_radius = if self.light_radius >= MAX_LIGHT_RADIUS {
+            MIN_LIGHT_RADIUS
+        } else {
+            (self.light_radius.floor() + 1.0).clamp(MIN_LIGHT_RADIUS, MAX_LIGHT_RADIUS)
+        };
+    }
+
+    pub fn next_maze_size(&mut self) {
+        self.maze_size = MazeSizePreset::from_size(self.maze_size)
+            .map_or(MazeSizePreset::Small, |size_preset| size_preset.next())
+            .get_size();
+    }
+}
+
+pub struct GameSettingsPlugin;
+
+impl Plugin for GameSettingsPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.add_system(apply_changed_settings.system());
+    }
+}
+
+pub fn get_settings_file_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|config_dir| config_dir.join(SETTINGS_DIR).join(SETTINGS_FILE))
+}
+
+// Default settings when there is no settings file yet.
+// A file with a key that is used for two actions gets the default keys.
+pub fn load_settings_file() -> Result<GameSettings, SettingsError> {
+    let path = get_settings_file_path().ok_or(SettingsError::NoConfigDir)?;
+    if !path.exists() {
+        return Ok(GameSettings::default());
+    }
+
+    let mut game_settings: GameSettings = ron::de::from_str(&fs::read_to_string(path)?)?;
+    // The other settings in the file are still used
+    if let Err(error) = game_settings.key_bindings.check_conflicts() {
+        println!("{}", error);
+        game_settings.key_bindings = KeyBindings::default();
+    }
+    Ok(game_settings)
+}
+
+pub fn save_settings_file(game_settings: &GameSettings) -> Result<PathBuf, SettingsError> {
+    let path = get_settings_file_path().ok_or(SettingsError::NoConfigDir)?;
+    if let Some(settings_dir) = path.parent() {
+        fs::create_dir_all(settings_dir)?;
+    }
+
+    fs::write(
+        &path,
+        ron::ser::to_string_pretty(game_settings, ron::ser::PrettyConfig::new())?,
+    )?;
+    Ok(path)
+}
+
+pub fn is_pressed(keyboard_input: &Input<KeyCode>, keys: &[KeyCode]) -> bool {
+    keys.iter().any(|key| keyboard_input.pressed(*key))
+}
+
+pub fn is_just_pressed(keyboard_input: &Input<KeyCode>, keys: &[KeyCode]) -> bool {
+    keys.iter().any(|key| keyboard_input.just_pressed(*key))
+}
+
+// The window gets the new settings and the settings file is written again.
+// The first time the settings are already used for the window, so there is nothing to do.
+fn apply_changed_settings(
+    game_settings: Res<GameSettings>,
+    mut windows: ResMut<Windows>,
+    mut is_started: Local<bool>,
+) {
+    if !*is_started {
+        *is_started = true;
+        return;
+    }
+    if !game_settings.is_changed() {
+        return;
+    }
+
+    if let Some(window) = windows.get_primary_mut() {
+        let (width, height) = game_settings.resolution;
+        window.set_mode(game_settings.screen_mode.to_window_mode());
+        window.set_resolution(width as f32, height as f32);
+        window.set_vsync(game_settings.vsync);
+    }
+
+    match save_settings_file(&game_settings) {
+        Ok(path) => println!("Settings saved: {}", path.display()),
+        Err(error) => println!("{}", error),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_keys_are_used_once() {
+        assert!(KeyBindings::default().check_conflicts().is_ok());
+    }
+
+    #[test]
+    fn key_for_two_actions_is_a_conflict() {
+        let key_bindings = KeyBindings {
+            north_west: vec![KeyCode::W],
+            ..KeyBindings::default()
+        };
+
+        assert!(matches!(
+            key_bindings.check_conflicts(),
+            Err(SettingsError::KeyConflict(
+                KeyCode::W,
+                "north",
+                "north_west"
+            ))
+        ));
+    }
+}
@@ -26,12 +26,12 @@ pub enum GameState {
     StartMenu,
     // Story,
     PlayingGame,
-    // Pause,
-    // Win,
+    Pause,
+    Win,
     Settings,
     Save,
     // About,
     GenerateNewGame,
-    // Loading,
+    Loading,
     LoadingAssets,
 }
@@ -1,3 +1,752 @@
-// TODO:RG show a timer?
-// or maybe buttons to activate certain buffs -> more light, teleportation, things like that
-// Menus?
+use crate::amazingly_lost_data::AmazinglyLostData;
+use crate::game_settings::{self, GameSettings};
+use crate::game_state::{ChangeGameStateEvent, GameState};
+use crate::sound_factory::{PlaySoundEvent, Sound};
+use crate::{game_save, tile_factory, GAME_TITLE};
+
+use amazingly_lost::maze_generator::MazeSizePreset;
+use bevy::app::AppExit;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+// TODO:RG buttons to activate certain buffs -> more light, teleportation, things like that
+
+pub const MENU_FONT: &str = "fonts/DejaVuSansMono-Bold.ttf";
+const TITLE_FONT_SIZE: f32 = 56.0;
+const TEXT_FONT_SIZE: f32 = 26.0;
+const BUTTON_FONT_SIZE: f32 = 26.0;
+// Wide enough for the longest algorithm name
+const BUTTON_WIDTH: f32 = 640.0;
+const BUTTON_HEIGHT: f32 = 48.0;
+
+// What a button in a menu does
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MenuAction {
+    NewGame,
+    // Back to the maze we left for the start menu, or the saved game
+    Continue,
+    // These change what the next maze looks like, the menu stays open
+    SizePreset,
+    Algorithm,
+    Theme,
+    LightMode,
+    Resume,
+    SaveGame,
+    // Same seed, so the same maze again
+    Restart,
+    NewMaze,
+    // Same as Restart and NewMaze, after the maze is finished
+    Replay,
+    NextMaze,
+    Settings,
+    // Settings for the window and the defaults of the game, saved in the settings file
+    ScreenMode,
+    Resolution,
+    Volume,
+    DefaultSize,
+    DefaultTheme,
+    DefaultLight,
+    LightRadius,
+    // Back to the menu we came from
+    Back,
+    MainMenu,
+    Quit,
+}
+
+impl MenuAction {
+    pub fn label(&self) -> &'static str {
+        match self {
+            MenuAction::NewGame => "New game",
+            MenuAction::Continue => "Continue",
+            MenuAction::SizePreset => "Size",
+            MenuAction::Algorithm => "Algorithm",
+            MenuAction::Theme => "Theme",
+            MenuAction::LightMode => "Light",
+            MenuAction::Resume => "Resume",
+            MenuAction::SaveGame => "Save game",
+            MenuAction::Restart => "Restart this maze",
+            MenuAction::NewMaze => "New maze",
+            MenuAction::Replay => "Play this maze again",
+            MenuAction::NextMaze => "Next maze",
+            MenuAction::Settings => "Settings",
+            MenuAction::ScreenMode => "Screen",
+            MenuAction::Resolution => "Resolution",
+            MenuAction::Volume => "Volume",
+            MenuAction::DefaultSize => "Default size",
+            MenuAction::DefaultTheme => "Default theme",
+            MenuAction::DefaultLight => "Default light",
+            MenuAction::LightRadius => "Light radius",
+            MenuAction::Back => "Back",
+            MenuAction::MainMenu => "Main menu",
+            MenuAction::Quit => "Quit",
+        }
+    }
+
+    pub fn is_option(&self) -> bool {
+        matches!(
+            self,
+            MenuAction::SizePreset
+                | MenuAction::Algorithm
+                | MenuAction::Theme
+                | MenuAction::LightMode
+                | MenuAction::ScreenMode
+                | MenuAction::Resolution
+                | MenuAction::Volume
+                | MenuAction::DefaultSize
+                | MenuAction::DefaultTheme
+                | MenuAction::DefaultLight
+                | MenuAction::LightRadius
+        )
+    }
+}
+
+// The dark layer over the game with the menu on it, removed when we leave the state
+pub struct MenuOverlay;
+
+pub struct MenuButton {
+    pub action: MenuAction,
+    pub index: usize,
+}
+
+// Text on a button, options show their current value
+pub struct MenuButtonText(pub MenuAction);
+
+// Which button is selected with the keyboard (or the mouse is on)
+pub struct MenuSelection {
+    pub selected: usize,
+    pub button_count: usize,
+    // State we go back to when leaving the settings
+    pub settings_return_state: GameState,
+    // State we go back to with Continue in the start menu, None if there is no game
+    pub continue_state: Option<GameState>,
+}
+
+impl Default for MenuSelection {
+    fn default() -> Self {
+        Self {
+            selected: 0,
+            button_count: 0,
+            settings_return_state: GameState::Pause,
+            continue_state: None,
+        }
+    }
+}
+
+pub struct MenuMaterials {
+    overlay: Handle<ColorMaterial>,
+    button: Handle<ColorMaterial>,
+    selected_button: Handle<ColorMaterial>,
+}
+
+impl FromWorld for MenuMaterials {
+    fn from_world(world: &mut World) -> Self {
+        let mut materials = world.get_resource_mut::<Assets<ColorMaterial>>().unwrap();
+
+        Self {
+            overlay: materials.add(Color::rgba(0.0, 0.0, 0.0, 0.75).into()),
+            button: materials.add(Color::rgb(0.15, 0.15, 0.2).into()),
+            selected_button: materials.add(Color::rgb(0.35, 0.3, 0.1).into()),
+        }
+    }
+}
+
+pub struct GameUiPlugin;
+
+impl Plugin for GameUiPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        app.init_resource::<MenuSelection>()
+            .init_resource::<MenuMaterials>()
+            .add_system(menu_input.system())
+            .add_system(update_button_texts.system())
+            .add_system_set(
+                SystemSet::on_enter(GameState::StartMenu).with_system(spawn_start_menu.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::StartMenu).with_system(despawn_menu.system()),
+            )
+            .add_system_set(
+                SystemSet::on_enter(GameState::Pause).with_system(spawn_pause_menu.system()),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Pause).with_system(despawn_menu.system()))
+            .add_system_set(
+                SystemSet::on_enter(GameState::Win).with_system(spawn_win_menu.system()),
+            )
+            .add_system_set(SystemSet::on_exit(GameState::Win).with_system(despawn_menu.system()))
+            .add_system_set(
+                SystemSet::on_enter(GameState::Settings).with_system(spawn_settings_menu.system()),
+            )
+            .add_system_set(
+                SystemSet::on_exit(GameState::Settings).with_system(despawn_menu.system()),
+            );
+    }
+}
+
+fn spawn_start_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    menu_materials: Res<MenuMaterials>,
+    mut menu_selection: ResMut<MenuSelection>,
+    amazing_data: Res<AmazinglyLostData>,
+    game_settings: Res<GameSettings>,
+) {
+    let lines = vec![
+        "Walk with the arrow keys or W, A, S and D".to_string(),
+        "P shows the way out, Esc pauses the game".to_string(),
+    ];
+    let mut actions = vec![
+        MenuAction::NewGame,
+        MenuAction::SizePreset,
+        MenuAction::Algorithm,
+        MenuAction::Theme,
+        MenuAction::LightMode,
+        MenuAction::Settings,
+        MenuAction::Quit,
+    ];
+    if menu_selection.continue_state.is_some() || game_save::has_save_file() {
+        actions.insert(0, MenuAction::Continue);
+    }
+
+    spawn_menu(
+        &mut commands,
+        &asset_server,
+        &menu_materials,
+        &mut menu_selection,
+        GAME_TITLE,
+        &lines,
+        &get_menu_buttons(&actions, &amazing_data, &game_settings),
+    );
+}
+
+fn spawn_pause_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    menu_materials: Res<MenuMaterials>,
+    mut menu_selection: ResMut<MenuSelection>,
+    amazing_data: Res<AmazinglyLostData>,
+    game_settings: Res<GameSettings>,
+) {
+    let lines = vec![
+        format!("Seed: {}", amazing_data.seed),
+        format!("Time: {}", format_play_time(amazing_data.play_time)),
+    ];
+    spawn_menu(
+        &mut commands,
+        &asset_server,
+        &menu_materials,
+        &mut menu_selection,
+        "Paused",
+        &lines,
+        &get_menu_buttons(
+            &[
+                MenuAction::Resume,
+                MenuAction::SaveGame,
+                MenuAction::Restart,
+                MenuAction::NewMaze,
+                MenuAction::Settings,
+                MenuAction::MainMenu,
+                MenuAction::Quit,
+            ],
+            &amazing_data,
+            &game_settings,
+        ),
+    );
+}
+
+fn spawn_win_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    menu_materials: Res<MenuMaterials>,
+    mut menu_selection: ResMut<MenuSelection>,
+    amazing_data: Res<AmazinglyLostData>,
+    game_settings: Res<GameSettings>,
+) {
+    let mut lines = vec![
+        format!("Time: {}", format_play_time(amazing_data.play_time)),
+        format!("Steps: {}", amazing_data.steps_walked),
+    ];
+    // The endless maze has no solution to compare with
+    match get_optimal_step_count(&amazing_data) {
+        Some(optimal_steps) => {
+            lines.push(format!("Shortest way: {} steps", optimal_steps));
+            lines.push(format!(
+                "Efficiency: {:.0}%",
+                get_efficiency(optimal_steps, amazing_data.steps_walked)
+            ));
+        }
+        None => lines.push("Shortest way: unknown".to_string()),
+    }
+    lines.push(format!(
+        "Solution used: {}",
+        if amazing_data.is_solution_shown {
+            "yes"
+        } else {
+            "no"
+        }
+    ));
+
+    spawn_menu(
+        &mut commands,
+        &asset_server,
+        &menu_materials,
+        &mut menu_selection,
+        "You found the exit!",
+        &lines,
+        &get_menu_buttons(
+            &[
+                MenuAction::NextMaze,
+                MenuAction::Replay,
+                MenuAction::MainMenu,
+                MenuAction::Quit,
+            ],
+            &amazing_data,
+            &game_settings,
+        ),
+    );
+}
+
+fn spawn_settings_menu(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    menu_materials: Res<MenuMaterials>,
+    mut menu_selection: ResMut<MenuSelection>,
+    amazing_data: Res<AmazinglyLostData>,
+    game_settings: Res<GameSettings>,
+) {
+    // The keys can only be changed in the settings file
+    let lines = match game_settings::get_settings_file_path() {
+        Some(path) => vec![
+            "Keys can be changed in the settings file:".to_string(),
+            path.display().to_string(),
+        ],
+        None => Vec::new(),
+    };
+    spawn_menu(
+        &mut commands,
+        &asset_server,
+        &menu_materials,
+        &mut menu_selection,
+        "Settings",
+        &lines,
+        &get_menu_buttons(
+            &[
+                MenuAction::ScreenMode,
+                MenuAction::Resolution,
+                MenuAction::Volume,
+                MenuAction::DefaultSize,
+                MenuAction::DefaultTheme,
+                MenuAction::DefaultLight,
+                MenuAction::LightRadius,
+                MenuAction::Back,
+            ],
+            &amazing_data,
+            &game_settings,
+        ),
+    );
+}
+
+fn get_menu_buttons(
+    actions: &[MenuAction],
+    amazing_data: &AmazinglyLostData,
+    game_settings: &GameSettings,
+) -> Vec<(MenuAction, String)> {
+    actions
+        .iter()
+        .map(|action| {
+            (
+                *action,
+                get_button_text(action, amazing_data, game_settings),
+            )
+        })
+        .collect()
+}
+
+// The label of the button, with the current value for an option
+fn get_button_text(
+    action: &MenuAction,
+    amazing_data: &AmazinglyLostData,
+    game_settings: &GameSettings,
+) -> String {
+    let value = match action {
+        MenuAction::SizePreset => get_size_name(amazing_data.maze_size),
+        MenuAction::Algorithm => amazing_data
+            .maze_algorithm
+            .get_algorithm()
+            .name()
+            .to_string(),
+        MenuAction::Theme => amazing_data.theme.clone(),
+        MenuAction::LightMode => amazing_data.light_mode.name().to_string(),
+        MenuAction::ScreenMode => game_settings.screen_mode.name().to_string(),
+        MenuAction::Resolution => format!(
+            "{}x{}",
+            game_settings.resolution.0, game_settings.resolution.1
+        ),
+        MenuAction::Volume => format!("{}%", game_settings.volume),
+        MenuAction::DefaultSize => get_size_name(game_settings.maze_size),
+        MenuAction::DefaultTheme => game_settings.theme.clone(),
+        MenuAction::DefaultLight => game_settings.light_mode.name().to_string(),
+        MenuAction::LightRadius => format!("{} tiles", game_settings.light_radius),
+        _ => return action.label().to_string(),
+    };
+    format!("{}: {}", action.label(), value)
+}
+
+fn get_size_name(maze_size: (u16, u16)) -> String {
+    match MazeSizePreset::from_size(maze_size) {
+        Some(size_preset) => size_preset.name().to_string(),
+        None => format!("{}x{}", maze_size.0, maze_size.1),
+    }
+}
+
+// A title, some lines of text and a button for every action, from top to bottom
+pub fn spawn_menu(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    menu_materials: &MenuMaterials,
+    menu_selection: &mut MenuSelection,
+    title: &str,
+    lines: &[String],
+    buttons: &[(MenuAction, String)],
+) {
+    let font: Handle<Font> = asset_server.load(MENU_FONT);
+    menu_selection.selected = 0;
+    menu_selection.button_count = buttons.len();
+
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                position_type: PositionType::Absolute,
+                // Column reverse puts the first child at the top
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            material: menu_materials.overlay.clone(),
+            ..Default::default()
+        })
+        .insert(MenuOverlay)
+        .with_children(|parent| {
+            parent.spawn_bundle(get_text_bundle(title, &font, TITLE_FONT_SIZE));
+
+            for line in lines {
+                parent.spawn_bundle(get_text_bundle(line, &font, TEXT_FONT_SIZE));
+            }
+
+            for (index, (action, text)) in buttons.iter().enumerate() {
+                let material = if index == 0 {
+                    menu_materials.selected_button.clone()
+                } else {
+                    menu_materials.button.clone()
+                };
+
+                parent
+                    .spawn_bundle(ButtonBundle {
+                        style: Style {
+                            size: Size::new(Val::Px(BUTTON_WIDTH), Val::Px(BUTTON_HEIGHT)),
+                            margin: Rect::all(Val::Px(6.0)),
+                            justify_content: JustifyContent::Center,
+                            align_items: AlignItems::Center,
+                            ..Default::default()
+                        },
+                        material,
+                        ..Default::default()
+                    })
+                    .insert(MenuButton {
+                        action: *action,
+                        index,
+                    })
+                    .with_children(|button| {
+                        button
+                            .spawn_bundle(get_text_bundle(text, &font, BUTTON_FONT_SIZE))
+                            .insert(MenuButtonText(*action));
+                    });
+            }
+        });
+}
+
+fn get_text_bundle(text: &str, font: &Handle<Font>, font_size: f32) -> TextBundle {
+    TextBundle {
+        style: Style {
+            margin: Rect::all(Val::Px(6.0)),
+            ..Default::default()
+        },
+        text: Text::with_section(
+            text,
+            TextStyle {
+                font: font.clone(),
+                font_size,
+                color: Color::WHITE,
+            },
+            Default::default(),
+        ),
+        ..Default::default()
+    }
+}
+
+pub fn despawn_menu(mut commands: Commands, overlay_query: Query<Entity, With<MenuOverlay>>) {
+    for overlay in overlay_query.iter() {
+        commands.entity(overlay).despawn_recursive();
+    }
+}
+
+// Everything a button in a menu can change
+#[derive(SystemParam)]
+pub struct MenuContext<'a> {
+    amazing_data: ResMut<'a, AmazinglyLostData>,
+    game_settings: ResMut<'a, GameSettings>,
+    game_state: Res<'a, State<GameState>>,
+    change_game_state: EventWriter<'a, ChangeGameStateEvent>,
+    exit: EventWriter<'a, AppExit>,
+    play_sound: EventWriter<'a, PlaySoundEvent>,
+}
+
+// Up and down (or W and S) to select a button, Enter or Space to use it.
+// The mouse selects the button it is on and a click uses it.
+fn menu_input(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut menu_selection: ResMut<MenuSelection>,
+    menu_materials: Res<MenuMaterials>,
+    mut button_query: Query<(&MenuButton, &Interaction, &mut Handle<ColorMaterial>)>,
+    changed_interaction_query: Query<(&MenuButton, &Interaction), Changed<Interaction>>,
+    mut menu_context: MenuContext,
+) {
+    if menu_selection.button_count == 0 || button_query.iter_mut().next().is_none() {
+        return;
+    }
+
+    let mut chosen_action = None;
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        chosen_action = get_escape_action(menu_context.game_state.current(), &menu_selection);
+    } else if keyboard_input.just_pressed(KeyCode::Up) || keyboard_input.just_pressed(KeyCode::W) {
+        menu_selection.selected = (menu_selection.selected + menu_selection.button_count - 1)
+            % menu_selection.button_count;
+    } else if keyboard_input.just_pressed(KeyCode::Down) || keyboard_input.just_pressed(KeyCode::S)
+    {
+        menu_selection.selected = (menu_selection.selected + 1) % menu_selection.button_count;
+    }
+
+    for (menu_button, interaction) in changed_interaction_query.iter() {
+        match interaction {
+            Interaction::Hovered => menu_selection.selected = menu_button.index,
+            Interaction::Clicked => chosen_action = Some(menu_button.action),
+            Interaction::None => {}
+        }
+    }
+
+    for (menu_button, _, mut material) in button_query.iter_mut() {
+        let is_selected = menu_button.index == menu_selection.selected;
+        if is_selected
+            && (keyboard_input.just_pressed(KeyCode::Return)
+                || keyboard_input.just_pressed(KeyCode::Space))
+        {
+            chosen_action = Some(menu_button.action);
+        }
+
+        *material = if is_selected {
+            menu_materials.selected_button.clone()
+        } else {
+            menu_materials.button.clone()
+        };
+    }
+
+    if let Some(action) = chosen_action {
+        // Only once, the menu is removed when the state changes
+        if !action.is_option() {
+            menu_selection.button_count = 0;
+        }
+        use_menu_action(&action, &mut menu_selection, &mut menu_context);
+    }
+}
+
+// Esc goes back to where we were before the menu
+fn get_escape_action(game_state: &GameState, menu_selection: &MenuSelection) -> Option<MenuAction> {
+    match game_state {
+        GameState::Pause => Some(MenuAction::Resume),
+        GameState::Settings => Some(MenuAction::Back),
+        GameState::StartMenu if menu_selection.continue_state.is_some() => {
+            Some(MenuAction::Continue)
+        }
+        _ => None,
+    }
+}
+
+// Options show their new value after they are changed
+fn update_button_texts(
+    amazing_data: Res<AmazinglyLostData>,
+    game_settings: Res<GameSettings>,
+    mut button_text_query: Query<(&MenuButtonText, &mut Text)>,
+) {
+    for (button_text, mut text) in button_text_query.iter_mut() {
+        let new_text = get_button_text(&button_text.0, &amazing_data, &game_settings);
+        // Only a changed text needs to be drawn again
+        if text.sections[0].value != new_text {
+            text.sections[0].value = new_text;
+        }
+    }
+}
+
+fn use_menu_action(
+    action: &MenuAction,
+    menu_selection: &mut MenuSelection,
+    menu_context: &mut MenuContext,
+) {
+    let MenuContext {
+        amazing_data,
+        game_settings,
+        game_state,
+        change_game_state,
+        exit,
+        play_sound,
+    } = menu_context;
+    let current_state = game_state.current();
+    // Also after a changed volume, so the new volume can be heard
+    play_sound.send(PlaySoundEvent(Sound::MenuButton));
+
+    match action {
+        MenuAction::NewGame => {
+            // The first maze uses the seed (or level) from the command line
+            if amazing_data.has_maze() {
+                amazing_data.new_seed();
+            }
+            menu_selection.continue_state = None;
+            change_game_state.send(ChangeGameStateEvent(GameState::GenerateNewGame));
+        }
+        MenuAction::Continue => {
+            if let Some(continue_state) = menu_selection.continue_state.take() {
+                change_game_state.send(ChangeGameStateEvent(continue_state));
+            } else {
+                // The maze is built again from the save in game_flow
+                match game_save::load_save_file() {
+                    Ok(save_game) => {
+                        amazing_data.saved_game = Some(save_game);
+                        change_game_state.send(ChangeGameStateEvent(GameState::Loading));
+                    }
+                    Err(error) => println!("{}", error),
+                }
+            }
+        }
+        MenuAction::SizePreset => {
+            amazing_data.maze_size = MazeSizePreset::from_size(amazing_data.maze_size)
+                .map_or(MazeSizePreset::Small, |size_preset| size_preset.next())
+                .get_size();
+        }
+        MenuAction::Algorithm => {
+            amazing_data.maze_algorithm = amazing_data.maze_algorithm.next();
+        }
+        MenuAction::Theme => {
+            // The textures of the new theme are loaded for the next maze (see game_flow)
+            if let Some(theme_name) = tile_factory::get_next_theme(&amazing_data.theme) {
+                amazing_data.theme = theme_name;
+            }
+        }
+        MenuAction::LightMode => {
+            amazing_data.light_mode = amazing_data.light_mode.next();
+        }
+        MenuAction::Resume => {
+            change_game_state.send(ChangeGameStateEvent(GameState::PlayingGame));
+        }
+        MenuAction::SaveGame => {
+            change_game_state.send(ChangeGameStateEvent(GameState::Save));
+        }
+        MenuAction::Restart | MenuAction::Replay => {
+            change_game_state.send(ChangeGameStateEvent(GameState::GenerateNewGame));
+        }
+        MenuAction::NewMaze | MenuAction::NextMaze => {
+            amazing_data.new_seed();
+            change_game_state.send(ChangeGameStateEvent(GameState::GenerateNewGame));
+        }
+        MenuAction::Settings => {
+            menu_selection.settings_return_state = *current_state;
+            change_game_state.send(ChangeGameStateEvent(GameState::Settings));
+        }
+        // A changed setting is saved and used for the window in game_settings.
+        // The defaults are also used for the next maze.
+        MenuAction::ScreenMode => {
+            game_settings.screen_mode = game_settings.screen_mode.next();
+        }
+        MenuAction::Resolution => {
+            game_settings.next_resolution();
+        }
+        MenuAction::Volume => {
+            game_settings.next_volume();
+        }
+        MenuAction::DefaultSize => {
+            game_settings.next_maze_size();
+            amazing_data.maze_size = game_settings.maze_size;
+        }
+        MenuAction::DefaultTheme => {
+            if let Some(theme_name) = tile_factory::get_next_theme(&game_settings.theme) {
+                game_settings.theme = theme_name;
+                amazing_data.theme = game_settings.theme.clone();
+            }
+        }
+        MenuAction::DefaultLight => {
+            game_settings.light_mode = game_settings.light_mode.next();
+            amazing_data.light_mode = game_settings.light_mode;
+        }
+        MenuAction::LightRadius => {
+            game_settings.next_light_radius();
+        }
+        MenuAction::Back => {
+            change_game_state.send(ChangeGameStateEvent(menu_selection.settings_return_state));
+        }
+        MenuAction::MainMenu => {
+            // After a pause we go on playing, after a win we see the results again
+            menu_selection.continue_state = Some(match current_state {
+                GameState::Win => GameState::Win,
+                _ => GameState::PlayingGame,
+            });
+            change_game_state.send(ChangeGameStateEvent(GameState::StartMenu));
+        }
+        MenuAction::Quit => {
+            println!("Exit Game");
+            exit.send(AppExit);
+        }
+    }
+}
+
+// Steps the player needs when going straight to the exit, counted the same way as
+// steps_walked: taking a ladder is not a step, going over a bridge is.
+fn get_optimal_step_count(amazing_data: &AmazinglyLostData) -> Option<usize> {
+    let solution = &amazing_data.maze_solution;
+    if solution.len() < 2 {
+        return None;
+    }
+
+    // Cells are always next to each other in the solution
+    if amazing_data.cell_maze.is_some() {
+        return Some(solution.len() - 1);
+    }
+
+    // Every tile is in the solution, also a BRIDGE, so only the ladders are not a step
+    let step_count = solution
+        .windows(2)
+        .filter(|step| step[0].2 == step[1].2)
+        .count();
+    Some(step_count)
+}
+
+// 100% is the shortest way, walking around makes it lower
+fn get_efficiency(optimal_steps: usize, steps_walked: usize) -> f32 {
+    if steps_walked == 0 {
+        return 100.0;
+    }
+    (optimal_steps as f32 / steps_walked as f32 * 100.0).min(100.0)
+}
+
+// Minutes and seconds, hours only when it took that long
+pub fn format_play_time(seconds: f32) -> String {
+    let seconds = seconds as u64;
+
+    if seconds >= 3600 {
+        format!(
+            "{}:{:02}:{:02}",
+            seconds / 3600,
+            seconds / 60 % 60,
+            seconds % 60
+        )
+    } else {
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    }
+}
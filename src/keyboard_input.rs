@@ -1,21 +1,27 @@
 use crate::player;
 use crate::{amazingly_lost_data::AmazinglyLostData, player::Player};
 
+use crate::game_maze::{CollisionTile, PlayerTile};
+use crate::game_settings::{self, GameSettings, KeyBindings};
 use crate::game_state::{ChangeGameStateEvent, GameState};
-use crate::maze_generator::{
-    CollisionTile, GameTile, PlayerTile, SolutionTile, SMALL_MAZE, VERY_VERY_LARGE_MAZE,
-};
+use amazingly_lost::maze_analysis::Difficulty;
+use amazingly_lost::maze_ascii;
+use amazingly_lost::maze_braid::MAX_LOOPINESS;
+use amazingly_lost::maze_floors::MAX_FLOORS;
+use amazingly_lost::maze_generator::{self, MazeSizePreset, SMALL_MAZE, VERY_VERY_LARGE_MAZE};
+use amazingly_lost::maze_level::{self, MazeLevel};
+use amazingly_lost::maze_rooms::RoomSettings;
+use amazingly_lost::maze_topology::GridTopology;
 
 use crate::player::ChangeDirectionEvent;
 
-use bevy::app::AppExit;
-
 use bevy::prelude::*;
 use bevy::render::camera::Camera;
 use bevy::window::WindowResized;
 use player::Directions;
 
-const MAZE_SIZE_SCALING: u16 = 33u16;
+const MAZE_SIZE_SCALING: i32 = 33;
+const LOOPINESS_STEP: u8 = 10u8;
 
 // For now we use the same value for frustum culling and zoom
 const MAX_ZOOM_FRUSTUM: f32 = 25.0f32;
@@ -41,59 +47,170 @@ pub fn keyboard_input_game(
         &mut Player,
         (With<PlayerTile>, (Without<CollisionTile>, Without<Camera>)),
     )>,
-    mut mazetile_query: Query<(&mut Visible, (With<GameTile>, With<SolutionTile>))>,
     mut amazing_data: ResMut<AmazinglyLostData>,
+    game_settings: Res<GameSettings>,
     game_state: Res<State<GameState>>,
     mut change_game_state: EventWriter<ChangeGameStateEvent>,
     mut change_direction: EventWriter<ChangeDirectionEvent>,
-    mut exit: EventWriter<AppExit>,
 ) {
+    let key_bindings = &game_settings.key_bindings;
     // Only when playing a game and the player is NOT already moving
     if *game_state.current() == GameState::PlayingGame {
-        check_for_player_movement(&keyboard_input, &mut player_query, &mut change_direction);
+        if amazing_data.grid_topology == GridTopology::Hexagonal {
+            check_for_player_movement_hexagonal(
+                &keyboard_input,
+                key_bindings,
+                &mut player_query,
+                &mut change_direction,
+            );
+        } else {
+            check_for_player_movement(
+                &keyboard_input,
+                key_bindings,
+                &mut player_query,
+                &mut change_direction,
+            );
+        }
 
-        if keyboard_input.just_pressed(KeyCode::N) {
+        if game_settings::is_just_pressed(&keyboard_input, &key_bindings.new_maze) {
             println!("New Game");
+            amazing_data.new_seed();
             change_game_state.send(ChangeGameStateEvent(GameState::GenerateNewGame));
-        } else if keyboard_input.just_pressed(KeyCode::P) {
-            for (mut maze_tile_sprite, _) in mazetile_query.iter_mut() {
-                // Hide current GameTiles and show Solution GameTiles or the other way around
-                maze_tile_sprite.is_visible = !maze_tile_sprite.is_visible;
-            }
-        } else if keyboard_input.just_pressed(KeyCode::Q)
-            || keyboard_input.just_pressed(KeyCode::Escape)
-        {
-            println!("Exit Game");
-            exit.send(AppExit);
-        } else if keyboard_input.just_pressed(KeyCode::PageUp) {
-            if amazing_data.maze_size.0 < VERY_VERY_LARGE_MAZE {
-                println!("Bigger maze");
-                amazing_data.maze_size = (
-                    amazing_data.maze_size.0 + MAZE_SIZE_SCALING,
-                    amazing_data.maze_size.1 + MAZE_SIZE_SCALING,
-                );
-                println!(
-                    "SIZE: {:?}{:?}",
-                    amazing_data.maze_size,
-                    amazing_data.maze_size.0 as usize * amazing_data.maze_size.1 as usize
-                );
+        } else if game_settings::is_just_pressed(&keyboard_input, &key_bindings.next_algorithm) {
+            // Same seed with another algorithm, so we can see the difference
+            amazing_data.maze_algorithm = amazing_data.maze_algorithm.next();
+            println!(
+                "Algorithm: {}",
+                amazing_data.maze_algorithm.get_algorithm().name()
+            );
+            change_game_state.send(ChangeGameStateEvent(GameState::GenerateNewGame));
+        } else if game_settings::is_just_pressed(&keyboard_input, &key_bindings.more_loops) {
+            // More loops, same seed so we can see the difference
+            if amazing_data.loopiness < MAX_LOOPINESS {
+                amazing_data.loopiness =
+                    (amazing_data.loopiness + LOOPINESS_STEP).min(MAX_LOOPINESS);
+                println!("Loops: {}%", amazing_data.loopiness);
                 change_game_state.send(ChangeGameStateEvent(GameState::GenerateNewGame));
             }
-        } else if keyboard_input.just_pressed(KeyCode::PageDown) {
-            if amazing_data.maze_size.0 > SMALL_MAZE {
-                println!("Smaller maze");
-                amazing_data.maze_size = (
-                    amazing_data.maze_size.0 - MAZE_SIZE_SCALING,
-                    amazing_data.maze_size.1 - MAZE_SIZE_SCALING,
-                );
-                println!(
-                    "SIZE: {:?}{:?}",
-                    amazing_data.maze_size,
-                    amazing_data.maze_size.0 as usize * amazing_data.maze_size.1 as usize
-                );
+        } else if game_settings::is_just_pressed(&keyboard_input, &key_bindings.less_loops) {
+            // Less loops, 0% gives a perfect maze
+            if amazing_data.loopiness > 0 {
+                amazing_data.loopiness = amazing_data.loopiness.saturating_sub(LOOPINESS_STEP);
+                println!("Loops: {}%", amazing_data.loopiness);
                 change_game_state.send(ChangeGameStateEvent(GameState::GenerateNewGame));
             }
-        } else if keyboard_input.just_pressed(KeyCode::O) {
+        } else if game_settings::is_just_pressed(&keyboard_input, &key_bindings.next_cell_shape) {
+            // Next cell shape, same seed so we can see the difference
+            amazing_data.grid_topology = amazing_data.grid_topology.next();
+            println!("Cells: {}", amazing_data.grid_topology.name());
+            change_game_state.send(ChangeGameStateEvent(GameState::GenerateNewGame));
+        } else if game_settings::is_just_pressed(&keyboard_input, &key_bindings.toggle_rooms) {
+            // Rooms with corridors or a normal maze (same seed)
+            amazing_data.room_settings = match amazing_data.room_settings {
+                Some(_) => None,
+                None => Some(RoomSettings::default()),
+            };
+            println!("Rooms: {}", amazing_data.room_settings.is_some());
+            change_game_state.send(ChangeGameStateEvent(GameState::GenerateNewGame));
+        } else if game_settings::is_just_pressed(&keyboard_input, &key_bindings.next_placement) {
+            // Next way to place the START and EXIT (same seed)
+            amazing_data.placement = amazing_data.placement.next();
+            println!("Placement: {}", amazing_data.placement.name());
+            change_game_state.send(ChangeGameStateEvent(GameState::GenerateNewGame));
+        } else if game_settings::is_just_pressed(&keyboard_input, &key_bindings.print_maze) {
+            // The floor we are on as text, to paste it somewhere or to change it by hand
+            match &amazing_data.maze_floors {
+                Some(maze_floors) if amazing_data.cell_maze.is_none() => {
+                    if let Some(maze) = maze_floors.floor(amazing_data.current_floor) {
+                        println!("{}", maze_ascii::maze_to_ascii(maze));
+                    }
+                }
+                _ => println!("Only a maze with square cells can be written as text"),
+            }
+        } else if game_settings::is_just_pressed(&keyboard_input, &key_bindings.save_level) {
+            // Save the maze as a level, to play it again with --level
+            save_level(&amazing_data);
+        } else if game_settings::is_just_pressed(&keyboard_input, &key_bindings.toggle_endless) {
+            // Endless maze or a normal maze (same seed)
+            amazing_data.is_endless = !amazing_data.is_endless;
+            println!("Endless: {}", amazing_data.is_endless);
+            change_game_state.send(ChangeGameStateEvent(GameState::GenerateNewGame));
+        } else if game_settings::is_just_pressed(&keyboard_input, &key_bindings.more_floors) {
+            // One floor more, after the last one we go back to one floor (same seed)
+            amazing_data.floor_count = amazing_data.floor_count % MAX_FLOORS + 1;
+            println!("Floors: {}", amazing_data.floor_count);
+            change_game_state.send(ChangeGameStateEvent(GameState::GenerateNewGame));
+        } else if game_settings::is_just_pressed(&keyboard_input, &key_bindings.next_difficulty) {
+            // Try to get a new maze with the next difficulty
+            amazing_data.difficulty = Difficulty::next(&amazing_data.difficulty);
+            match &amazing_data.difficulty {
+                Some(difficulty) => println!("Difficulty: {}", difficulty.name()),
+                None => println!("Difficulty: any"),
+            }
+            amazing_data.new_seed();
+            change_game_state.send(ChangeGameStateEvent(GameState::GenerateNewGame));
+        } else if game_settings::is_just_pressed(&keyboard_input, &key_bindings.show_solution) {
+            // Shown once is enough to count as help when the maze is finished
+            amazing_data.is_solution_shown = true;
+            // The solution tiles are shown or hidden in game_light, only in the light
+            amazing_data.is_solution_visible = !amazing_data.is_solution_visible;
+        } else if game_settings::is_just_pressed(&keyboard_input, &key_bindings.pause) {
+            // Movement and the play time stop until the player resumes
+            change_game_state.send(ChangeGameStateEvent(GameState::Pause));
+        } else if game_settings::is_just_pressed(&keyboard_input, &key_bindings.bigger_maze) {
+            // Bigger maze
+            change_maze_size(
+                &mut amazing_data,
+                MAZE_SIZE_SCALING,
+                MAZE_SIZE_SCALING,
+                &mut change_game_state,
+            );
+        } else if game_settings::is_just_pressed(&keyboard_input, &key_bindings.smaller_maze) {
+            // Smaller maze
+            change_maze_size(
+                &mut amazing_data,
+                -MAZE_SIZE_SCALING,
+                -MAZE_SIZE_SCALING,
+                &mut change_game_state,
+            );
+        } else if game_settings::is_just_pressed(&keyboard_input, &key_bindings.wider_maze) {
+            // Wider maze
+            change_maze_size(
+                &mut amazing_data,
+                MAZE_SIZE_SCALING,
+                0,
+                &mut change_game_state,
+            );
+        } else if game_settings::is_just_pressed(&keyboard_input, &key_bindings.narrower_maze) {
+            // Narrower maze
+            change_maze_size(
+                &mut amazing_data,
+                -MAZE_SIZE_SCALING,
+                0,
+                &mut change_game_state,
+            );
+        } else if game_settings::is_just_pressed(&keyboard_input, &key_bindings.taller_maze) {
+            // Taller maze
+            change_maze_size(
+                &mut amazing_data,
+                0,
+                MAZE_SIZE_SCALING,
+                &mut change_game_state,
+            );
+        } else if game_settings::is_just_pressed(&keyboard_input, &key_bindings.lower_maze) {
+            // Lower maze
+            change_maze_size(
+                &mut amazing_data,
+                0,
+                -MAZE_SIZE_SCALING,
+                &mut change_game_state,
+            );
+        } else if let Some(size_preset) = get_pressed_size_preset(&keyboard_input, key_bindings) {
+            println!("{} maze", size_preset.name());
+            amazing_data.maze_size = size_preset.get_size();
+            amazing_data.new_seed();
+            change_game_state.send(ChangeGameStateEvent(GameState::GenerateNewGame));
+        } else if game_settings::is_just_pressed(&keyboard_input, &key_bindings.zoom_out) {
             // Zoom out
             for (mut transform, _) in camera_query.iter_mut() {
                 if transform.scale.x + 1f32 < MAX_ZOOM_FRUSTUM {
@@ -102,7 +219,7 @@ pub fn keyboard_input_game(
                     transform.scale.z = transform.scale.z + STEP_ZOOM_FRUSTUM;
                 }
             }
-        } else if keyboard_input.just_pressed(KeyCode::I) {
+        } else if game_settings::is_just_pressed(&keyboard_input, &key_bindings.zoom_in) {
             // Zoom in
             for (mut transform, _) in camera_query.iter_mut() {
                 if transform.scale.x - 1f32 >= MIN_ZOOM_FRUSTUM {
@@ -116,8 +233,56 @@ pub fn keyboard_input_game(
     }
 }
 
+// Change the width and/or height of the maze, but stay between the smallest and the biggest maze
+fn change_maze_size(
+    amazing_data: &mut ResMut<AmazinglyLostData>,
+    width_change: i32,
+    height_change: i32,
+    change_game_state: &mut EventWriter<ChangeGameStateEvent>,
+) {
+    let (width, height) = amazing_data.maze_size;
+    let new_width =
+        (width as i32 + width_change).clamp(SMALL_MAZE as i32, VERY_VERY_LARGE_MAZE as i32);
+    let new_height =
+        (height as i32 + height_change).clamp(SMALL_MAZE as i32, VERY_VERY_LARGE_MAZE as i32);
+    let (new_width, new_height) =
+        maze_generator::get_valid_maze_size(new_width as usize, new_height as usize);
+
+    // Already at the smallest or biggest size
+    if (new_width as u16, new_height as u16) == amazing_data.maze_size {
+        return;
+    }
+
+    amazing_data.maze_size = (new_width as u16, new_height as u16);
+    println!(
+        "SIZE: {:?}{:?}",
+        amazing_data.maze_size,
+        new_width * new_height
+    );
+    amazing_data.new_seed();
+    change_game_state.send(ChangeGameStateEvent(GameState::GenerateNewGame));
+}
+
+fn get_pressed_size_preset(
+    keyboard_input: &Res<Input<KeyCode>>,
+    key_bindings: &KeyBindings,
+) -> Option<MazeSizePreset> {
+    if game_settings::is_just_pressed(keyboard_input, &key_bindings.small_maze) {
+        Some(MazeSizePreset::Small)
+    } else if game_settings::is_just_pressed(keyboard_input, &key_bindings.medium_maze) {
+        Some(MazeSizePreset::Medium)
+    } else if game_settings::is_just_pressed(keyboard_input, &key_bindings.large_maze) {
+        Some(MazeSizePreset::Large)
+    } else if game_settings::is_just_pressed(keyboard_input, &key_bindings.very_very_large_maze) {
+        Some(MazeSizePreset::VeryVeryLarge)
+    } else {
+        None
+    }
+}
+
 fn check_for_player_movement(
     keyboard_input: &Res<Input<KeyCode>>,
+    key_bindings: &KeyBindings,
     player_query: &mut Query<(
         &mut Player,
         (With<PlayerTile>, (Without<CollisionTile>, Without<Camera>)),
@@ -129,15 +294,86 @@ fn check_for_player_movement(
     if let Ok((player, _filters)) = player_query.single_mut() {
         // We only want to check if a key is pressed if we don't move from tile to tile
         if player.moving == Directions::None {
-            if keyboard_input.pressed(KeyCode::Up) || keyboard_input.pressed(KeyCode::W) {
+            if game_settings::is_pressed(keyboard_input, &key_bindings.north) {
                 change_direction.send(ChangeDirectionEvent(Directions::North));
-            } else if keyboard_input.pressed(KeyCode::Down) || keyboard_input.pressed(KeyCode::S) {
+            } else if game_settings::is_pressed(keyboard_input, &key_bindings.south) {
                 change_direction.send(ChangeDirectionEvent(Directions::South));
-            } else if keyboard_input.pressed(KeyCode::Right) || keyboard_input.pressed(KeyCode::D) {
+            } else if game_settings::is_pressed(keyboard_input, &key_bindings.east) {
                 change_direction.send(ChangeDirectionEvent(Directions::East));
-            } else if keyboard_input.pressed(KeyCode::Left) || keyboard_input.pressed(KeyCode::A) {
+            } else if game_settings::is_pressed(keyboard_input, &key_bindings.west) {
                 change_direction.send(ChangeDirectionEvent(Directions::West));
             }
         }
     }
 }
+
+// Hexagonal cells have no neighbour to the north or south, only diagonally.
+// By default W and E go up, Z and X go down (see KeyBindings).
+fn check_for_player_movement_hexagonal(
+    keyboard_input: &Res<Input<KeyCode>>,
+    key_bindings: &KeyBindings,
+    player_query: &mut Query<(
+        &mut Player,
+        (With<PlayerTile>, (Without<CollisionTile>, Without<Camera>)),
+    )>,
+    change_direction: &mut EventWriter<ChangeDirectionEvent>,
+) {
+    if let Ok((player, _filters)) = player_query.single_mut() {
+        // We only want to check if a key is pressed if we don't move from cell to cell
+        if player.moving == Directions::None {
+            if game_settings::is_pressed(keyboard_input, &key_bindings.north_west) {
+                change_direction.send(ChangeDirectionEvent(Directions::NorthWest));
+            } else if game_settings::is_pressed(keyboard_input, &key_bindings.north_east) {
+                change_direction.send(ChangeDirectionEvent(Directions::NorthEast));
+            } else if game_settings::is_pressed(keyboard_input, &key_bindings.south_west) {
+                change_direction.send(ChangeDirectionEvent(Directions::SouthWest));
+            } else if game_settings::is_pressed(keyboard_input, &key_bindings.south_east) {
+                change_direction.send(ChangeDirectionEvent(Directions::SouthEast));
+            } else if game_settings::is_pressed(keyboard_input, &key_bindings.east) {
+                change_direction.send(ChangeDirectionEvent(Directions::East));
+            } else if game_settings::is_pressed(keyboard_input, &key_bindings.west) {
+                change_direction.send(ChangeDirectionEvent(Directions::West));
+            }
+        }
+    }
+}
+
+// Only a maze with square cells and one floor can be saved as a level
+fn save_level(amazing_data: &AmazinglyLostData) {
+    let maze = match &amazing_data.maze_floors {
+        Some(maze_floors) if amazing_data.cell_maze.is_none() && maze_floors.floor_count() == 1 => {
+            maze_floors.floor(0)
+        }
+        _ => None,
+    };
+
+    if let Some(maze) = maze {
+        let solution: Vec<(usize, usize)> = amazing_data
+            .maze_solution
+            .iter()
+            .map(|(x, y, _)| (*x, *y))
+            .collect();
+        let mut level = MazeLevel::from_maze(
+            maze,
+            &solution,
+            amazing_data.seed,
+            amazing_data.maze_algorithm,
+            &amazing_data.theme,
+        );
+        // Also the items that were picked up, they are back when the level is played again
+        level.items = amazing_data
+            .items
+            .iter()
+            .chain(amazing_data.collected_items.iter())
+            .cloned()
+            .collect();
+        let path = format!("amazingly_lost_{}.ron", amazing_data.seed);
+
+        match maze_level::save_level_file(&level, &path) {
+            Ok(()) => println!("Level saved: {}", path),
+            Err(error) => println!("{}", error),
+        }
+    } else {
+        println!("Only a maze with square cells and one floor can be saved as a level");
+    }
+}
@@ -0,0 +1,19 @@
+// The maze itself without anything from Bevy.
+// The game uses this to create its mazes, but it can also be used to generate
+// and check mazes without a window (e.g. on a build server).
+pub mod maze;
+pub mod maze_algorithms;
+pub mod maze_analysis;
+pub mod maze_ascii;
+pub mod maze_braid;
+pub mod maze_chunks;
+pub mod maze_floors;
+pub mod maze_generator;
+pub mod maze_image;
+pub mod maze_level;
+pub mod maze_mask;
+pub mod maze_placement;
+pub mod maze_rooms;
+pub mod maze_solver;
+pub mod maze_tile;
+pub mod maze_topology;
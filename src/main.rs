@@ -2,30 +2,49 @@ use bevy::prelude::*;
 use bevy::render::camera::ScalingMode;
 mod amazingly_lost_data;
 mod game_flow;
+mod game_light;
+mod game_maze;
+mod game_save;
+mod game_settings;
 mod game_state;
 mod game_ui;
 mod keyboard_input;
-mod maze_generator;
-mod maze_tile;
 mod player;
+mod sound_factory;
 mod tile_factory;
 
 use crate::game_flow::GameFlowPlugin;
+use crate::game_light::GameLightPlugin;
+use crate::game_maze::ChangeFloorEvent;
+use crate::game_save::GameSavePlugin;
+use crate::game_settings::{GameSettings, GameSettingsPlugin};
 use crate::game_state::{ChangeGameStateEvent, ChangeGameStatePlugin, GameState};
+use crate::game_ui::GameUiPlugin;
 
 use crate::player::{ChangeDirectionEvent, ChangeDirectionPlugin};
+use crate::sound_factory::SoundPlugin;
+use amazingly_lost::maze_ascii;
+use amazingly_lost::maze_level::{self, MazeLevel};
+use amazingly_lost::maze_mask::MazeMask;
 use amazingly_lost_data::AmazinglyLostData;
 
 use bevy::sprite::SpriteSettings;
 use keyboard_input::KeyboardInputPlugin;
+use std::env;
 
-const GAME_TITLE: &str = "Amazingly Lost";
+pub const GAME_TITLE: &str = "Amazingly Lost";
 
 fn main() {
+    let game_settings = game_settings::load_settings_file().unwrap_or_else(|error| {
+        println!("{}", error);
+        GameSettings::default()
+    });
+
     // Resources first
     App::build()
-        .insert_resource(window_descriptor())
-        .insert_resource(initialize_game_data())
+        .insert_resource(window_descriptor(&game_settings))
+        .insert_resource(initialize_game_data(&game_settings))
+        .insert_resource(game_settings)
         .insert_resource(ClearColor(Color::BLACK))
         // Enabling frustum culling can be removed if it's default in Bevy
         .insert_resource(SpriteSettings {
@@ -36,32 +55,91 @@ fn main() {
         .add_plugin(ChangeGameStatePlugin)
         .add_plugin(ChangeDirectionPlugin)
         .add_plugin(KeyboardInputPlugin)
+        .add_plugin(GameUiPlugin)
+        .add_plugin(GameSavePlugin)
+        .add_plugin(GameSettingsPlugin)
+        .add_plugin(GameLightPlugin)
+        .add_plugin(SoundPlugin)
         .add_state(GameState::LoadingAssets)
         .add_event::<ChangeGameStateEvent>()
         .add_event::<ChangeDirectionEvent>()
+        .add_event::<ChangeFloorEvent>()
         .add_startup_system(setup_game.system())
         .run();
 }
 
 //TODO:RG eventhandler
 
-fn window_descriptor() -> WindowDescriptor {
+fn window_descriptor(game_settings: &GameSettings) -> WindowDescriptor {
     WindowDescriptor {
         title: GAME_TITLE.to_string(),
-        width: 1440.0,
-        height: 900.0,
-        vsync: true,
+        width: game_settings.resolution.0 as f32,
+        height: game_settings.resolution.1 as f32,
+        vsync: game_settings.vsync,
         resizable: true,
-        mode: bevy::window::WindowMode::Windowed,
-        // TODO:RG use when finished
-        // mode: bevy::window::WindowMode::BorderlessFullscreen,
+        mode: game_settings.screen_mode.to_window_mode(),
         ..Default::default()
     }
 }
 
 //Initialize game data
-fn initialize_game_data() -> AmazinglyLostData {
-    AmazinglyLostData::new()
+fn initialize_game_data(game_settings: &GameSettings) -> AmazinglyLostData {
+    let mut amazing_data = AmazinglyLostData::new();
+    amazing_data.theme = game_settings.theme.clone();
+    amazing_data.maze_size = game_settings.maze_size;
+    amazing_data.light_mode = game_settings.light_mode;
+
+    // A seed can be given as argument to play a shared maze again.
+    // With --mask <png file> the maze gets the shape of the white part of the image.
+    // With --level <json or ron file> we play a saved level (see maze_level).
+    // With --ascii <text file> we play a maze drawn by hand (see maze_ascii).
+    let mut arguments = env::args().skip(1);
+    while let Some(argument) = arguments.next() {
+        if argument == "--mask" {
+            match arguments.next().map(MazeMask::from_png_file) {
+                Some(Ok(maze_mask)) => amazing_data.maze_mask = Some(maze_mask),
+                Some(Err(error)) => println!("{}", error),
+                None => println!("No mask file given"),
+            }
+        } else if argument == "--level" {
+            // Check the tiles now, so we don't find out when the game has started
+            let level = arguments.next().map(|path| {
+                maze_level::load_level_file(path).and_then(|level| level.to_maze().map(|_| level))
+            });
+            match level {
+                Some(Ok(level)) => {
+                    amazing_data.seed = level.seed;
+                    amazing_data.maze_algorithm = level.algorithm;
+                    if !level.theme.is_empty() {
+                        amazing_data.theme = level.theme.clone();
+                    }
+                    amazing_data.level = Some(level);
+                }
+                Some(Err(error)) => println!("{}", error),
+                None => println!("No level file given"),
+            }
+        } else if argument == "--ascii" {
+            match arguments.next().map(maze_ascii::load_ascii_file) {
+                Some(Ok(maze)) => {
+                    amazing_data.level = Some(MazeLevel::from_maze(
+                        &maze,
+                        &[],
+                        amazing_data.seed,
+                        amazing_data.maze_algorithm,
+                        &amazing_data.theme,
+                    ))
+                }
+                Some(Err(error)) => println!("{}", error),
+                None => println!("No maze file given"),
+            }
+        } else {
+            match argument.parse::<u64>() {
+                Ok(seed) => amazing_data.seed = seed,
+                Err(_) => println!("Not a valid seed: {}", argument),
+            }
+        }
+    }
+    amazing_data
 }
 
 fn setup_game(mut commands: Commands, _amazing_data: ResMut<AmazinglyLostData>) {
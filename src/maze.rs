@@ -0,0 +1,415 @@
+use crate::maze_tile::{MazeTile, TileType};
+
+// North is up on the screen, which is a higher y position in the maze
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Direction {
+    North,
+    South,
+    East,
+    West,
+}
+
+impl Direction {
+    pub fn opposite(&self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::South => Direction::North,
+            Direction::East => Direction::West,
+            Direction::West => Direction::East,
+        }
+    }
+
+    // The two directions at a right angle of this one
+    pub fn perpendicular(&self) -> [Direction; 2] {
+        match self {
+            Direction::North | Direction::South => [Direction::East, Direction::West],
+            Direction::East | Direction::West => [Direction::North, Direction::South],
+        }
+    }
+}
+
+pub const ALL_DIRECTIONS: [Direction; 4] = [
+    Direction::North,
+    Direction::South,
+    Direction::East,
+    Direction::West,
+];
+
+// The grid of MazeTiles, stored row by row. Positions are always (x, y).
+//
+// A cell is a tile with an odd x and y position, this is where the player can walk.
+// Between two cells there is a WALL tile that can be opened up (a passage).
+// Tiles with an even x and y position (pillars) and the BORDER are never opened.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Maze {
+    width: usize,
+    height: usize,
+    tiles: Vec<MazeTile>,
+}
+
+impl Maze {
+    // Create a maze with a BORDER all around it, OPEN cells and WALLS between all of them
+    pub fn new(width: usize, height: usize) -> Self {
+        let mut tiles = Vec::<MazeTile>::with_capacity(width * height);
+
+        for y in 0..height {
+            for x in 0..width {
+                // Start at id 1
+                let tile_id = y * width + x + 1;
+
+                let tile_type = if y == 0 || y == height - 1 || x == 0 || x == width - 1 {
+                    // Borders can never change to OPEN
+                    TileType::Border
+                } else if x % 2 == 1 && y % 2 == 1 {
+                    TileType::Open
+                } else {
+                    TileType::Wall
+                };
+                tiles.push(MazeTile::new(tile_id, tile_type));
+            }
+        }
+
+        Self {
+            width,
+            height,
+            tiles,
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn is_in_bounds(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> Option<&MazeTile> {
+        if self.is_in_bounds(x, y) {
+            self.tiles.get(y * self.width + x)
+        } else {
+            None
+        }
+    }
+
+    pub fn get_mut(&mut self, x: usize, y: usize) -> Option<&mut MazeTile> {
+        if self.is_in_bounds(x, y) {
+            self.tiles.get_mut(y * self.width + x)
+        } else {
+            None
+        }
+    }
+
+    pub fn tile_type(&self, x: usize, y: usize) -> Option<&TileType> {
+        self.get(x, y).map(|maze_tile| &maze_tile.tile_type)
+    }
+
+    // Does nothing if the position is outside of the maze.
+    // can_be_used follows the new type, like in MazeTile::new
+    pub fn set_tile_type(&mut self, x: usize, y: usize, tile_type: TileType) {
+        if let Some(maze_tile) = self.get_mut(x, y) {
+            maze_tile.can_be_used = tile_type != TileType::Wall && tile_type != TileType::Border;
+            maze_tile.tile_type = tile_type;
+        }
+    }
+
+    pub fn is_wall(&self, x: usize, y: usize) -> bool {
+        self.tile_type(x, y) == Some(&TileType::Wall)
+    }
+
+    // Can the player walk on this tile. Outside of the maze is never passable.
+    pub fn is_passable(&self, x: usize, y: usize) -> bool {
+        match self.tile_type(x, y) {
+            Some(TileType::Wall) | Some(TileType::Border) | None => false,
+            Some(_) => true,
+        }
+    }
+
+    // Position next to (x, y) in the given direction, if it's inside the maze
+    pub fn neighbour(&self, x: usize, y: usize, direction: &Direction) -> Option<(usize, usize)> {
+        let position = match direction {
+            Direction::North => (Some(x), y.checked_add(1)),
+            Direction::South => (Some(x), y.checked_sub(1)),
+            Direction::East => (x.checked_add(1), Some(y)),
+            Direction::West => (x.checked_sub(1), Some(y)),
+        };
+
+        match position {
+            (Some(next_x), Some(next_y)) if self.is_in_bounds(next_x, next_y) => {
+                Some((next_x, next_y))
+            }
+            _ => None,
+        }
+    }
+
+    // All positions next to (x, y) that are inside the maze (not diagonal)
+    pub fn neighbours(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        ALL_DIRECTIONS
+            .iter()
+            .filter_map(|direction| self.neighbour(x, y, direction))
+            .collect()
+    }
+
+    pub fn is_bridge(&self, x: usize, y: usize) -> bool {
+        self.tile_type(x, y) == Some(&TileType::Bridge)
+    }
+
+    // All positions next to (x, y) where the player can walk.
+    // We can't stop (or turn) on a BRIDGE, so we go straight over it to the tile behind it.
+    pub fn passable_neighbours(&self, x: usize, y: usize) -> Vec<(usize, usize)> {
+        let is_on_bridge = self.is_bridge(x, y);
+
+        ALL_DIRECTIONS
+            .iter()
+            .filter_map(|direction| {
+                let (next_x, next_y) = self.neighbour(x, y, direction)?;
+
+                if self.is_bridge(next_x, next_y) && !is_on_bridge {
+                    self.neighbour(next_x, next_y, direction)
+                } else {
+                    Some((next_x, next_y))
+                }
+            })
+            .filter(|(next_x, next_y)| self.is_passable(*next_x, *next_y))
+            .collect()
+    }
+
+    pub fn rows(&self) -> std::slice::Chunks<'_, MazeTile> {
+        self.tiles.chunks(self.width.max(1))
+    }
+
+    pub fn row(&self, y: usize) -> impl Iterator<Item = &MazeTile> {
+        self.tiles
+            .iter()
+            .skip(y * self.width)
+            .take(if y < self.height { self.width } else { 0 })
+    }
+
+    pub fn column(&self, x: usize) -> impl Iterator<Item = &MazeTile> {
+        let width = self.width;
+        self.tiles
+            .iter()
+            .enumerate()
+            .filter(move |(index, _)| x < width && index % width == x)
+            .map(|(_, maze_tile)| maze_tile)
+    }
+
+    // All positions (x, y) row by row
+    pub fn positions(&self) -> impl Iterator<Item = (usize, usize)> {
+        let width = self.width;
+        (0..self.height).flat_map(move |y| (0..width).map(move |x| (x, y)))
+    }
+
+    // Number of cells in the x and y direction
+    pub fn cell_count(&self) -> (usize, usize) {
+        (
+            self.width.saturating_sub(1) / 2,
+            self.height.saturating_sub(1) / 2,
+        )
+    }
+
+    // Cell (x, y) is tile (x * 2 + 1, y * 2 + 1)
+    pub fn cell_to_tile(cell: &(usize, usize)) -> (usize, usize) {
+        (cell.0 * 2 + 1, cell.1 * 2 + 1)
+    }
+
+    // Only tiles with odd positions are cells
+    pub fn tile_to_cell(x: usize, y: usize) -> Option<(usize, usize)> {
+        if x % 2 == 1 && y % 2 == 1 {
+            Some((x / 2, y / 2))
+        } else {
+            None
+        }
+    }
+
+    // Position of the WALL tile between two cells that are next to each other
+    pub fn wall_between(
+        cell: &(usize, usize),
+        other_cell: &(usize, usize),
+    ) -> Option<(usize, usize)> {
+        let distance_x = (cell.0 as isize - other_cell.0 as isize).abs();
+        let distance_y = (cell.1 as isize - other_cell.1 as isize).abs();
+
+        if distance_x + distance_y == 1 {
+            Some((cell.0 + other_cell.0 + 1, cell.1 + other_cell.1 + 1))
+        } else {
+            None
+        }
+    }
+
+    // A cell that became BORDER (e.g. outside of a mask) is not part of the maze
+    pub fn is_usable_cell(&self, cell: &(usize, usize)) -> bool {
+        let (x, y) = Maze::cell_to_tile(cell);
+
+        match self.tile_type(x, y) {
+            Some(TileType::Border) | None => false,
+            Some(_) => true,
+        }
+    }
+
+    // All cells that are part of the maze, row by row
+    pub fn usable_cells(&self) -> Vec<(usize, usize)> {
+        let (cells_x, cells_y) = self.cell_count();
+
+        (0..cells_y)
+            .flat_map(|y| (0..cells_x).map(move |x| (x, y)))
+            .filter(|cell| self.is_usable_cell(cell))
+            .collect()
+    }
+
+    // Usable cells next to this cell (not diagonal)
+    pub fn cell_neighbours(&self, cell: &(usize, usize)) -> Vec<(usize, usize)> {
+        let (cells_x, cells_y) = self.cell_count();
+        let (x, y) = *cell;
+        let mut neighbours = Vec::<(usize, usize)>::new();
+
+        if y + 1 < cells_y {
+            neighbours.push((x, y + 1));
+        }
+        if y > 0 {
+            neighbours.push((x, y - 1));
+        }
+        if x + 1 < cells_x {
+            neighbours.push((x + 1, y));
+        }
+        if x > 0 {
+            neighbours.push((x - 1, y));
+        }
+        neighbours.retain(|other_cell| self.is_usable_cell(other_cell));
+        neighbours
+    }
+
+    // Usable cell next to this cell in the given direction
+    pub fn cell_neighbour(
+        &self,
+        cell: &(usize, usize),
+        direction: &Direction,
+    ) -> Option<(usize, usize)> {
+        let (cells_x, cells_y) = self.cell_count();
+        let (x, y) = *cell;
+
+        let other_cell = match direction {
+            Direction::North if y + 1 < cells_y => (x, y + 1),
+            Direction::South if y > 0 => (x, y - 1),
+            Direction::East if x + 1 < cells_x => (x + 1, y),
+            Direction::West if x > 0 => (x - 1, y),
+            _ => return None,
+        };
+
+        if self.is_usable_cell(&other_cell) {
+            Some(other_cell)
+        } else {
+            None
+        }
+    }
+
+    // Open the WALL between two usable cells next to each other
+    pub fn carve_passage(&mut self, cell: &(usize, usize), other_cell: &(usize, usize)) {
+        if !self.is_usable_cell(cell) || !self.is_usable_cell(other_cell) {
+            return;
+        }
+
+        if let Some((wall_x, wall_y)) = Maze::wall_between(cell, other_cell) {
+            self.set_tile_type(wall_x, wall_y, TileType::Open);
+        }
+    }
+
+    // Put a WALL back between two usable cells next to each other
+    pub fn close_passage(&mut self, cell: &(usize, usize), other_cell: &(usize, usize)) {
+        if !self.is_usable_cell(cell) || !self.is_usable_cell(other_cell) {
+            return;
+        }
+
+        if let Some((wall_x, wall_y)) = Maze::wall_between(cell, other_cell) {
+            self.set_tile_type(wall_x, wall_y, TileType::Wall);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_maze_has_a_border_pillars_and_closed_cells() {
+        let maze = Maze::new(7, 5);
+
+        assert_eq!(maze.tile_type(0, 2), Some(&TileType::Border));
+        assert_eq!(maze.tile_type(6, 4), Some(&TileType::Border));
+        assert_eq!(maze.tile_type(2, 2), Some(&TileType::Wall));
+        assert_eq!(maze.tile_type(2, 1), Some(&TileType::Wall));
+        assert_eq!(maze.tile_type(3, 3), Some(&TileType::Open));
+        assert_eq!(maze.cell_count(), (3, 2));
+        assert_eq!(maze.usable_cells().len(), 6);
+        assert!(maze.passable_neighbours(3, 3).is_empty());
+    }
+
+    #[test]
+    fn outside_of_the_maze_is_nothing() {
+        let mut maze = Maze::new(5, 5);
+        maze.set_tile_type(5, 1, TileType::Open);
+
+        assert_eq!(maze.tile_type(5, 1), None);
+        assert_eq!(maze.tile_type(1, 5), None);
+        assert!(!maze.is_passable(5, 1));
+        assert_eq!(maze.row(5).count(), 0);
+        assert_eq!(maze.column(5).count(), 0);
+    }
+
+    #[test]
+    fn neighbours_stay_inside_of_the_maze() {
+        let maze = Maze::new(5, 5);
+
+        assert_eq!(maze.neighbour(0, 0, &Direction::South), None);
+        assert_eq!(maze.neighbour(0, 0, &Direction::West), None);
+        assert_eq!(maze.neighbour(4, 4, &Direction::North), None);
+        assert_eq!(maze.neighbour(4, 4, &Direction::East), None);
+        assert_eq!(maze.neighbour(2, 2, &Direction::North), Some((2, 3)));
+        assert_eq!(maze.neighbours(0, 0), vec![(0, 1), (1, 0)]);
+        assert_eq!(maze.neighbours(2, 2).len(), 4);
+    }
+
+    #[test]
+    fn cells_are_the_odd_tiles() {
+        assert_eq!(Maze::cell_to_tile(&(2, 1)), (5, 3));
+        assert_eq!(Maze::tile_to_cell(5, 3), Some((2, 1)));
+        assert_eq!(Maze::tile_to_cell(4, 3), None);
+        assert_eq!(Maze::wall_between(&(1, 1), &(2, 1)), Some((4, 3)));
+        assert_eq!(Maze::wall_between(&(1, 1), &(1, 0)), Some((3, 2)));
+        assert_eq!(Maze::wall_between(&(1, 1), &(2, 2)), None);
+    }
+
+    #[test]
+    fn carved_passage_can_be_walked_and_closed_again() {
+        let mut maze = Maze::new(7, 5);
+
+        maze.carve_passage(&(0, 0), &(1, 0));
+        assert_eq!(maze.passable_neighbours(1, 1), vec![(2, 1)]);
+        assert_eq!(maze.passable_neighbours(2, 1), vec![(3, 1), (1, 1)]);
+
+        maze.close_passage(&(0, 0), &(1, 0));
+        assert!(maze.passable_neighbours(1, 1).is_empty());
+
+        // Cells that are not next to each other have no wall between them
+        maze.carve_passage(&(0, 0), &(2, 0));
+        assert!(maze.passable_neighbours(1, 1).is_empty());
+    }
+
+    #[test]
+    fn border_cell_is_not_usable() {
+        let mut maze = Maze::new(7, 5);
+        maze.set_tile_type(3, 1, TileType::Border);
+
+        assert!(!maze.is_usable_cell(&(1, 0)));
+        assert_eq!(maze.cell_neighbours(&(0, 0)), vec![(0, 1)]);
+        assert_eq!(maze.cell_neighbour(&(0, 0), &Direction::East), None);
+
+        // No passage into a cell that is not part of the maze
+        maze.carve_passage(&(0, 0), &(1, 0));
+        assert!(maze.is_wall(2, 1));
+    }
+}
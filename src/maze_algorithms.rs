@@ -0,0 +1,622 @@
+use crate::maze::{Direction, Maze, ALL_DIRECTIONS};
+use crate::maze_generator::MazeAlgorithm;
+use crate::maze_tile::TileType;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+// All algorithms work with the cells of the Maze and only open up (or close) the
+// passages between them. Cells that are BORDER (e.g. outside of a mask) are skipped.
+
+// Walks randomly to unvisited cells and goes back one step when it gets stuck.
+// Gives long winding corridors with few dead ends.
+pub struct RecursiveBacktracker;
+
+impl MazeAlgorithm for RecursiveBacktracker {
+    fn name(&self) -> &'static str {
+        "Recursive backtracker"
+    }
+
+    fn carve(&self, maze: &mut Maze, rng: &mut StdRng) {
+        let (cells_x, cells_y) = maze.cell_count();
+        let mut visited = vec![vec![false; cells_x]; cells_y];
+
+        let start = random_cell(maze, rng);
+        visited[start.1][start.0] = true;
+        let mut stack = vec![start];
+
+        while let Some(&cell) = stack.last() {
+            let unvisited: Vec<(usize, usize)> = maze
+                .cell_neighbours(&cell)
+                .into_iter()
+                .filter(|(x, y)| !visited[*y][*x])
+                .collect();
+
+            if let Some(&next_cell) = unvisited.choose(rng) {
+                maze.carve_passage(&cell, &next_cell);
+                visited[next_cell.1][next_cell.0] = true;
+                stack.push(next_cell);
+            } else {
+                // Dead end, go back one step
+                stack.pop();
+            }
+        }
+    }
+}
+
+// Randomly opens walls between cells that are not yet connected to each other.
+// Gives lots of short dead ends.
+pub struct Kruskal;
+
+impl MazeAlgorithm for Kruskal {
+    fn name(&self) -> &'static str {
+        "Kruskal"
+    }
+
+    fn carve(&self, maze: &mut Maze, rng: &mut StdRng) {
+        let (cells_x, cells_y) = maze.cell_count();
+
+        // Every cell starts in its own set
+        let mut sets: Vec<usize> = (0..cells_x * cells_y).collect();
+
+        // All walls between two usable cells, east and north of a cell
+        let mut walls = Vec::<((usize, usize), (usize, usize))>::new();
+        for cell in maze.usable_cells() {
+            for other_cell in [(cell.0 + 1, cell.1), (cell.0, cell.1 + 1)] {
+                if other_cell.0 < cells_x
+                    && other_cell.1 < cells_y
+                    && maze.is_usable_cell(&other_cell)
+                {
+                    walls.push((cell, other_cell));
+                }
+            }
+        }
+        walls.shuffle(rng);
+
+        for (cell, other_cell) in walls {
+            let set = find_set(&mut sets, cell.1 * cells_x + cell.0);
+            let other_set = find_set(&mut sets, other_cell.1 * cells_x + other_cell.0);
+
+            // Only open walls between cells that can't reach each other yet
+            if set != other_set {
+                sets[other_set] = set;
+                maze.carve_passage(&cell, &other_cell);
+            }
+        }
+    }
+}
+
+// Grows the maze from one cell by connecting random cells on the edge (frontier).
+// Gives many short dead ends that spread out from the first cell.
+pub struct Prim;
+
+impl MazeAlgorithm for Prim {
+    fn name(&self) -> &'static str {
+        "Prim"
+    }
+
+    fn carve(&self, maze: &mut Maze, rng: &mut StdRng) {
+        let (cells_x, cells_y) = maze.cell_count();
+        let mut in_maze = vec![vec![false; cells_x]; cells_y];
+        let mut in_frontier = vec![vec![false; cells_x]; cells_y];
+        let mut frontier = Vec::<(usize, usize)>::new();
+
+        let start = random_cell(maze, rng);
+        in_maze[start.1][start.0] = true;
+        add_to_frontier(maze, &start, &in_maze, &mut in_frontier, &mut frontier);
+
+        while !frontier.is_empty() {
+            let cell = frontier.swap_remove(rng.gen_range(0..frontier.len()));
+
+            // Connect it to a random cell that is already part of the maze
+            let connected: Vec<(usize, usize)> = maze
+                .cell_neighbours(&cell)
+                .into_iter()
+                .filter(|(x, y)| in_maze[*y][*x])
+                .collect();
+            if let Some(other_cell) = connected.choose(rng) {
+                maze.carve_passage(&cell, other_cell);
+            }
+
+            in_maze[cell.1][cell.0] = true;
+            add_to_frontier(maze, &cell, &in_maze, &mut in_frontier, &mut frontier);
+        }
+    }
+}
+
+// Loop-erased random walks from unvisited cells until they hit the maze.
+// Gives an unbiased maze, every possible maze has the same chance.
+pub struct Wilson;
+
+impl MazeAlgorithm for Wilson {
+    fn name(&self) -> &'static str {
+        "Wilson"
+    }
+
+    fn carve(&self, maze: &mut Maze, rng: &mut StdRng) {
+        let (cells_x, cells_y) = maze.cell_count();
+        let mut in_maze = vec![vec![false; cells_x]; cells_y];
+        // Where the random walk went last from this cell
+        let mut next_step = vec![vec![(0usize, 0usize); cells_x]; cells_y];
+
+        let mut remaining = maze.usable_cells();
+        // A cell of a mask with no neighbours can never be walked to, it stays on its own
+        remaining.retain(|cell| !maze.cell_neighbours(cell).is_empty());
+        remaining.shuffle(rng);
+
+        // First cell is the start of the maze
+        if let Some(first) = remaining.pop() {
+            in_maze[first.1][first.0] = true;
+        }
+
+        while let Some(walk_start) = remaining.pop() {
+            if in_maze[walk_start.1][walk_start.0] {
+                continue;
+            }
+
+            // Walk randomly until we hit the maze. Walking over our own path again
+            // overwrites the last step, which erases the loop.
+            let mut cell = walk_start;
+            while !in_maze[cell.1][cell.0] {
+                let neighbours = maze.cell_neighbours(&cell);
+                let next_cell = *neighbours.choose(rng).unwrap();
+                next_step[cell.1][cell.0] = next_cell;
+                cell = next_cell;
+            }
+
+            // Now add the loop free walk to the maze
+            let mut cell = walk_start;
+            while !in_maze[cell.1][cell.0] {
+                let next_cell = next_step[cell.1][cell.0];
+                maze.carve_passage(&cell, &next_cell);
+                in_maze[cell.1][cell.0] = true;
+                cell = next_cell;
+            }
+        }
+    }
+}
+
+// Creates the maze one row at a time and only needs to know about the current row.
+// Gives a maze with a lot of horizontal corridors.
+pub struct Eller;
+
+impl MazeAlgorithm for Eller {
+    fn name(&self) -> &'static str {
+        "Eller"
+    }
+
+    fn carve(&self, maze: &mut Maze, rng: &mut StdRng) {
+        let (cells_x, cells_y) = maze.cell_count();
+        // Set id for every cell in the current row
+        let mut row_sets: Vec<usize> = (0..cells_x).collect();
+        let mut next_set_id = cells_x;
+
+        for y in 0..cells_y {
+            let is_last_row = y + 1 == cells_y;
+
+            // Randomly join cells next to each other that are in different sets.
+            // In the last row every set has to be joined.
+            for x in 0..cells_x.saturating_sub(1) {
+                if row_sets[x] != row_sets[x + 1] && (is_last_row || rng.gen_bool(0.5)) {
+                    maze.carve_passage(&(x, y), &(x + 1, y));
+
+                    let old_set = row_sets[x + 1];
+                    let new_set = row_sets[x];
+                    for set in row_sets.iter_mut() {
+                        if *set == old_set {
+                            *set = new_set;
+                        }
+                    }
+                }
+            }
+
+            if is_last_row {
+                break;
+            }
+
+            // Every set needs at least one cell that goes down to the next row
+            let mut next_row_sets = vec![usize::MAX; cells_x];
+            let mut set_ids = row_sets.clone();
+            set_ids.sort_unstable();
+            set_ids.dedup();
+
+            for set_id in set_ids {
+                let mut set_cells: Vec<usize> =
+                    (0..cells_x).filter(|x| row_sets[*x] == set_id).collect();
+                set_cells.shuffle(rng);
+
+                let go_down_count = rng.gen_range(1..=set_cells.len());
+                for x in set_cells.iter().take(go_down_count) {
+                    maze.carve_passage(&(*x, y), &(*x, y + 1));
+                    next_row_sets[*x] = set_id;
+                }
+            }
+
+            // Cells that are not connected from above get a new set
+            for set in next_row_sets.iter_mut() {
+                if *set == usize::MAX {
+                    *set = next_set_id;
+                    next_set_id += 1;
+                }
+            }
+            row_sets = next_row_sets;
+        }
+    }
+}
+
+// Walks randomly like the recursive backtracker, but when stuck it hunts for an
+// unvisited cell next to the maze instead of going back.
+// Gives long corridors, but takes more time for bigger mazes.
+pub struct HuntAndKill;
+
+impl MazeAlgorithm for HuntAndKill {
+    fn name(&self) -> &'static str {
+        "Hunt and kill"
+    }
+
+    fn carve(&self, maze: &mut Maze, rng: &mut StdRng) {
+        let (cells_x, cells_y) = maze.cell_count();
+        // Cells that are not usable count as visited, so we never hunt for them
+        let mut visited: Vec<Vec<bool>> = (0..cells_y)
+            .map(|y| {
+                (0..cells_x)
+                    .map(|x| !maze.is_usable_cell(&(x, y)))
+                    .collect()
+            })
+            .collect();
+
+        let mut current = Some(random_cell(maze, rng));
+        let mut hunt_row = 0;
+
+        while let Some(cell) = current {
+            visited[cell.1][cell.0] = true;
+
+            let unvisited: Vec<(usize, usize)> = maze
+                .cell_neighbours(&cell)
+                .into_iter()
+                .filter(|(x, y)| !visited[*y][*x])
+                .collect();
+
+            if let Some(&next_cell) = unvisited.choose(rng) {
+                maze.carve_passage(&cell, &next_cell);
+                current = Some(next_cell);
+                continue;
+            }
+
+            // Hunt mode. Rows before hunt_row are completely visited, so skip those.
+            while hunt_row < cells_y && visited[hunt_row].iter().all(|is_visited| *is_visited) {
+                hunt_row += 1;
+            }
+
+            current = None;
+            'hunt: for y in hunt_row..cells_y {
+                for x in 0..cells_x {
+                    if visited[y][x] {
+                        continue;
+                    }
+
+                    let connected: Vec<(usize, usize)> = maze
+                        .cell_neighbours(&(x, y))
+                        .into_iter()
+                        .filter(|(nx, ny)| visited[*ny][*nx])
+                        .collect();
+
+                    if let Some(other_cell) = connected.choose(rng) {
+                        maze.carve_passage(&(x, y), other_cell);
+                        current = Some((x, y));
+                        break 'hunt;
+                    }
+                }
+            }
+        }
+    }
+}
+
+// Keeps a list of active cells and picks the newest (like the recursive backtracker)
+// or a random one (like Prim) to grow from. Half the time it's one, half the time the other.
+pub struct GrowingTree;
+
+impl MazeAlgorithm for GrowingTree {
+    fn name(&self) -> &'static str {
+        "Growing tree"
+    }
+
+    fn carve(&self, maze: &mut Maze, rng: &mut StdRng) {
+        let (cells_x, cells_y) = maze.cell_count();
+        let mut visited = vec![vec![false; cells_x]; cells_y];
+
+        let start = random_cell(maze, rng);
+        visited[start.1][start.0] = true;
+        let mut active_cells = vec![start];
+
+        while !active_cells.is_empty() {
+            let index = if rng.gen_bool(0.5) {
+                active_cells.len() - 1
+            } else {
+                rng.gen_range(0..active_cells.len())
+            };
+            let cell = active_cells[index];
+
+            let unvisited: Vec<(usize, usize)> = maze
+                .cell_neighbours(&cell)
+                .into_iter()
+                .filter(|(x, y)| !visited[*y][*x])
+                .collect();
+
+            if let Some(&next_cell) = unvisited.choose(rng) {
+                maze.carve_passage(&cell, &next_cell);
+                visited[next_cell.1][next_cell.0] = true;
+                active_cells.push(next_cell);
+            } else {
+                active_cells.remove(index);
+            }
+        }
+    }
+}
+
+// Goes row by row and creates runs of cells to the east. At the end of every run
+// one random cell of the run is connected to the row above.
+// The first row is always one long corridor.
+pub struct Sidewinder;
+
+impl MazeAlgorithm for Sidewinder {
+    fn name(&self) -> &'static str {
+        "Sidewinder"
+    }
+
+    fn carve(&self, maze: &mut Maze, rng: &mut StdRng) {
+        let (cells_x, cells_y) = maze.cell_count();
+
+        for y in 0..cells_y {
+            let mut run_start = 0;
+
+            for x in 0..cells_x {
+                let at_east_side = x + 1 == cells_x;
+                let close_run = at_east_side || (y > 0 && rng.gen_bool(0.5));
+
+                if close_run {
+                    if y > 0 {
+                        let run_cell = rng.gen_range(run_start..=x);
+                        maze.carve_passage(&(run_cell, y), &(run_cell, y - 1));
+                    }
+                    run_start = x + 1;
+                } else {
+                    maze.carve_passage(&(x, y), &(x + 1, y));
+                }
+            }
+        }
+    }
+}
+
+// Every cell opens the wall to the north or to the east.
+// Very fast, but gives two long corridors along the sides and a diagonal texture.
+pub struct BinaryTree;
+
+impl MazeAlgorithm for BinaryTree {
+    fn name(&self) -> &'static str {
+        "Binary tree"
+    }
+
+    fn carve(&self, maze: &mut Maze, rng: &mut StdRng) {
+        let (cells_x, cells_y) = maze.cell_count();
+
+        for y in 0..cells_y {
+            for x in 0..cells_x {
+                let mut directions = Vec::<(usize, usize)>::new();
+                if y + 1 < cells_y {
+                    directions.push((x, y + 1));
+                }
+                if x + 1 < cells_x {
+                    directions.push((x + 1, y));
+                }
+
+                if let Some(other_cell) = directions.choose(rng) {
+                    maze.carve_passage(&(x, y), other_cell);
+                }
+            }
+        }
+    }
+}
+
+// Starts without any walls and keeps dividing the area with a wall that has one gap in it.
+// Gives long straight walls and a boxy look.
+pub struct RecursiveDivision;
+
+impl MazeAlgorithm for RecursiveDivision {
+    fn name(&self) -> &'static str {
+        "Recursive division"
+    }
+
+    fn carve(&self, maze: &mut Maze, rng: &mut StdRng) {
+        let (cells_x, cells_y) = maze.cell_count();
+
+        // First remove every wall between the cells
+        for y in 0..cells_y {
+            for x in 0..cells_x {
+                if x + 1 < cells_x {
+                    maze.carve_passage(&(x, y), &(x + 1, y));
+                }
+                if y + 1 < cells_y {
+                    maze.carve_passage(&(x, y), &(x, y + 1));
+                }
+            }
+        }
+
+        // Areas (x, y, width, height) in cells that still need to be divided.
+        // We don't use recursion, so big mazes can't overflow the stack.
+        let mut areas = vec![(0usize, 0usize, cells_x, cells_y)];
+
+        while let Some((x, y, width, height)) = areas.pop() {
+            if width < 2 || height < 2 {
+                continue;
+            }
+
+            let divide_horizontal = if width < height {
+                true
+            } else if height < width {
+                false
+            } else {
+                rng.gen_bool(0.5)
+            };
+
+            if divide_horizontal {
+                // Wall between row wall_y and wall_y + 1 with one gap
+                let wall_y = y + rng.gen_range(0..height - 1);
+                let gap_x = x + rng.gen_range(0..width);
+                for cell_x in x..x + width {
+                    if cell_x != gap_x {
+                        maze.close_passage(&(cell_x, wall_y), &(cell_x, wall_y + 1));
+                    }
+                }
+                areas.push((x, y, width, wall_y - y + 1));
+                areas.push((x, wall_y + 1, width, y + height - wall_y - 1));
+            } else {
+                // Wall between column wall_x and wall_x + 1 with one gap
+                let wall_x = x + rng.gen_range(0..width - 1);
+                let gap_y = y + rng.gen_range(0..height);
+                for cell_y in y..y + height {
+                    if cell_y != gap_y {
+                        maze.close_passage(&(wall_x, cell_y), &(wall_x + 1, cell_y));
+                    }
+                }
+                areas.push((x, y, wall_x - x + 1, height));
+                areas.push((wall_x + 1, y, x + width - wall_x - 1, height));
+            }
+        }
+    }
+}
+
+// The recursive backtracker, but it can also go under a corridor to an unvisited cell
+// on the other side. The cell in the middle becomes a BRIDGE.
+// Gives corridors that cross each other without a junction.
+pub struct Weave;
+
+// The next cell and the cell we go under to get there (if any)
+type WeaveStep = ((usize, usize), Option<(usize, usize)>);
+
+impl MazeAlgorithm for Weave {
+    fn name(&self) -> &'static str {
+        "Weave"
+    }
+
+    fn carve(&self, maze: &mut Maze, rng: &mut StdRng) {
+        let (cells_x, cells_y) = maze.cell_count();
+        let mut visited = vec![vec![false; cells_x]; cells_y];
+
+        let start = random_cell(maze, rng);
+        visited[start.1][start.0] = true;
+        let mut stack = vec![start];
+
+        while let Some(&cell) = stack.last() {
+            let mut next_steps: Vec<WeaveStep> = maze
+                .cell_neighbours(&cell)
+                .into_iter()
+                .filter(|(x, y)| !visited[*y][*x])
+                .map(|next_cell| (next_cell, None))
+                .collect();
+
+            for direction in ALL_DIRECTIONS.iter() {
+                if let Some((bridge_cell, next_cell)) = get_tunnel(maze, &cell, direction) {
+                    if !visited[next_cell.1][next_cell.0] {
+                        next_steps.push((next_cell, Some(bridge_cell)));
+                    }
+                }
+            }
+
+            match next_steps.choose(rng) {
+                Some(&(next_cell, None)) => {
+                    maze.carve_passage(&cell, &next_cell);
+                    visited[next_cell.1][next_cell.0] = true;
+                    stack.push(next_cell);
+                }
+                Some(&(next_cell, Some(bridge_cell))) => {
+                    maze.carve_passage(&cell, &bridge_cell);
+                    maze.carve_passage(&bridge_cell, &next_cell);
+                    let (bridge_x, bridge_y) = Maze::cell_to_tile(&bridge_cell);
+                    maze.set_tile_type(bridge_x, bridge_y, TileType::Bridge);
+                    visited[next_cell.1][next_cell.0] = true;
+                    stack.push(next_cell);
+                }
+                None => {
+                    // Dead end, go back one step
+                    stack.pop();
+                }
+            }
+        }
+    }
+}
+
+// We can go under the next cell if it is a straight corridor at a right angle of
+// the direction we go in. Gives the cell we go under and the cell behind it.
+fn get_tunnel(
+    maze: &Maze,
+    cell: &(usize, usize),
+    direction: &Direction,
+) -> Option<((usize, usize), (usize, usize))> {
+    let bridge_cell = maze.cell_neighbour(cell, direction)?;
+    let next_cell = maze.cell_neighbour(&bridge_cell, direction)?;
+    let (bridge_x, bridge_y) = Maze::cell_to_tile(&bridge_cell);
+
+    if maze.tile_type(bridge_x, bridge_y) != Some(&TileType::Open) {
+        return None;
+    }
+
+    let is_open = |passage_direction: &Direction| {
+        maze.neighbour(bridge_x, bridge_y, passage_direction)
+            .is_some_and(|(wall_x, wall_y)| maze.is_passable(wall_x, wall_y))
+    };
+    let is_straight_corridor = direction.perpendicular().iter().all(is_open)
+        && !is_open(direction)
+        && !is_open(&direction.opposite());
+
+    if is_straight_corridor {
+        Some((bridge_cell, next_cell))
+    } else {
+        None
+    }
+}
+
+// A random usable cell. We first try any cell, so mazes without a mask stay the
+// same for the same seed.
+pub(crate) fn random_cell(maze: &Maze, rng: &mut StdRng) -> (usize, usize) {
+    let (cells_x, cells_y) = maze.cell_count();
+    let cell = (rng.gen_range(0..cells_x), rng.gen_range(0..cells_y));
+
+    if maze.is_usable_cell(&cell) {
+        cell
+    } else {
+        *maze.usable_cells().choose(rng).unwrap_or(&cell)
+    }
+}
+
+// Find the set of a cell for Kruskal and make the path shorter for the next time
+pub(crate) fn find_set(sets: &mut [usize], cell_index: usize) -> usize {
+    let mut root = cell_index;
+    while sets[root] != root {
+        root = sets[root];
+    }
+
+    let mut index = cell_index;
+    while sets[index] != root {
+        let next_index = sets[index];
+        sets[index] = root;
+        index = next_index;
+    }
+    root
+}
+
+fn add_to_frontier(
+    maze: &Maze,
+    cell: &(usize, usize),
+    in_maze: &[Vec<bool>],
+    in_frontier: &mut [Vec<bool>],
+    frontier: &mut Vec<(usize, usize)>,
+) {
+    for (x, y) in maze.cell_neighbours(cell) {
+        if !in_maze[y][x] && !in_frontier[y][x] {
+            in_frontier[y][x] = true;
+            frontier.push((x, y));
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use crate::maze::Maze;
+use crate::maze_solver;
+
+use serde::{Deserialize, Serialize};
+
+// About the number of tiles the player can walk on in the smallest (33 by 33)
+// and biggest (231 by 231) maze in the game
+const MIN_PASSABLE_TILES: f32 = 33.0 * 33.0 / 2.0;
+const MAX_PASSABLE_TILES: f32 = 231.0 * 231.0 / 2.0;
+const MAX_DECISION_DENSITY: f32 = 0.35;
+const MAX_DEAD_END_DENSITY: f32 = 0.2;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum Difficulty {
+    Easy,
+    Medium,
+    Hard,
+    VeryHard,
+}
+
+impl Difficulty {
+    pub fn from_score(score: f32) -> Self {
+        if score < 25.0 {
+            Difficulty::Easy
+        } else if score < 50.0 {
+            Difficulty::Medium
+        } else if score < 75.0 {
+            Difficulty::Hard
+        } else {
+            Difficulty::VeryHard
+        }
+    }
+
+    // Lowest and highest score (not included) for this difficulty
+    pub fn score_range(&self) -> (f32, f32) {
+        match self {
+            Difficulty::Easy => (0.0, 25.0),
+            Difficulty::Medium => (25.0, 50.0),
+            Difficulty::Hard => (50.0, 75.0),
+            Difficulty::VeryHard => (75.0, f32::MAX),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Difficulty::Easy => "Easy",
+            Difficulty::Medium => "Medium",
+            Difficulty::Hard => "Hard",
+            Difficulty::VeryHard => "Very hard",
+        }
+    }
+
+    // Used to go through all difficulties in the game, None means any difficulty
+    pub fn next(difficulty: &Option<Difficulty>) -> Option<Difficulty> {
+        match difficulty {
+            None => Some(Difficulty::Easy),
+            Some(Difficulty::Easy) => Some(Difficulty::Medium),
+            Some(Difficulty::Medium) => Some(Difficulty::Hard),
+            Some(Difficulty::Hard) => Some(Difficulty::VeryHard),
+            Some(Difficulty::VeryHard) => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct MazeRating {
+    // Tiles with only one way in or out
+    pub dead_end_count: usize,
+    // Tiles with three or four ways to go
+    pub junction_count: usize,
+    // Average number of steps between two dead ends or junctions
+    pub average_corridor_length: f32,
+    pub solution_length: usize,
+    // Solution length compared to all the tiles where the player can walk
+    pub solution_ratio: f32,
+    // Places on the solution where the player has to choose between multiple ways
+    pub decision_points: usize,
+    // From 0 (very easy) to 100 (very hard)
+    pub difficulty_score: f32,
+}
+
+impl MazeRating {
+    pub fn difficulty(&self) -> Difficulty {
+        Difficulty::from_score(self.difficulty_score)
+    }
+}
+
+pub fn analyse_maze(maze: &Maze, solution: &[(usize, usize)]) -> MazeRating {
+    let mut passable_tiles = 0;
+    let mut dead_end_count = 0;
+    let mut junction_count = 0;
+
+    for (x, y) in maze.positions() {
+        if maze.is_passable(x, y) {
+            passable_tiles += 1;
+
+            match get_way_count(maze, x, y) {
+                1 => dead_end_count += 1,
+                3 | 4 => junction_count += 1,
+                _ => {}
+            }
+        }
+    }
+
+    let decision_points = get_decision_points(maze, solution);
+    let solution_ratio = if passable_tiles > 0 {
+        solution.len() as f32 / passable_tiles as f32
+    } else {
+        0.0
+    };
+
+    // Bigger mazes are harder, but not twice as hard when they are twice as big
+    let size_part = (((passable_tiles as f32).max(1.0).ln() - MIN_PASSABLE_TILES.ln())
+        / (MAX_PASSABLE_TILES.ln() - MIN_PASSABLE_TILES.ln()))
+    .clamp(0.0, 1.0);
+    // Every choice on the way to the exit is a chance to get lost.
+    // Mazes with lots of choices have one about every 3 steps.
+    let decision_density = decision_points as f32 / solution.len().max(1) as f32;
+    let decision_part = (decision_density / MAX_DECISION_DENSITY).min(1.0);
+    // Lots of dead ends waste time. Mazes with lots of them have one about every 5 tiles.
+    let dead_end_density = dead_end_count as f32 / passable_tiles.max(1) as f32;
+    let dead_end_part = (dead_end_density / MAX_DEAD_END_DENSITY).min(1.0);
+
+    MazeRating {
+        dead_end_count,
+        junction_count,
+        average_corridor_length: get_average_corridor_length(maze),
+        solution_length: solution.len(),
+        solution_ratio,
+        decision_points,
+        difficulty_score: 100.0 * (0.4 * size_part + 0.35 * decision_part + 0.25 * dead_end_part),
+    }
+}
+
+// Number of ways the player can go from this tile.
+// On a BRIDGE we can only go on or go back, it's never a junction.
+fn get_way_count(maze: &Maze, x: usize, y: usize) -> usize {
+    if maze.is_bridge(x, y) {
+        return 2;
+    }
+    maze.passable_neighbours(x, y).len()
+}
+
+// The START counts if there is more than one way to go, every other tile on the solution
+// counts if there is more than one way to go forward (we don't count where we came from)
+fn get_decision_points(maze: &Maze, solution: &[(usize, usize)]) -> usize {
+    let mut decision_points = 0;
+
+    for (index, (x, y)) in solution.iter().enumerate() {
+        // Nothing to decide at the exit
+        if index + 1 == solution.len() {
+            break;
+        }
+
+        let way_count = get_way_count(maze, *x, *y);
+        let ways_forward = if index == 0 { way_count } else { way_count - 1 };
+
+        if ways_forward > 1 {
+            decision_points += 1;
+        }
+    }
+    decision_points
+}
+
+// Walk from every dead end and junction through the corridors until we reach the next one
+fn get_average_corridor_length(maze: &Maze) -> f32 {
+    let mut total_length = 0;
+    let mut corridor_count = 0;
+
+    for (x, y) in maze.positions() {
+        if !maze.is_passable(x, y) || get_way_count(maze, x, y) == 2 {
+            continue;
+        }
+
+        for first_step in maze.passable_neighbours(x, y) {
+            let mut previous = (x, y);
+            let mut current = first_step;
+            // A step over a BRIDGE is two tiles long
+            let mut length = maze_solver::get_manhattan_distance(&previous, &current);
+
+            // Follow the corridor, tiles with two ways are part of it
+            while get_way_count(maze, current.0, current.1) == 2 {
+                let next = maze
+                    .passable_neighbours(current.0, current.1)
+                    .into_iter()
+                    .find(|position| *position != previous);
+
+                match next {
+                    Some(next) => {
+                        length += maze_solver::get_manhattan_distance(&current, &next);
+                        previous = current;
+                        current = next;
+                    }
+                    None => break,
+                }
+            }
+
+            total_length += length;
+            corridor_count += 1;
+        }
+    }
+
+    // Every corridor is walked from both ends, but that doesn't change the average
+    if corridor_count > 0 {
+        total_length as f32 / corridor_count as f32
+    } else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze_tile::TileType;
+
+    // A T: a corridor from west to east with a dead end going north from the middle
+    fn t_maze() -> (Maze, Vec<(usize, usize)>) {
+        let mut maze = Maze::new(7, 5);
+        maze.carve_passage(&(0, 0), &(1, 0));
+        maze.carve_passage(&(1, 0), &(2, 0));
+        maze.carve_passage(&(1, 0), &(1, 1));
+        // The other cells of the top row are not part of the maze
+        maze.set_tile_type(1, 3, TileType::Border);
+        maze.set_tile_type(5, 3, TileType::Border);
+
+        (maze, vec![(1, 1), (2, 1), (3, 1), (4, 1), (5, 1)])
+    }
+
+    #[test]
+    fn score_thresholds_give_the_difficulty() {
+        assert_eq!(Difficulty::from_score(0.0), Difficulty::Easy);
+        assert_eq!(Difficulty::from_score(24.9), Difficulty::Easy);
+        assert_eq!(Difficulty::from_score(25.0), Difficulty::Medium);
+        assert_eq!(Difficulty::from_score(49.9), Difficulty::Medium);
+        assert_eq!(Difficulty::from_score(50.0), Difficulty::Hard);
+        assert_eq!(Difficulty::from_score(74.9), Difficulty::Hard);
+        assert_eq!(Difficulty::from_score(75.0), Difficulty::VeryHard);
+        assert_eq!(Difficulty::from_score(100.0), Difficulty::VeryHard);
+    }
+
+    #[test]
+    fn score_range_matches_the_difficulty() {
+        let mut difficulty = Difficulty::next(&None);
+        while let Some(current) = difficulty {
+            let (lowest, highest) = current.score_range();
+            assert_eq!(Difficulty::from_score(lowest), current);
+
+            // The next difficulty starts where this one stops
+            difficulty = Difficulty::next(&difficulty);
+            match difficulty {
+                Some(next) => assert_eq!(next.score_range().0, highest),
+                None => assert_eq!(highest, f32::MAX),
+            }
+        }
+    }
+
+    #[test]
+    fn t_maze_has_one_junction_and_one_decision() {
+        let (maze, solution) = t_maze();
+        let rating = analyse_maze(&maze, &solution);
+
+        assert_eq!(rating.dead_end_count, 3);
+        assert_eq!(rating.junction_count, 1);
+        assert_eq!(rating.average_corridor_length, 2.0);
+        assert_eq!(rating.solution_length, 5);
+        assert_eq!(rating.solution_ratio, 5.0 / 7.0);
+        assert_eq!(rating.decision_points, 1);
+
+        // Too small for the size to count, a decision every 5 steps and lots of dead ends
+        let expected_score = 100.0 * (0.35 * (0.2 / MAX_DECISION_DENSITY) + 0.25);
+        assert!((rating.difficulty_score - expected_score).abs() < 0.001);
+        assert_eq!(rating.difficulty(), Difficulty::Medium);
+    }
+
+    #[test]
+    fn straight_corridor_has_no_decisions() {
+        let mut maze = Maze::new(7, 3);
+        maze.carve_passage(&(0, 0), &(1, 0));
+        maze.carve_passage(&(1, 0), &(2, 0));
+        let solution = vec![(1, 1), (2, 1), (3, 1), (4, 1), (5, 1)];
+
+        let rating = analyse_maze(&maze, &solution);
+        assert_eq!(rating.dead_end_count, 2);
+        assert_eq!(rating.junction_count, 0);
+        assert_eq!(rating.decision_points, 0);
+        assert_eq!(rating.average_corridor_length, 4.0);
+        // Only the dead ends count
+        assert!((rating.difficulty_score - 25.0).abs() < 0.001);
+    }
+}
@@ -0,0 +1,421 @@
+use crate::maze::Maze;
+use crate::maze_floors::{FloorPosition, MultiFloorMaze};
+use crate::maze_generator::MIN_MAZE_SIZE_X_OR_Y;
+use crate::maze_solver;
+use crate::maze_tile::TileType;
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+// A maze as text, one character for every tile. The first line is the north side.
+//
+// # = BORDER, and the pillars between the WALLS
+// | = WALL between a cell and the cell east or west of it
+// - = WALL between a cell and the cell north or south of it
+// G = OPEN (spaces and @ are also read as OPEN, so a maze is easier to draw)
+// S = START, E = EXIT
+//
+// Other tiles: R = ROOM, = = BRIDGE, U = LADDER up, D = LADDER down, X = fake EXIT
+//
+// #########
+// #SGGG|GE#
+// #-#G#-#G#
+// #GGGGGGG#
+// #########
+
+#[derive(Debug)]
+pub enum AsciiError {
+    Io(std::io::Error),
+    // No lines with tiles
+    Empty,
+    // Every line needs the same number of tiles
+    RaggedLine {
+        line: usize,
+        width: usize,
+        expected: usize,
+    },
+    // Sizes need to be odd, so there are WALLS between all cells and a BORDER around them
+    InvalidSize(usize, usize),
+    // All floors of a maze need the same size as the first floor
+    FloorSize {
+        floor: usize,
+        width: usize,
+        height: usize,
+    },
+    UnknownCharacter {
+        character: char,
+        line: usize,
+        column: usize,
+    },
+    // Needs exactly one START and one EXIT
+    StartCount(usize),
+    ExitCount(usize),
+    NoPathToExit,
+}
+
+impl fmt::Display for AsciiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AsciiError::Io(error) => write!(f, "Can't read the maze: {}", error),
+            AsciiError::Empty => write!(f, "The maze has no tiles"),
+            AsciiError::RaggedLine {
+                line,
+                width,
+                expected,
+            } => write!(
+                f,
+                "Line {} has {} tiles, but the maze is {} tiles wide",
+                line, width, expected
+            ),
+            AsciiError::InvalidSize(width, height) => write!(
+                f,
+                "The maze is {}x{} tiles, it needs an odd width and height of at least {}",
+                width, height, MIN_MAZE_SIZE_X_OR_Y
+            ),
+            AsciiError::FloorSize {
+                floor,
+                width,
+                height,
+            } => write!(
+                f,
+                "Floor {} is {}x{} tiles, it needs the size of the first floor",
+                floor + 1,
+                width,
+                height
+            ),
+            AsciiError::UnknownCharacter {
+                character,
+                line,
+                column,
+            } => write!(
+                f,
+                "Unknown tile '{}' on line {}, column {}",
+                character, line, column
+            ),
+            AsciiError::StartCount(count) => {
+                write!(f, "The maze needs one start (S), it has {}", count)
+            }
+            AsciiError::ExitCount(count) => {
+                write!(f, "The maze needs one exit (E), it has {}", count)
+            }
+            AsciiError::NoPathToExit => write!(f, "There is no way from the start to the exit"),
+        }
+    }
+}
+
+impl std::error::Error for AsciiError {}
+
+impl From<std::io::Error> for AsciiError {
+    fn from(error: std::io::Error) -> Self {
+        AsciiError::Io(error)
+    }
+}
+
+pub fn maze_to_ascii(maze: &Maze) -> String {
+    let mut ascii = String::with_capacity((maze.width() + 1) * maze.height());
+
+    for y in (0..maze.height()).rev() {
+        for x in 0..maze.width() {
+            if let Some(tile_type) = maze.tile_type(x, y) {
+                ascii.push(tile_to_char(tile_type, x, y));
+            }
+        }
+        ascii.push('\n');
+    }
+    ascii
+}
+
+// Reads a maze written by maze_to_ascii or drawn by hand.
+// Empty lines at the start and end and spaces at the end of a line are skipped.
+pub fn maze_from_ascii(ascii: &str) -> Result<Maze, AsciiError> {
+    let maze = tiles_from_ascii(ascii)?;
+
+    let start = find_only_tile(&maze, &TileType::Start).map_err(AsciiError::StartCount)?;
+    let exit = find_only_tile(&maze, &TileType::Exit).map_err(AsciiError::ExitCount)?;
+    if maze_solver::breadth_first_path(&maze, &start, &exit).is_none() {
+        return Err(AsciiError::NoPathToExit);
+    }
+    Ok(maze)
+}
+
+// Reads every floor like maze_from_ascii, the first one is floor 0. The START and EXIT
+// can be on any floor, so only the whole maze needs one of each and a path between them.
+pub fn floors_from_ascii<S: AsRef<str>>(floors: &[S]) -> Result<MultiFloorMaze, AsciiError> {
+    let floors = floors
+        .iter()
+        .map(|ascii| tiles_from_ascii(ascii.as_ref()))
+        .collect::<Result<Vec<Maze>, AsciiError>>()?;
+    if let Some(first_floor) = floors.first() {
+        if let Some((floor, maze)) = floors.iter().enumerate().find(|(_, maze)| {
+            maze.width() != first_floor.width() || maze.height() != first_floor.height()
+        }) {
+            return Err(AsciiError::FloorSize {
+                floor,
+                width: maze.width(),
+                height: maze.height(),
+            });
+        }
+    }
+    let maze = MultiFloorMaze::from_floors(floors);
+
+    let start = find_only_floor_tile(&maze, &TileType::Start).map_err(AsciiError::StartCount)?;
+    let exit = find_only_floor_tile(&maze, &TileType::Exit).map_err(AsciiError::ExitCount)?;
+    if maze_solver::breadth_first_path(&maze, &start, &exit).is_none() {
+        return Err(AsciiError::NoPathToExit);
+    }
+    Ok(maze)
+}
+
+// The tiles without checking the START and EXIT, one floor can have neither of them
+fn tiles_from_ascii(ascii: &str) -> Result<Maze, AsciiError> {
+    let lines: Vec<&str> = ascii
+        .lines()
+        .map(|line| line.trim_end())
+        .skip_while(|line| line.is_empty())
+        .collect();
+    let line_count = lines
+        .iter()
+        .rposition(|line| !line.is_empty())
+        .map_or(0, |last_line| last_line + 1);
+    let lines = &lines[..line_count];
+
+    let width = match lines.first() {
+        Some(line) => line.chars().count(),
+        None => return Err(AsciiError::Empty),
+    };
+    let height = lines.len();
+    for (line_number, line) in lines.iter().enumerate() {
+        let line_width = line.chars().count();
+        if line_width != width {
+            return Err(AsciiError::RaggedLine {
+                line: line_number + 1,
+                width: line_width,
+                expected: width,
+            });
+        }
+    }
+    if width % 2 != 1
+        || height % 2 != 1
+        || width < MIN_MAZE_SIZE_X_OR_Y as usize
+        || height < MIN_MAZE_SIZE_X_OR_Y as usize
+    {
+        return Err(AsciiError::InvalidSize(width, height));
+    }
+
+    let mut maze = Maze::new(width, height);
+    for (line_number, line) in lines.iter().enumerate() {
+        // The first line is the north side, which has the highest y
+        let y = height - 1 - line_number;
+
+        for (x, character) in line.chars().enumerate() {
+            let tile_type = char_to_tile(character, x, y, width, height).ok_or(
+                AsciiError::UnknownCharacter {
+                    character,
+                    line: line_number + 1,
+                    column: x + 1,
+                },
+            )?;
+            maze.set_tile_type(x, y, tile_type);
+        }
+    }
+    // Pillars look the same inside and outside of the maze, only the cells around them
+    // tell if they are part of the shape of a mask (see MazeMask::apply)
+    for (x, y) in maze.positions().collect::<Vec<(usize, usize)>>() {
+        if x % 2 != 1 && y % 2 != 1 && maze.is_wall(x, y) && !is_next_to_maze_cell(&maze, x, y) {
+            maze.set_tile_type(x, y, TileType::Border);
+        }
+    }
+    Ok(maze)
+}
+
+pub fn load_ascii_file<P: AsRef<Path>>(path: P) -> Result<Maze, AsciiError> {
+    maze_from_ascii(&fs::read_to_string(path)?)
+}
+
+pub fn save_ascii_file<P: AsRef<Path>>(maze: &Maze, path: P) -> Result<(), AsciiError> {
+    fs::write(path, maze_to_ascii(maze))?;
+    Ok(())
+}
+
+fn tile_to_char(tile_type: &TileType, x: usize, y: usize) -> char {
+    match tile_type {
+        TileType::Border => '#',
+        // Pillars can never be opened, so they look like the BORDER
+        TileType::Wall if x % 2 != 1 && y % 2 != 1 => '#',
+        TileType::Wall if y % 2 == 1 => '|',
+        TileType::Wall => '-',
+        TileType::Open => 'G',
+        TileType::Start => 'S',
+        TileType::Exit => 'E',
+        TileType::Room => 'R',
+        TileType::Bridge => '=',
+        TileType::LadderUp => 'U',
+        TileType::LadderDown => 'D',
+        TileType::DecoyExit => 'X',
+    }
+}
+
+// The outside of the maze is always BORDER, a # inside the maze is a pillar (WALL)
+// unless it's on a cell or between two cells. Then it's outside of the maze shape.
+// Pillars outside of the maze shape are turned into BORDER by maze_from_ascii.
+fn char_to_tile(
+    character: char,
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+) -> Option<TileType> {
+    let tile_type = match character {
+        '#' if x == 0 || y == 0 || x == width - 1 || y == height - 1 => TileType::Border,
+        '#' if x % 2 != 1 && y % 2 != 1 => TileType::Wall,
+        '#' => TileType::Border,
+        '|' | '-' => TileType::Wall,
+        'G' | '@' | ' ' => TileType::Open,
+        'S' => TileType::Start,
+        'E' => TileType::Exit,
+        'R' => TileType::Room,
+        '=' => TileType::Bridge,
+        'U' => TileType::LadderUp,
+        'D' => TileType::LadderDown,
+        'X' => TileType::DecoyExit,
+        _ => return None,
+    };
+    Some(tile_type)
+}
+
+// A pillar touches the four cells diagonal to it, outside of the maze shape they are all BORDER
+fn is_next_to_maze_cell(maze: &Maze, x: usize, y: usize) -> bool {
+    [
+        (x - 1, y - 1),
+        (x + 1, y - 1),
+        (x - 1, y + 1),
+        (x + 1, y + 1),
+    ]
+    .iter()
+    .any(|(cell_x, cell_y)| maze.tile_type(*cell_x, *cell_y) != Some(&TileType::Border))
+}
+
+// Position of the tile if there is only one of this type, otherwise how many there are
+fn find_only_tile(maze: &Maze, tile_type: &TileType) -> Result<(usize, usize), usize> {
+    let positions: Vec<(usize, usize)> = maze
+        .positions()
+        .filter(|(x, y)| maze.tile_type(*x, *y) == Some(tile_type))
+        .collect();
+
+    match positions.as_slice() {
+        [position] => Ok(*position),
+        _ => Err(positions.len()),
+    }
+}
+
+// Same as find_only_tile, but on all floors
+fn find_only_floor_tile(
+    maze: &MultiFloorMaze,
+    tile_type: &TileType,
+) -> Result<FloorPosition, usize> {
+    let positions: Vec<FloorPosition> = maze
+        .positions()
+        .filter(|position| maze.tile_type(position) == Some(tile_type))
+        .collect();
+
+    match positions.as_slice() {
+        [position] => Ok(*position),
+        _ => Err(positions.len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze_generator::{self, MazeAlgorithmType};
+    use crate::maze_mask::MazeMask;
+    use crate::maze_placement::PlacementStrategy;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn maze_survives_ascii() {
+        // Weave and decoys for BRIDGE and fake EXIT tiles
+        let mut rng = StdRng::seed_from_u64(3);
+        let (maze, _) = maze_generator::create_maze(
+            25,
+            21,
+            &None,
+            &MazeAlgorithmType::Weave,
+            30,
+            &PlacementStrategy::DecoyExits(2),
+            &mut rng,
+        );
+
+        let ascii = maze_to_ascii(&maze);
+        assert_eq!(maze_from_ascii(&ascii).unwrap(), maze);
+        assert_eq!(maze_to_ascii(&maze_from_ascii(&ascii).unwrap()), ascii);
+    }
+
+    #[test]
+    fn masked_maze_survives_ascii() {
+        // A plus sign, the corners are outside of the maze
+        let carvable = (0..25)
+            .map(|pixel| (1..4).contains(&(pixel % 5)) || (1..4).contains(&(pixel / 5)))
+            .collect();
+        let maze_mask = Some(MazeMask::new(5, 5, carvable));
+        let mut rng = StdRng::seed_from_u64(3);
+        let (maze, _) = maze_generator::create_maze(
+            21,
+            21,
+            &maze_mask,
+            &MazeAlgorithmType::RecursiveBacktracker,
+            0,
+            &PlacementStrategy::FarthestExit,
+            &mut rng,
+        );
+
+        let ascii = maze_to_ascii(&maze);
+        assert_eq!(maze_from_ascii(&ascii).unwrap(), maze);
+    }
+
+    #[test]
+    fn multi_floor_maze_survives_ascii() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let (maze, solution) = maze_generator::create_multi_floor_maze(
+            15,
+            15,
+            3,
+            &None,
+            &MazeAlgorithmType::RecursiveBacktracker,
+            0,
+            &mut rng,
+        );
+        // Otherwise every floor could be read on its own
+        assert_ne!(
+            solution.first().map(|(_, _, floor)| *floor),
+            solution.last().map(|(_, _, floor)| *floor)
+        );
+
+        let floors: Vec<String> = maze.floors().iter().map(maze_to_ascii).collect();
+        assert!(maze_from_ascii(&floors[0]).is_err());
+        assert_eq!(floors_from_ascii(&floors).unwrap(), maze);
+    }
+
+    #[test]
+    fn floors_need_the_same_size() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let (maze, _) = maze_generator::create_multi_floor_maze(
+            15,
+            15,
+            2,
+            &None,
+            &MazeAlgorithmType::RecursiveBacktracker,
+            0,
+            &mut rng,
+        );
+        let mut floors: Vec<String> = maze.floors().iter().map(maze_to_ascii).collect();
+        floors[1] = maze_to_ascii(&Maze::new(17, 15));
+
+        assert!(matches!(
+            floors_from_ascii(&floors),
+            Err(AsciiError::FloorSize { floor: 1, .. })
+        ));
+    }
+}
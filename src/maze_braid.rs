@@ -0,0 +1,146 @@
+use crate::maze::Maze;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+// 0% gives a perfect maze (only one way to the exit), 100% removes every dead end
+pub const MAX_LOOPINESS: u8 = 100;
+
+// Removes dead ends by opening one of their walls, which creates loops in the maze.
+// Loopiness is the chance in % that a dead end is removed.
+//
+// Only WALL tiles between two cells are opened, so we never open the BORDER.
+// Every 2 by 2 block of tiles has one pillar (even x and y) in it and pillars are never
+// opened, so this can't create open areas of 2 by 2 tiles either.
+pub fn braid_maze(maze: &mut Maze, loopiness: u8, rng: &mut StdRng) {
+    let loopiness = loopiness.min(MAX_LOOPINESS);
+    if loopiness == 0 {
+        return;
+    }
+
+    let mut dead_ends = get_dead_ends(maze);
+    dead_ends.shuffle(rng);
+
+    for dead_end in dead_ends {
+        // A dead end can already be removed if its neighbour was opened up before
+        if !is_dead_end(maze, &dead_end) || rng.gen_range(0..MAX_LOOPINESS) >= loopiness {
+            continue;
+        }
+
+        let closed_neighbours: Vec<(usize, usize)> = maze
+            .cell_neighbours(&dead_end)
+            .into_iter()
+            .filter(|other_cell| can_open_passage(maze, &dead_end, other_cell))
+            .collect();
+
+        // Opening up to another dead end removes two dead ends at once
+        let dead_end_neighbours: Vec<(usize, usize)> = closed_neighbours
+            .iter()
+            .filter(|other_cell| is_dead_end(maze, other_cell))
+            .copied()
+            .collect();
+
+        let other_cell = if dead_end_neighbours.is_empty() {
+            closed_neighbours.choose(rng)
+        } else {
+            dead_end_neighbours.choose(rng)
+        };
+
+        if let Some(other_cell) = other_cell {
+            maze.carve_passage(&dead_end, other_cell);
+        }
+    }
+}
+
+// All cells that only have one way in or out
+pub fn get_dead_ends(maze: &Maze) -> Vec<(usize, usize)> {
+    let (cells_x, cells_y) = maze.cell_count();
+    let mut dead_ends = Vec::<(usize, usize)>::new();
+
+    for y in 0..cells_y {
+        for x in 0..cells_x {
+            if is_dead_end(maze, &(x, y)) {
+                dead_ends.push((x, y));
+            }
+        }
+    }
+    dead_ends
+}
+
+pub fn is_dead_end(maze: &Maze, cell: &(usize, usize)) -> bool {
+    let (tile_x, tile_y) = Maze::cell_to_tile(cell);
+
+    maze.is_passable(tile_x, tile_y) && get_open_passage_count(maze, cell) == 1
+}
+
+// Number of open passages from this cell to the cells next to it
+pub fn get_open_passage_count(maze: &Maze, cell: &(usize, usize)) -> usize {
+    maze.cell_neighbours(cell)
+        .iter()
+        .filter_map(|other_cell| Maze::wall_between(cell, other_cell))
+        .filter(|(wall_x, wall_y)| maze.is_passable(*wall_x, *wall_y))
+        .count()
+}
+
+// Only a closed WALL between two cells where the player can walk can be opened
+fn can_open_passage(maze: &Maze, cell: &(usize, usize), other_cell: &(usize, usize)) -> bool {
+    let (other_x, other_y) = Maze::cell_to_tile(other_cell);
+
+    match Maze::wall_between(cell, other_cell) {
+        Some((wall_x, wall_y)) => {
+            maze.is_wall(wall_x, wall_y) && maze.is_passable(other_x, other_y)
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze_generator::{self, MazeAlgorithmType};
+    use crate::maze_mask::MazeMask;
+    use crate::maze_tile::TileType;
+    use rand::SeedableRng;
+
+    #[test]
+    fn braiding_never_opens_border_or_pillars() {
+        // A diamond, so there is BORDER inside the maze too
+        let carvable = (0..49)
+            .map(|pixel: i32| (pixel % 7 - 3).abs() + (pixel / 7 - 3).abs() <= 3)
+            .collect();
+        let maze_mask = Some(MazeMask::new(7, 7, carvable));
+        let mut rng = StdRng::seed_from_u64(8);
+        let (mut maze, _) = maze_generator::create_random_maze(
+            29,
+            29,
+            &maze_mask,
+            &MazeAlgorithmType::RecursiveBacktracker,
+            &mut rng,
+        );
+        let closed_tiles: Vec<(usize, usize)> = maze
+            .positions()
+            .filter(|(x, y)| {
+                maze.tile_type(*x, *y) == Some(&TileType::Border) || (x % 2 != 1 && y % 2 != 1)
+            })
+            .collect();
+
+        braid_maze(&mut maze, MAX_LOOPINESS, &mut rng);
+
+        assert!(get_dead_ends(&maze).is_empty());
+        for (x, y) in closed_tiles {
+            assert!(!maze.is_passable(x, y), "({}, {}) was opened", x, y);
+        }
+    }
+
+    #[test]
+    fn no_loopiness_keeps_the_maze() {
+        let mut rng = StdRng::seed_from_u64(8);
+        let (maze, _) =
+            maze_generator::create_random_maze(21, 21, &None, &MazeAlgorithmType::Prim, &mut rng);
+
+        let mut braided_maze = maze.clone();
+        braid_maze(&mut braided_maze, 0, &mut rng);
+        assert_eq!(braided_maze, maze);
+    }
+}
@@ -0,0 +1,293 @@
+use crate::maze::Maze;
+use crate::maze_braid;
+use crate::maze_generator::MazeAlgorithmType;
+use crate::maze_tile::TileType;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+// Chunk (x, y), chunk (0, 0) has the START. Chunks can also be west or south of it.
+pub type ChunkPosition = (i32, i32);
+// Tile (x, y) in the whole endless maze
+pub type WorldPosition = (i64, i64);
+
+// Cells in one row or column of a chunk
+pub const CHUNK_CELLS: usize = 16;
+// Tiles in one row or column of a chunk. A chunk owns the WALL on its west and south
+// side, the WALL on the east and north side belongs to the next chunk.
+pub const CHUNK_TILES: usize = 2 * CHUNK_CELLS;
+// Number of passages to the next chunk on every side
+const EDGE_OPENINGS: usize = 2;
+// Exit is this many chunks away from the start (horizontal plus vertical)
+pub const DEFAULT_EXIT_DISTANCE: u32 = 10;
+
+// Used to give every chunk and every side between two chunks its own seed
+const SEED_CHUNK: u64 = 1;
+const SEED_EAST_SIDE: u64 = 2;
+const SEED_NORTH_SIDE: u64 = 3;
+const SEED_EXIT: u64 = 4;
+
+// A maze without an end, made of chunks that are generated when they are needed.
+// Every chunk has its own seed (from the seed of the maze), so a chunk is the same
+// every time it is generated and we don't need to keep chunks we don't see.
+//
+// Every chunk is a maze on its own. The sides between two chunks get a few passages,
+// both chunks use the same seed for them so the passages always line up.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InfiniteMaze {
+    seed: u64,
+    maze_algorithm: MazeAlgorithmType,
+    loopiness: u8,
+    // In chunks from the start chunk
+    exit_distance: u32,
+    exit_chunk: ChunkPosition,
+    // Cell in the exit chunk
+    exit_cell: (usize, usize),
+}
+
+impl InfiniteMaze {
+    pub fn new(
+        seed: u64,
+        maze_algorithm: MazeAlgorithmType,
+        loopiness: u8,
+        exit_distance: u32,
+    ) -> Self {
+        let mut rng = StdRng::seed_from_u64(mix_seed(seed, 0, 0, SEED_EXIT));
+        // Any chunk at exactly this distance, but never the start chunk
+        let exit_distance = exit_distance.max(1);
+        let chunk_distance = exit_distance as i32;
+        let exit_x = rng.gen_range(-chunk_distance..=chunk_distance);
+        let exit_y = if rng.gen_bool(0.5) {
+            chunk_distance - exit_x.abs()
+        } else {
+            exit_x.abs() - chunk_distance
+        };
+        let exit_cell = (rng.gen_range(0..CHUNK_CELLS), rng.gen_range(0..CHUNK_CELLS));
+
+        Self {
+            seed,
+            maze_algorithm,
+            loopiness,
+            exit_distance,
+            exit_chunk: (exit_x, exit_y),
+            exit_cell,
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn exit_distance(&self) -> u32 {
+        self.exit_distance
+    }
+
+    pub fn exit_chunk(&self) -> ChunkPosition {
+        self.exit_chunk
+    }
+
+    pub fn start_position(&self) -> WorldPosition {
+        let (x, y) = Maze::cell_to_tile(&(CHUNK_CELLS / 2, CHUNK_CELLS / 2));
+        (x as i64, y as i64)
+    }
+
+    pub fn exit_position(&self) -> WorldPosition {
+        let (x, y) = Maze::cell_to_tile(&self.exit_cell);
+        let (origin_x, origin_y) = chunk_origin(&self.exit_chunk);
+        (origin_x + x as i64, origin_y + y as i64)
+    }
+
+    pub fn chunk_seed(&self, chunk: &ChunkPosition) -> u64 {
+        mix_seed(self.seed, chunk.0, chunk.1, SEED_CHUNK)
+    }
+
+    // The chunk with the WALLS on all four sides, so it is CHUNK_TILES + 1 tiles wide
+    // and high. Tile (0, 0) is at chunk_origin in the endless maze.
+    pub fn generate_chunk(&self, chunk: &ChunkPosition) -> Maze {
+        let mut rng = StdRng::seed_from_u64(self.chunk_seed(chunk));
+        let mut maze = Maze::new(CHUNK_TILES + 1, CHUNK_TILES + 1);
+
+        self.maze_algorithm
+            .get_algorithm()
+            .carve(&mut maze, &mut rng);
+        maze_braid::braid_maze(&mut maze, self.loopiness, &mut rng);
+
+        // Passages to the chunks around this one
+        for cell in self.side_openings(&(chunk.0 - 1, chunk.1), SEED_EAST_SIDE) {
+            maze.set_tile_type(0, 2 * cell + 1, TileType::Open);
+        }
+        for cell in self.side_openings(chunk, SEED_EAST_SIDE) {
+            maze.set_tile_type(CHUNK_TILES, 2 * cell + 1, TileType::Open);
+        }
+        for cell in self.side_openings(&(chunk.0, chunk.1 - 1), SEED_NORTH_SIDE) {
+            maze.set_tile_type(2 * cell + 1, 0, TileType::Open);
+        }
+        for cell in self.side_openings(chunk, SEED_NORTH_SIDE) {
+            maze.set_tile_type(2 * cell + 1, CHUNK_TILES, TileType::Open);
+        }
+
+        if *chunk == (0, 0) {
+            let (start_x, start_y) = self.start_position();
+            maze.set_tile_type(start_x as usize, start_y as usize, TileType::Start);
+        }
+        if *chunk == self.exit_chunk {
+            let (exit_x, exit_y) = Maze::cell_to_tile(&self.exit_cell);
+            maze.set_tile_type(exit_x, exit_y, TileType::Exit);
+        }
+        maze
+    }
+
+    // Cells along the east (or north) side of this chunk that have a passage
+    fn side_openings(&self, chunk: &ChunkPosition, side: u64) -> Vec<usize> {
+        let mut rng = StdRng::seed_from_u64(mix_seed(self.seed, chunk.0, chunk.1, side));
+        let cells: Vec<usize> = (0..CHUNK_CELLS).collect();
+
+        cells
+            .choose_multiple(&mut rng, EDGE_OPENINGS)
+            .copied()
+            .collect()
+    }
+}
+
+// Tile in the endless maze of tile (0, 0) of this chunk
+pub fn chunk_origin(chunk: &ChunkPosition) -> WorldPosition {
+    (
+        chunk.0 as i64 * CHUNK_TILES as i64,
+        chunk.1 as i64 * CHUNK_TILES as i64,
+    )
+}
+
+pub fn chunk_of_position(position: &WorldPosition) -> ChunkPosition {
+    (
+        position.0.div_euclid(CHUNK_TILES as i64) as i32,
+        position.1.div_euclid(CHUNK_TILES as i64) as i32,
+    )
+}
+
+// This chunk and all chunks at most radius chunks away (also diagonal)
+pub fn chunks_around(chunk: &ChunkPosition, radius: i32) -> Vec<ChunkPosition> {
+    (chunk.1 - radius..=chunk.1 + radius)
+        .flat_map(|y| (chunk.0 - radius..=chunk.0 + radius).map(move |x| (x, y)))
+        .collect()
+}
+
+// SplitMix64, so chunks next to each other get very different seeds
+fn mix_seed(seed: u64, x: i32, y: i32, salt: u64) -> u64 {
+    let mut value = seed
+        ^ (x as u32 as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        ^ (y as u32 as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F)
+        ^ salt.wrapping_mul(0x1656_67B1_9E37_79F9);
+
+    value = (value ^ (value >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    value = (value ^ (value >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    value ^ (value >> 31)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn infinite_maze(seed: u64) -> InfiniteMaze {
+        InfiniteMaze::new(
+            seed,
+            MazeAlgorithmType::RecursiveBacktracker,
+            20,
+            DEFAULT_EXIT_DISTANCE,
+        )
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_chunks() {
+        let maze = infinite_maze(42);
+
+        for chunk in chunks_around(&(0, 0), 2) {
+            assert_eq!(
+                maze.generate_chunk(&chunk),
+                infinite_maze(42).generate_chunk(&chunk)
+            );
+        }
+        assert_ne!(
+            maze.generate_chunk(&(1, 0)),
+            infinite_maze(43).generate_chunk(&(1, 0))
+        );
+        assert_ne!(maze.generate_chunk(&(1, 0)), maze.generate_chunk(&(0, 1)));
+    }
+
+    #[test]
+    fn seams_between_chunks_line_up() {
+        for seed in [0, 42, u64::MAX] {
+            let maze = infinite_maze(seed);
+
+            for (x, y) in chunks_around(&(0, 0), 2) {
+                let chunk = maze.generate_chunk(&(x, y));
+                let east_chunk = maze.generate_chunk(&(x + 1, y));
+                let north_chunk = maze.generate_chunk(&(x, y + 1));
+
+                let east_side: Vec<TileType> = chunk
+                    .column(CHUNK_TILES)
+                    .map(|tile| tile.tile_type.clone())
+                    .collect();
+                let west_side: Vec<TileType> = east_chunk
+                    .column(0)
+                    .map(|tile| tile.tile_type.clone())
+                    .collect();
+                assert_eq!(east_side, west_side, "{} {:?}", seed, (x, y));
+
+                let north_side: Vec<TileType> = chunk
+                    .row(CHUNK_TILES)
+                    .map(|tile| tile.tile_type.clone())
+                    .collect();
+                let south_side: Vec<TileType> = north_chunk
+                    .row(0)
+                    .map(|tile| tile.tile_type.clone())
+                    .collect();
+                assert_eq!(north_side, south_side, "{} {:?}", seed, (x, y));
+
+                let openings = east_side
+                    .iter()
+                    .filter(|tile_type| **tile_type == TileType::Open)
+                    .count();
+                assert_eq!(openings, EDGE_OPENINGS);
+            }
+        }
+    }
+
+    #[test]
+    fn exit_is_at_the_exit_distance() {
+        for seed in 0..20 {
+            let maze = infinite_maze(seed);
+            let (exit_x, exit_y) = maze.exit_chunk();
+
+            assert_eq!(
+                exit_x.unsigned_abs() + exit_y.unsigned_abs(),
+                DEFAULT_EXIT_DISTANCE
+            );
+            assert_eq!(chunk_of_position(&maze.exit_position()), maze.exit_chunk());
+        }
+
+        let maze = infinite_maze(42);
+        let (start_x, start_y) = maze.start_position();
+        assert_eq!(
+            maze.generate_chunk(&(0, 0))
+                .tile_type(start_x as usize, start_y as usize),
+            Some(&TileType::Start)
+        );
+        assert_eq!(
+            InfiniteMaze::new(42, MazeAlgorithmType::RecursiveBacktracker, 0, 0).exit_distance(),
+            1
+        );
+    }
+
+    #[test]
+    fn positions_west_and_south_of_the_start_are_in_negative_chunks() {
+        let chunk_tiles = CHUNK_TILES as i64;
+
+        assert_eq!(chunk_of_position(&(0, 0)), (0, 0));
+        assert_eq!(chunk_of_position(&(chunk_tiles - 1, chunk_tiles)), (0, 1));
+        assert_eq!(chunk_of_position(&(-1, -chunk_tiles)), (-1, -1));
+        assert_eq!(chunk_of_position(&(-chunk_tiles - 1, 0)), (-2, 0));
+        assert_eq!(chunk_origin(&(-2, 1)), (-2 * chunk_tiles, chunk_tiles));
+        assert_eq!(chunks_around(&(3, -3), 1).len(), 9);
+    }
+}
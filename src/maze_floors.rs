@@ -0,0 +1,243 @@
+use crate::maze::Maze;
+use crate::maze_solver::{self, Walkable};
+use crate::maze_tile::TileType;
+
+// Position (x, y, floor), the first floor is 0
+pub type FloorPosition = (usize, usize, usize);
+
+// More floors make the maze a lot harder (and slower to generate)
+pub const MAX_FLOORS: usize = 5;
+
+// A maze with floors on top of each other. Every floor is a normal Maze with the
+// same size and LADDER tiles go to the same (x, y) on the floor above or below.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MultiFloorMaze {
+    floors: Vec<Maze>,
+}
+
+impl MultiFloorMaze {
+    // All floors need to have the same width and height
+    pub fn from_floors(floors: Vec<Maze>) -> Self {
+        assert!(
+            floors
+                .windows(2)
+                .all(|pair| pair[0].width() == pair[1].width()
+                    && pair[0].height() == pair[1].height()),
+            "All floors need to have the same size"
+        );
+
+        Self { floors }
+    }
+
+    pub fn width(&self) -> usize {
+        self.floors.first().map_or(0, |maze| maze.width())
+    }
+
+    pub fn height(&self) -> usize {
+        self.floors.first().map_or(0, |maze| maze.height())
+    }
+
+    pub fn floor_count(&self) -> usize {
+        self.floors.len()
+    }
+
+    pub fn floor(&self, floor: usize) -> Option<&Maze> {
+        self.floors.get(floor)
+    }
+
+    pub fn floor_mut(&mut self, floor: usize) -> Option<&mut Maze> {
+        self.floors.get_mut(floor)
+    }
+
+    pub fn floors(&self) -> &[Maze] {
+        &self.floors
+    }
+
+    pub fn tile_type(&self, position: &FloorPosition) -> Option<&TileType> {
+        let (x, y, floor) = *position;
+        self.floor(floor).and_then(|maze| maze.tile_type(x, y))
+    }
+
+    // Does nothing if the position is outside of the maze
+    pub fn set_tile_type(&mut self, position: &FloorPosition, tile_type: TileType) {
+        let (x, y, floor) = *position;
+        if let Some(maze) = self.floor_mut(floor) {
+            maze.set_tile_type(x, y, tile_type);
+        }
+    }
+
+    pub fn is_passable(&self, position: &FloorPosition) -> bool {
+        let (x, y, floor) = *position;
+        self.floor(floor).is_some_and(|maze| maze.is_passable(x, y))
+    }
+
+    // Where we end up if we take the ladder on this position, None if there is no ladder
+    pub fn ladder_destination(&self, position: &FloorPosition) -> Option<FloorPosition> {
+        let (x, y, floor) = *position;
+        let destination = match self.tile_type(position) {
+            Some(TileType::LadderUp) => (x, y, floor + 1),
+            Some(TileType::LadderDown) if floor > 0 => (x, y, floor - 1),
+            _ => return None,
+        };
+
+        if self.is_passable(&destination) {
+            Some(destination)
+        } else {
+            None
+        }
+    }
+
+    // All positions next to this one on the same floor where the player can walk,
+    // and the other end of the ladder if there is one
+    pub fn passable_neighbours(&self, position: &FloorPosition) -> Vec<FloorPosition> {
+        let (x, y, floor) = *position;
+        let mut neighbours: Vec<FloorPosition> = match self.floor(floor) {
+            Some(maze) => maze
+                .passable_neighbours(x, y)
+                .into_iter()
+                .map(|(next_x, next_y)| (next_x, next_y, floor))
+                .collect(),
+            None => Vec::new(),
+        };
+
+        if let Some(destination) = self.ladder_destination(position) {
+            neighbours.push(destination);
+        }
+        neighbours
+    }
+
+    // All positions (x, y, floor) floor by floor and row by row
+    pub fn positions(&self) -> impl Iterator<Item = FloorPosition> + '_ {
+        self.floors
+            .iter()
+            .enumerate()
+            .flat_map(|(floor, maze)| maze.positions().map(move |(x, y)| (x, y, floor)))
+    }
+
+    // Position of the first tile with this TileType
+    pub fn find_tile(&self, tile_type: &TileType) -> Option<FloorPosition> {
+        self.positions()
+            .find(|position| self.tile_type(position) == Some(tile_type))
+    }
+}
+
+impl Walkable for MultiFloorMaze {
+    type Position = FloorPosition;
+
+    fn position_count(&self) -> usize {
+        self.width() * self.height() * self.floor_count()
+    }
+
+    fn position_index(&self, position: &FloorPosition) -> usize {
+        let (x, y, floor) = *position;
+        (floor * self.height() + y) * self.width() + x
+    }
+
+    fn is_passable_position(&self, position: &FloorPosition) -> bool {
+        self.is_passable(position)
+    }
+
+    fn passable_neighbours_of(&self, position: &FloorPosition) -> Vec<FloorPosition> {
+        self.passable_neighbours(position)
+    }
+
+    // Every ladder is one step, so we need at least one step for every floor
+    fn estimated_steps(&self, position: &FloorPosition, other_position: &FloorPosition) -> usize {
+        maze_solver::get_manhattan_distance(
+            &(position.0, position.1),
+            &(other_position.0, other_position.1),
+        ) + position.2.abs_diff(other_position.2)
+    }
+
+    fn max_step_distance(&self) -> usize {
+        self.floors
+            .iter()
+            .map(|maze| maze.max_step_distance())
+            .max()
+            .unwrap_or(1)
+    }
+
+    // A ladder goes straight to the other floor, only a BRIDGE is skipped
+    fn tiles_between(
+        &self,
+        position: &FloorPosition,
+        other_position: &FloorPosition,
+    ) -> Vec<FloorPosition> {
+        let floor = position.2;
+        match self.floor(floor) {
+            Some(maze) if other_position.2 == floor => maze
+                .tiles_between(
+                    &(position.0, position.1),
+                    &(other_position.0, other_position.1),
+                )
+                .into_iter()
+                .map(|(x, y)| (x, y, floor))
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Two floors of 5 x 3 tiles, every floor has the cells (1, 1) and (3, 1) with a wall between
+    fn two_floors() -> MultiFloorMaze {
+        MultiFloorMaze::from_floors(vec![Maze::new(5, 3), Maze::new(5, 3)])
+    }
+
+    #[test]
+    fn ladder_goes_to_the_same_tile_on_the_other_floor() {
+        let mut maze = two_floors();
+        maze.set_tile_type(&(3, 1, 0), TileType::LadderUp);
+        maze.set_tile_type(&(3, 1, 1), TileType::LadderDown);
+
+        assert_eq!(maze.ladder_destination(&(3, 1, 0)), Some((3, 1, 1)));
+        assert_eq!(maze.ladder_destination(&(3, 1, 1)), Some((3, 1, 0)));
+        assert_eq!(maze.ladder_destination(&(1, 1, 0)), None);
+        assert_eq!(maze.passable_neighbours(&(3, 1, 0)), vec![(3, 1, 1)]);
+    }
+
+    #[test]
+    fn ladder_connects_the_floors() {
+        let mut maze = two_floors();
+        maze.set_tile_type(&(2, 1, 0), TileType::Open);
+        maze.set_tile_type(&(3, 1, 0), TileType::LadderUp);
+        maze.set_tile_type(&(3, 1, 1), TileType::LadderDown);
+        maze.set_tile_type(&(2, 1, 1), TileType::Open);
+
+        let path = maze_solver::path_to_farthest_tile(&maze, &(1, 1, 0));
+        assert_eq!(
+            path,
+            vec![
+                (1, 1, 0),
+                (2, 1, 0),
+                (3, 1, 0),
+                (3, 1, 1),
+                (2, 1, 1),
+                (1, 1, 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn ladder_without_a_floor_goes_nowhere() {
+        let mut maze = two_floors();
+        // Up from the top floor, down from the first floor and up into a wall
+        maze.set_tile_type(&(1, 1, 1), TileType::LadderUp);
+        maze.set_tile_type(&(1, 1, 0), TileType::LadderDown);
+        maze.set_tile_type(&(3, 1, 0), TileType::LadderUp);
+        maze.set_tile_type(&(3, 1, 1), TileType::Wall);
+
+        assert_eq!(maze.ladder_destination(&(1, 1, 1)), None);
+        assert_eq!(maze.ladder_destination(&(1, 1, 0)), None);
+        assert_eq!(maze.ladder_destination(&(3, 1, 0)), None);
+    }
+
+    #[test]
+    #[should_panic(expected = "All floors need to have the same size")]
+    fn floors_need_the_same_size() {
+        MultiFloorMaze::from_floors(vec![Maze::new(5, 3), Maze::new(5, 5)]);
+    }
+}
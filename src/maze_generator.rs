@@ -1,523 +1,651 @@
-use crate::amazingly_lost_data::AmazinglyLostData;
-
-use crate::maze_tile::{MazeTile, TileType};
-
-use bevy::prelude::*;
-use bevy::render::camera::Camera;
-use rand::Rng;
-
-pub const SPRITE_SIZE_MAZE: usize = 100;
-pub const NEXT_OPEN_WALL: usize = 15;
-pub const DISTANCE_FROM_EXIT: usize = 10;
+use crate::maze::Maze;
+use crate::maze_algorithms;
+use crate::maze_analysis::{self, Difficulty, MazeRating};
+use crate::maze_braid;
+use crate::maze_floors::{FloorPosition, MultiFloorMaze, MAX_FLOORS};
+use crate::maze_mask::MazeMask;
+use crate::maze_placement::{self, PlacementStrategy};
+use crate::maze_rooms::{self, RoomSettings};
+use crate::maze_solver;
+use crate::maze_tile::TileType;
+use crate::maze_topology::{self, CellMaze, GridTopology};
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
 
 pub const SMALL_MAZE: u16 = 33u16;
 pub const MEDIUM_MAZE: u16 = 77u16;
 pub const LARGE_MAZE: u16 = 121u16;
 pub const VERY_VERY_LARGE_MAZE: u16 = 231u16;
 
-#[derive(Clone, Debug, Eq, PartialEq)]
-pub enum CollisionType {
-    CollisionWall,
-    CollisionExit,
-    CollisionStart,
+// Smallest maze that still has a start and an exit on different cells
+pub const MIN_MAZE_SIZE_X_OR_Y: u16 = 5u16;
+// To be sure we don't go over a certain size for the maze -> minus 100
+pub const MAX_MAZE_SIZE_X_OR_Y: u16 = u16::MAX - 100;
+
+// Named sizes (width, height) for the maze
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MazeSizePreset {
+    Small,
+    Medium,
+    Large,
+    VeryVeryLarge,
 }
 
-// Collision structs
-#[derive(Clone, Debug, PartialEq)]
-pub struct CollisionTile {
-    pub collision_type: CollisionType,
-}
-
-// A MovingTile and LockedTile are both a GameTile
-pub struct GameTile;
-pub struct SolutionTile;
-pub struct PlayerTile;
-
-pub fn create_new_maze(
-    mut commands: &mut Commands,
-    mut amazing_data: &mut ResMut<AmazinglyLostData>,
-    camera_query: &mut Query<(&mut Transform, &Camera)>,
-) {
-    let (mut maze, solution) = create_random_maze(
-        amazing_data.maze_size.0 as usize,
-        amazing_data.maze_size.1 as usize,
-    );
-
-    create_random_open_walls(&mut maze, &solution);
-
-    // Make the maze visible for the player
-    paint_the_maze(&mut commands, &solution, &mut maze, &mut amazing_data);
-
-    place_camera_on_starting_tile(camera_query, &mut amazing_data);
-    // Place player and camera on the same position
-
-    place_player_in_maze(&mut commands, &mut amazing_data);
+impl MazeSizePreset {
+    pub fn get_size(&self) -> (u16, u16) {
+        match self {
+            MazeSizePreset::Small => (SMALL_MAZE, SMALL_MAZE),
+            MazeSizePreset::Medium => (MEDIUM_MAZE, MEDIUM_MAZE),
+            MazeSizePreset::Large => (LARGE_MAZE, LARGE_MAZE),
+            MazeSizePreset::VeryVeryLarge => (VERY_VERY_LARGE_MAZE, VERY_VERY_LARGE_MAZE),
+        }
+    }
 
-    // Set player starting position here. Is needed for camera/player placement
-    let starting_point = *solution.first().unwrap();
-    amazing_data.player.position_x = starting_point.0 as f32;
-    amazing_data.player.position_y = starting_point.1 as f32;
+    pub fn name(&self) -> &'static str {
+        match self {
+            MazeSizePreset::Small => "Small",
+            MazeSizePreset::Medium => "Medium",
+            MazeSizePreset::Large => "Large",
+            MazeSizePreset::VeryVeryLarge => "Very very large",
+        }
+    }
 
-    amazing_data.exit_point_game = *solution.last().unwrap();
+    // Used to go through all the sizes in the game menu
+    pub fn next(&self) -> Self {
+        match self {
+            MazeSizePreset::Small => MazeSizePreset::Medium,
+            MazeSizePreset::Medium => MazeSizePreset::Large,
+            MazeSizePreset::Large => MazeSizePreset::VeryVeryLarge,
+            MazeSizePreset::VeryVeryLarge => MazeSizePreset::Small,
+        }
+    }
 
-    // set solution for this maze
-    amazing_data.maze_solution = solution;
+    // None if the size is not one of the presets
+    pub fn from_size(size: (u16, u16)) -> Option<Self> {
+        [
+            MazeSizePreset::Small,
+            MazeSizePreset::Medium,
+            MazeSizePreset::Large,
+            MazeSizePreset::VeryVeryLarge,
+        ]
+        .iter()
+        .find(|preset| preset.get_size() == size)
+        .copied()
+    }
 }
 
-fn create_random_maze(width: usize, height: usize) -> (Vec<Vec<MazeTile>>, Vec<(usize, usize)>) {
-    // Contains positions (x, y) we use this for random start point and later on
-    // to save the start to end point solution
-    let mut solution_path = Vec::<(usize, usize)>::new();
-
-    // Use tmp_maze_path to get all usable starting points
-    let (mut maze, mut pos_x, mut pos_y) = initialize_maze_size(&width, &height);
-
-    // Starting position saved for solution
-    solution_path.push((pos_x, pos_y));
-
-    set_maze_tile_for_game(&mut pos_x, &mut pos_y, &mut maze, &mut solution_path);
-
-    // Last tile is the exit
-    let (last_x, last_y) = *solution_path.last().unwrap();
-    maze[last_y][last_x].tile_type = TileType::Exit;
-
-    (maze, solution_path)
+// The width and height of a maze need to be odd, so there is a cell or a BORDER
+// at every odd position. Even sizes get one bigger (or smaller if that's too big).
+pub fn get_valid_maze_size(width: usize, height: usize) -> (usize, usize) {
+    (get_valid_maze_length(width), get_valid_maze_length(height))
 }
 
-// To make the maze a bit more difficult, we open up more walls so we have less obvious paths.
-fn create_random_open_walls(maze: &mut Vec<Vec<MazeTile>>, solution_path: &Vec<(usize, usize)>) {
-    let (exit_x, exit_y) = *solution_path.last().unwrap();
-    let mut next_tile = NEXT_OPEN_WALL;
-
-    loop {
-        if next_tile < solution_path.len() {
-            let (pos_x, pos_y) = *solution_path.get(next_tile).unwrap();
-
-            // If we are to close to the exit then continue with another tile
-            // Untill we are far enough from it
-            if !((pos_y < exit_y && (exit_y - pos_y) > DISTANCE_FROM_EXIT)
-                || (pos_y > exit_y && (pos_y - exit_y) > DISTANCE_FROM_EXIT))
-                && !((pos_x < exit_x && (exit_x - pos_x) > DISTANCE_FROM_EXIT)
-                    || (pos_x > exit_x && (pos_x - exit_x) > DISTANCE_FROM_EXIT))
-            {
-                next_tile += 1;
-                continue;
-            }
-
-            // Wall North
-            if maze[pos_y + 1][pos_x].tile_type == TileType::Wall
-                // Only open walls that are not between a OPEN tile. No use to open it up
-                && maze[pos_y + 1][pos_x - 1].tile_type == TileType::Wall
-                && maze[pos_y + 1][pos_x + 1].tile_type == TileType::Wall
-            {
-                maze[pos_y + 1][pos_x].tile_type = TileType::Open;
-
-                // Also check if the tile past this this wall is a wall.
-                // Then we hit a T-junction and also need to open this wall
-                if maze[pos_y + 2][pos_x].tile_type == TileType::Wall {
-                    maze[pos_y + 2][pos_x].tile_type = TileType::Open;
-                }
-
-                // Wall South
-            } else if maze[pos_y - 1][pos_x].tile_type == TileType::Wall
-                // Only open walls that are not between a OPEN tile. No use to open it up
-                && maze[pos_y - 1][pos_x - 1].tile_type == TileType::Wall
-                && maze[pos_y - 1][pos_x + 1].tile_type == TileType::Wall
-            {
-                maze[pos_y - 1][pos_x].tile_type = TileType::Open;
-
-                // Also check if the tile past this this wall is a wall.
-                // Then we hit a T-junction
-                if maze[pos_y - 2][pos_x].tile_type == TileType::Wall {
-                    maze[pos_y - 2][pos_x].tile_type = TileType::Open;
-                }
-
-                // Wall East
-            } else if maze[pos_y][pos_x + 1].tile_type == TileType::Wall
-                // Only open walls that are not between a OPEN tile. No use to open it up
-                && maze[pos_y - 1][pos_x + 1].tile_type == TileType::Wall
-                && maze[pos_y + 1][pos_x + 1].tile_type == TileType::Wall
-            {
-                maze[pos_y][pos_x + 1].tile_type = TileType::Open;
+fn get_valid_maze_length(length: usize) -> usize {
+    let min_length = MIN_MAZE_SIZE_X_OR_Y as usize;
+    let max_length = MAX_MAZE_SIZE_X_OR_Y as usize;
+    let length = length.clamp(min_length, max_length);
+
+    if length % 2 == 1 {
+        length
+    } else if length < max_length {
+        length + 1
+    } else {
+        length - 1
+    }
+}
 
-                // Also check if the tile past this this wall is a wall.
-                // Then we hit a T-junction
-                if maze[pos_y][pos_x + 2].tile_type == TileType::Wall {
-                    maze[pos_y][pos_x + 2].tile_type = TileType::Open;
-                }
+// How many mazes we try to get one with the requested difficulty
+pub const MAX_DIFFICULTY_TRIES: usize = 20;
 
-                // Wall West
-            } else if maze[pos_y][pos_x - 1].tile_type == TileType::Wall
-                // Only open walls that are not between a OPEN tile. No use to open it up
-                && maze[pos_y - 1][pos_x - 1].tile_type == TileType::Wall
-                && maze[pos_y + 1][pos_x - 1].tile_type == TileType::Wall
-            {
-                maze[pos_y][pos_x - 1].tile_type = TileType::Open;
+// Every maze generation algorithm only opens up (or closes) the WALL tiles between cells.
+// The start, exit and solution are found afterwards, so this works for every algorithm.
+pub trait MazeAlgorithm {
+    fn name(&self) -> &'static str;
+    fn carve(&self, maze: &mut Maze, rng: &mut StdRng);
+}
 
-                // Also check if the tile past this this wall is a wall.
-                // Then we hit a T-junction
-                if maze[pos_y][pos_x - 2].tile_type == TileType::Wall {
-                    maze[pos_y][pos_x - 2].tile_type = TileType::Open;
-                }
+// All algorithms that can be selected in the game
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum MazeAlgorithmType {
+    RecursiveBacktracker,
+    Kruskal,
+    Prim,
+    Wilson,
+    Eller,
+    HuntAndKill,
+    GrowingTree,
+    Sidewinder,
+    BinaryTree,
+    RecursiveDivision,
+    Weave,
+}
 
-                // No wall to open, then find another wall at another tile
-            } else {
-                next_tile += 1;
-                continue;
+impl MazeAlgorithmType {
+    pub fn get_algorithm(&self) -> Box<dyn MazeAlgorithm> {
+        match self {
+            MazeAlgorithmType::RecursiveBacktracker => {
+                Box::new(maze_algorithms::RecursiveBacktracker)
             }
-
-            next_tile += NEXT_OPEN_WALL;
-        } else {
-            // Can't break open more walls
-            break;
+            MazeAlgorithmType::Kruskal => Box::new(maze_algorithms::Kruskal),
+            MazeAlgorithmType::Prim => Box::new(maze_algorithms::Prim),
+            MazeAlgorithmType::Wilson => Box::new(maze_algorithms::Wilson),
+            MazeAlgorithmType::Eller => Box::new(maze_algorithms::Eller),
+            MazeAlgorithmType::HuntAndKill => Box::new(maze_algorithms::HuntAndKill),
+            MazeAlgorithmType::GrowingTree => Box::new(maze_algorithms::GrowingTree),
+            MazeAlgorithmType::Sidewinder => Box::new(maze_algorithms::Sidewinder),
+            MazeAlgorithmType::BinaryTree => Box::new(maze_algorithms::BinaryTree),
+            MazeAlgorithmType::RecursiveDivision => Box::new(maze_algorithms::RecursiveDivision),
+            MazeAlgorithmType::Weave => Box::new(maze_algorithms::Weave),
         }
     }
-}
 
-fn set_maze_tile_for_game(
-    pos_x: &mut usize,
-    pos_y: &mut usize,
-    maze: &mut Vec<Vec<MazeTile>>,
-    solution_path: &mut Vec<(usize, usize)>,
-) {
-    // But first make tmp_path equal to the solution_path so we have the starting point
-    let mut tmp_path = Vec::<(usize, usize)>::new();
-    tmp_path.clone_from(solution_path);
-
-    loop {
-        let (next_x, next_y, wall_x, wall_y) = get_random_directions(pos_x, pos_y, maze);
-
-        if maze[next_y][next_x].id == maze[*pos_y][*pos_x].id {
-            // In this case we can't continue to another tile and need to go back one step
-            // one step in the tmp_maze_path and check that one for a new direction.
-            if tmp_path.len() > 1 {
-                // Remove last path tile
-                tmp_path.pop().unwrap();
-                // And another that contains the position of a WALL and can't be used for directions
-                tmp_path.pop().unwrap();
-
-                // Get last in list for new position to look at
-                let last_x_y = tmp_path.last().unwrap(); // {
-                *pos_x = last_x_y.0;
-                *pos_y = last_x_y.1;
-            } else {
-                // This happens if we have used all the tiles
-                // and only have the starting point in the tmp_path
-                break;
-            }
-            continue;
+    // Used to go through all the algorithms in the game
+    pub fn next(&self) -> Self {
+        match self {
+            MazeAlgorithmType::RecursiveBacktracker => MazeAlgorithmType::Kruskal,
+            MazeAlgorithmType::Kruskal => MazeAlgorithmType::Prim,
+            MazeAlgorithmType::Prim => MazeAlgorithmType::Wilson,
+            MazeAlgorithmType::Wilson => MazeAlgorithmType::Eller,
+            MazeAlgorithmType::Eller => MazeAlgorithmType::HuntAndKill,
+            MazeAlgorithmType::HuntAndKill => MazeAlgorithmType::GrowingTree,
+            MazeAlgorithmType::GrowingTree => MazeAlgorithmType::Sidewinder,
+            MazeAlgorithmType::Sidewinder => MazeAlgorithmType::BinaryTree,
+            MazeAlgorithmType::BinaryTree => MazeAlgorithmType::RecursiveDivision,
+            MazeAlgorithmType::RecursiveDivision => MazeAlgorithmType::Weave,
+            MazeAlgorithmType::Weave => MazeAlgorithmType::RecursiveBacktracker,
         }
+    }
+}
 
-        maze[next_y][next_x].can_be_used = false;
+// Create a complete maze with the start, exit and loops (loopiness in %, see maze_braid).
+// Gives the maze and the solution from the start to the exit.
+// With a mask the maze only uses the white part of it (see maze_mask).
+pub fn create_maze(
+    width: usize,
+    height: usize,
+    maze_mask: &Option<MazeMask>,
+    maze_algorithm: &MazeAlgorithmType,
+    loopiness: u8,
+    placement: &PlacementStrategy,
+    rng: &mut StdRng,
+) -> (Maze, Vec<(usize, usize)>) {
+    let (mut maze, start) = create_random_maze(width, height, maze_mask, maze_algorithm, rng);
+
+    maze_braid::braid_maze(&mut maze, loopiness, rng);
+
+    // After the loops, so the EXIT is still the farthest away and the decoys are dead ends.
+    // Contains positions (x, y) from the start to the end point.
+    let solution = maze_placement::place_start_and_exit(&mut maze, &start, placement, rng);
+
+    (maze, solution)
+}
 
-        // Add WALL and next tile to the tmp_path
-        tmp_path.push((wall_x, wall_y));
-        tmp_path.push((next_x, next_y));
-        *pos_x = next_x;
-        *pos_y = next_y;
+// Create a maze with more floors on top of each other (at most MAX_FLOORS).
+// Every floor has one LADDER up to the next floor, so it's still a perfect maze
+// before the loops are added. The START is on the first floor and the EXIT is the
+// tile farthest away from it, which is almost always on another floor.
+pub fn create_multi_floor_maze(
+    width: usize,
+    height: usize,
+    floor_count: usize,
+    maze_mask: &Option<MazeMask>,
+    maze_algorithm: &MazeAlgorithmType,
+    loopiness: u8,
+    rng: &mut StdRng,
+) -> (MultiFloorMaze, Vec<FloorPosition>) {
+    let (width, height) = get_valid_maze_size(width, height);
+    let floor_count = floor_count.clamp(1, MAX_FLOORS);
+
+    let mut floors = Vec::<Maze>::with_capacity(floor_count);
+    for _ in 0..floor_count {
+        let mut maze = Maze::new(width, height);
+        if let Some(maze_mask) = maze_mask {
+            maze_mask.apply(&mut maze);
+        }
 
-        // If tmp_path is longer than the solution then overwrite the solution
-        // This way we get the longest path from start to end point
-        if tmp_path.len() > solution_path.len() {
-            solution_path.clone_from(&tmp_path);
+        maze_algorithm.get_algorithm().carve(&mut maze, rng);
+        connect_separate_parts(&mut maze, rng);
+        maze_braid::braid_maze(&mut maze, loopiness, rng);
+        floors.push(maze);
+    }
+    // The START is picked before the LADDERS, so a first floor with only one cell keeps
+    // it. Without an OPEN cell (only a BRIDGE left) the START goes on any usable cell.
+    let (start_x, start_y) = match get_open_cells(&floors[0]).choose(rng) {
+        Some(&tile) => tile,
+        None => Maze::cell_to_tile(&maze_algorithms::random_cell(&floors[0], rng)),
+    };
+    floors[0].set_tile_type(start_x, start_y, TileType::Start);
+    let start = (start_x, start_y, 0);
+    let mut maze = MultiFloorMaze::from_floors(floors);
+
+    for floor in 0..floor_count - 1 {
+        // Not on the LADDER that comes up from the floor below and not under a BRIDGE
+        let ladder_cells: Vec<(usize, usize)> = maze
+            .floor(floor)
+            .map(get_open_cells)
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|(x, y)| maze.tile_type(&(*x, *y, floor + 1)) == Some(&TileType::Open))
+            .collect();
+        if let Some(&(x, y)) = ladder_cells.choose(rng) {
+            maze.set_tile_type(&(x, y, floor), TileType::LadderUp);
+            maze.set_tile_type(&(x, y, floor + 1), TileType::LadderDown);
         }
     }
+
+    // Ladders are never the farthest away, there is always more maze behind them
+    let solution = maze_solver::path_to_farthest_tile(&maze, &start);
+    if let Some(exit) = solution.last() {
+        maze.set_tile_type(exit, TileType::Exit);
+    }
+
+    (maze, solution)
 }
 
-fn get_random_directions(
-    pos_x: &usize,
-    pos_y: &usize,
-    maze: &mut Vec<Vec<MazeTile>>,
-) -> (usize, usize, usize, usize) {
-    // Keep track of what direction we can use
-    let mut directions = Vec::<(usize, usize, usize, usize)>::new();
+// Tiles of the usable cells of a floor that are still OPEN
+fn get_open_cells(floor_maze: &Maze) -> Vec<(usize, usize)> {
+    floor_maze
+        .usable_cells()
+        .iter()
+        .map(Maze::cell_to_tile)
+        .filter(|(x, y)| floor_maze.tile_type(*x, *y) == Some(&TileType::Open))
+        .collect()
+}
 
-    // Don't use &mut maze[y][x] because we would need it twice for current_tile and
-    // the direction tile and that causes problem
+// Create a dungeon: rooms first (see maze_rooms) and then maze corridors between them.
+// Every room gets at least one door, loops (loopiness in %) give some rooms more doors.
+pub fn create_rooms_maze(
+    width: usize,
+    height: usize,
+    maze_mask: &Option<MazeMask>,
+    room_settings: &RoomSettings,
+    loopiness: u8,
+    placement: &PlacementStrategy,
+    rng: &mut StdRng,
+) -> (Maze, Vec<(usize, usize)>) {
+    let (width, height) = get_valid_maze_size(width, height);
+    let mut maze = Maze::new(width, height);
+    if let Some(maze_mask) = maze_mask {
+        maze_mask.apply(&mut maze);
+    }
 
-    // If we go to a position that is a WALL, then we know that on the other side is
-    // an OPEN tile. If the position is a BORDER, then on the other side is nothing
+    maze_rooms::place_rooms(&mut maze, room_settings, rng);
+    maze_rooms::carve_corridors(&mut maze, rng);
+    // Opens one door for every room and connects the corridors
+    connect_separate_parts(&mut maze, rng);
+    maze_braid::braid_maze(&mut maze, loopiness, rng);
 
-    // TODO:RG X and Y are switched
+    // The START can also be in a room
+    let start = Maze::cell_to_tile(&maze_algorithms::random_cell(&maze, rng));
+    let solution = maze_placement::place_start_and_exit(&mut maze, &start, placement, rng);
 
-    if maze[*pos_y + 1][*pos_x].tile_type == TileType::Wall {
-        // Check if we can use the tile on the other side
-        if maze[*pos_y + 2][*pos_x].can_be_used {
-            // Give the position of the WALL that can be set to OPEN
-            // and give next tile position
-            directions.push((*pos_x, *pos_y + 1, *pos_x, *pos_y + 2));
-        }
-    }
+    (maze, solution)
+}
 
-    if maze[*pos_y - 1][*pos_x].tile_type == TileType::Wall {
-        // Check if we can use the tile on the other side
-        if maze[*pos_y - 2][*pos_x].can_be_used {
-            // Give the position of the WALL that can be set to OPEN
-            // and give next tile position
-            directions.push((*pos_x, *pos_y - 1, *pos_x, *pos_y - 2));
-        }
-    }
+// Create a maze with hexagonal or triangular cells (see maze_topology).
+// The size is in tiles like the other mazes, so it gets about the same number of cells.
+// Gives the maze and the solution from the start cell to the exit cell.
+pub fn create_cell_maze(
+    width: usize,
+    height: usize,
+    topology: GridTopology,
+    loopiness: u8,
+    rng: &mut StdRng,
+) -> (CellMaze, Vec<(usize, usize)>) {
+    let (width, height) = get_valid_maze_size(width, height);
+    let mut maze = CellMaze::new(topology, (width - 1) / 2, (height - 1) / 2);
+
+    maze_topology::carve_cell_maze(&mut maze, rng);
+    maze_topology::braid_cell_maze(&mut maze, loopiness, rng);
+
+    // The cell that is the farthest away from the start is the exit
+    let start = *maze
+        .cells()
+        .collect::<Vec<(usize, usize)>>()
+        .choose(rng)
+        .unwrap();
+    let longest_path = maze_solver::path_to_farthest_tile(&maze, &start);
+    let exit = *longest_path.last().unwrap();
+    let solution = maze_solver::a_star_path(&maze, &start, &exit).unwrap_or(longest_path);
+
+    (maze, solution)
+}
 
-    if maze[*pos_y][*pos_x + 1].tile_type == TileType::Wall {
-        // Check if we can use the tile on the other side
-        if maze[*pos_y][*pos_x + 2].can_be_used {
-            // Give the position of the WALL that can be set to OPEN
-            // and give next tile position
-            directions.push((*pos_x + 1, *pos_y, *pos_x + 2, *pos_y));
+// Keep creating mazes until one has the requested difficulty (None is any difficulty).
+// If none of them have it, we use the one that came the closest.
+#[allow(clippy::too_many_arguments)]
+pub fn create_maze_with_difficulty(
+    width: usize,
+    height: usize,
+    maze_mask: &Option<MazeMask>,
+    maze_algorithm: &MazeAlgorithmType,
+    loopiness: u8,
+    difficulty: &Option<Difficulty>,
+    placement: &PlacementStrategy,
+    rng: &mut StdRng,
+) -> (Maze, Vec<(usize, usize)>, MazeRating) {
+    let mut closest = None;
+    let mut closest_distance = f32::MAX;
+
+    for _ in 0..MAX_DIFFICULTY_TRIES {
+        let (maze, solution) = create_maze(
+            width,
+            height,
+            maze_mask,
+            maze_algorithm,
+            loopiness,
+            placement,
+            rng,
+        );
+        let rating = maze_analysis::analyse_maze(&maze, &solution);
+
+        let distance = match difficulty {
+            Some(difficulty) => get_distance_to_difficulty(difficulty, rating.difficulty_score),
+            None => 0.0,
+        };
+
+        if distance < closest_distance {
+            closest_distance = distance;
+            closest = Some((maze, solution, rating));
         }
-    }
 
-    if maze[*pos_y][*pos_x - 1].tile_type == TileType::Wall {
-        // Check if we can use the tile on the other side
-        if maze[*pos_y][*pos_x - 2].can_be_used {
-            // Give the position of the WALL that can be set to OPEN
-            // and give next tile position
-            directions.push((*pos_x - 1, *pos_y, *pos_x - 2, *pos_y));
+        if closest_distance == 0.0 {
+            break;
         }
     }
 
-    get_new_direction(pos_x, pos_y, &directions, maze)
+    closest.unwrap()
 }
 
-fn get_new_direction(
-    pos_x: &usize,
-    pos_y: &usize,
-    directions: &Vec<(usize, usize, usize, usize)>,
-    maze: &mut Vec<Vec<MazeTile>>,
-) -> (usize, usize, usize, usize) {
-    // We can have none, one or multiple directions
-
-    // Default direction is our current tile and 0, 0 is not used
-    // and needed if we can't go in other directions
-    let mut direction = &(0, 0, *pos_x, *pos_y);
-
-    if directions.len() == 1 {
-        // Only one direction to use
-        direction = directions.first().unwrap();
-    } else if directions.len() > 1 {
-        // We have multiple directions, so we need to get a random tile
-        let random_direction = rand::thread_rng().gen_range(0..directions.len());
-        direction = directions.get(random_direction).unwrap();
-    }
-
-    let wall_x = direction.0;
-    let wall_y = direction.1;
-    let next_x = direction.2;
-    let next_y = direction.3;
+fn get_distance_to_difficulty(difficulty: &Difficulty, score: f32) -> f32 {
+    let (lowest, highest) = difficulty.score_range();
 
-    // Doensn't matter if this is the current tile or a WALL.
-    // But position can't be 0,0 for a wall
-    if wall_x != 0 && wall_y != 0 {
-        maze[wall_y][wall_x].tile_type = TileType::Open;
+    if score < lowest {
+        lowest - score
+    } else if score >= highest {
+        score - highest
+    } else {
+        0.0
     }
-
-    // Return next position
-    (next_x, next_y, wall_x, wall_y)
 }
 
-// Create an maze with default value tiles
-fn initialize_maze_size(width: &usize, height: &usize) -> (Vec<Vec<MazeTile>>, usize, usize) {
-    let mut tmp_open_tiles = Vec::<(usize, usize)>::new();
+// A carved maze without the START and EXIT, and the tile where the START can go.
+// The START and EXIT are placed with maze_placement, after the loops are added.
+pub fn create_random_maze(
+    width: usize,
+    height: usize,
+    maze_mask: &Option<MazeMask>,
+    maze_algorithm: &MazeAlgorithmType,
+    rng: &mut StdRng,
+) -> (Maze, (usize, usize)) {
+    let (mut maze, start_x, start_y) = initialize_maze_size(&width, &height, maze_mask, rng);
+
+    maze_algorithm.get_algorithm().carve(&mut maze, rng);
+    connect_separate_parts(&mut maze, rng);
+
+    (maze, (start_x, start_y))
+}
 
-    let mut maze = Vec::<Vec<MazeTile>>::new();
-    let mut tile_id = 0;
+// Create an maze with default value tiles and a random starting point.
+// The starting point is not a START tile yet, see maze_placement.
+pub fn initialize_maze_size(
+    width: &usize,
+    height: &usize,
+    maze_mask: &Option<MazeMask>,
+    rng: &mut StdRng,
+) -> (Maze, usize, usize) {
+    let (width, height) = get_valid_maze_size(*width, *height);
+    let mut maze = Maze::new(width, height);
+
+    if let Some(maze_mask) = maze_mask {
+        maze_mask.apply(&mut maze);
+    }
 
-    for y in 0..*height {
-        let mut maze_row = Vec::<MazeTile>::new();
+    // Every usable cell can be a starting point
+    let (start_x, start_y) = Maze::cell_to_tile(&maze_algorithms::random_cell(&maze, rng));
 
-        for x in 0..*width {
-            // Start at id 1
-            tile_id += 1;
+    (maze, start_x, start_y)
+}
 
-            // This is always a BORDER
-            if y == 0 || y == *height - 1 || x == 0 || x == *width - 1 {
-                // Borders can never change to OPEN
-                maze_row.push(MazeTile::new(tile_id, TileType::Border));
+// The algorithms that go row by row (like Eller and Sidewinder) expect a rectangle of
+// cells. With a mask they can leave parts of the maze that are not connected to each
+// other. Open one random WALL between every two parts, so it's still a perfect maze.
+fn connect_separate_parts(maze: &mut Maze, rng: &mut StdRng) {
+    let (cells_x, cells_y) = maze.cell_count();
+    let mut sets: Vec<usize> = (0..cells_x * cells_y).collect();
+    let mut closed_walls = Vec::<((usize, usize), (usize, usize))>::new();
+
+    for cell in maze.usable_cells() {
+        for other_cell in [(cell.0 + 1, cell.1), (cell.0, cell.1 + 1)] {
+            if other_cell.0 >= cells_x
+                || other_cell.1 >= cells_y
+                || !maze.is_usable_cell(&other_cell)
+            {
+                continue;
+            }
 
-                // Every odd position is an OPEN TileType
-            } else if x % 2 != 0 && y % 2 != 0 {
-                // Add OPEN tile tmp_open_tiles for random startpoint
-                tmp_open_tiles.push((x, y));
-                maze_row.push(MazeTile::new(tile_id, TileType::Open));
+            let (wall_x, wall_y) = Maze::wall_between(&cell, &other_cell).unwrap();
+            if maze.is_passable(wall_x, wall_y) {
+                let (cell_x, cell_y) = Maze::cell_to_tile(&cell);
+                if maze.is_bridge(cell_x, cell_y) {
+                    continue;
+                }
+                // A BRIDGE connects the cells on both sides of it, not the bridge itself.
+                // Bridges can be next to each other, so we go on until we are off them.
+                let (step_x, step_y) = (other_cell.0 - cell.0, other_cell.1 - cell.1);
+                let mut other_cell = other_cell;
+                while maze.is_bridge(2 * other_cell.0 + 1, 2 * other_cell.1 + 1) {
+                    other_cell = (other_cell.0 + step_x, other_cell.1 + step_y);
+                }
 
-                // The rest are all walls
+                let set = maze_algorithms::find_set(&mut sets, cell.1 * cells_x + cell.0);
+                let other_set =
+                    maze_algorithms::find_set(&mut sets, other_cell.1 * cells_x + other_cell.0);
+                sets[other_set] = set;
             } else {
-                maze_row.push(MazeTile::new(tile_id, TileType::Wall));
+                closed_walls.push((cell, other_cell));
             }
         }
-        maze.push(maze_row);
     }
-    // Find a starting point
-    let random_number = rand::thread_rng().gen_range(0..tmp_open_tiles.len() - 1);
-    let random_position = tmp_open_tiles.get(random_number).unwrap();
 
-    maze[random_position.1][random_position.0].tile_type = TileType::Start;
-    maze[random_position.1][random_position.0].can_be_used = false;
+    closed_walls.shuffle(rng);
+    for (cell, other_cell) in closed_walls {
+        let set = maze_algorithms::find_set(&mut sets, cell.1 * cells_x + cell.0);
+        let other_set = maze_algorithms::find_set(&mut sets, other_cell.1 * cells_x + other_cell.0);
 
-    (maze, random_position.0, random_position.1)
+        if set != other_set {
+            sets[other_set] = set;
+            maze.carve_passage(&cell, &other_cell);
+        }
+    }
 }
 
-pub fn paint_the_maze(
-    commands: &mut Commands,
-    solution: &Vec<(usize, usize)>,
-    maze: &mut Vec<Vec<MazeTile>>,
-    amazing_data: &mut ResMut<AmazinglyLostData>,
-) {
-    // TODO:RG WALLS also part of solution and dont overpaint
-    for (x, y) in solution {
-        maze[*y][*x].part_of_solution = true;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze_mask::MazeMask;
+    use rand::SeedableRng;
+    use std::collections::HashSet;
+
+    const SEED: u64 = 42;
+
+    fn all_algorithms() -> Vec<MazeAlgorithmType> {
+        let mut algorithms = vec![MazeAlgorithmType::RecursiveBacktracker];
+        loop {
+            let next = algorithms.last().unwrap().next();
+            if next == algorithms[0] {
+                return algorithms;
+            }
+            algorithms.push(next);
+        }
     }
 
-    // z-axis is always 0.0, we don't use depth
-
-    let mut pos_y = SPRITE_SIZE_MAZE as f32; // / 2.0;
-
-    for maze_row in maze {
-        let mut pos_x = SPRITE_SIZE_MAZE as f32; // / 2.0;
-
-        for maze_tile in maze_row {
-            match &maze_tile.tile_type {
-                TileType::Border | TileType::Wall => {
-                    if let Some(mut border_texture_handle) =
-                        amazing_data.game_tile_handlers.get_random_game_tile(
-                            &maze_tile.tile_type,
-                            &Vec3::new(pos_x, pos_y as f32, 1.0),
-                        )
-                    {
-                        border_texture_handle.sprite = Sprite::new(Vec2::new(
-                            SPRITE_SIZE_MAZE as f32,
-                            SPRITE_SIZE_MAZE as f32,
-                        ));
-
-                        let flip_it: bool = rand::thread_rng().gen_range(0..2) == 1;
-
-                        border_texture_handle.sprite.flip_x = flip_it;
-
-                        commands
-                            .spawn_bundle(border_texture_handle.clone())
-                            .insert(GameTile)
-                            .insert(CollisionTile {
-                                collision_type: CollisionType::CollisionWall,
-                            });
-                    }
-                }
-                TileType::Start => {
-                    println!("Start:{}-{}", pos_x, pos_y);
-                    amazing_data.starting_point_sprites = (pos_x, pos_y);
-                    if let Some(mut start_texture_handle) = amazing_data
-                        .game_tile_handlers
-                        .get_game_start(&Vec3::new(pos_x, pos_y, 1.0))
-                    {
-                        start_texture_handle.sprite = Sprite::new(Vec2::new(
-                            SPRITE_SIZE_MAZE as f32,
-                            SPRITE_SIZE_MAZE as f32,
-                        ));
-
-                        commands
-                            .spawn_bundle(start_texture_handle.clone())
-                            .insert(GameTile)
-                            .insert(CollisionTile {
-                                collision_type: CollisionType::CollisionStart,
-                            });
-                    }
-                }
-                TileType::Exit => {
-                    if let Some(mut exit_texture_handle) = amazing_data
-                        .game_tile_handlers
-                        .get_game_exit(&Vec3::new(pos_x, pos_y, 1.0))
-                    {
-                        exit_texture_handle.sprite = Sprite::new(Vec2::new(
-                            SPRITE_SIZE_MAZE as f32,
-                            SPRITE_SIZE_MAZE as f32,
-                        ));
-
-                        commands
-                            .spawn_bundle(exit_texture_handle.clone())
-                            .insert(GameTile)
-                            .insert(CollisionTile {
-                                collision_type: CollisionType::CollisionExit,
-                            });
-                    }
-                }
-                TileType::Open => {
-                    if let Some(mut open_texture_handle) = amazing_data
-                        .game_tile_handlers
-                        .get_random_game_tile(&maze_tile.tile_type, &Vec3::new(pos_x, pos_y, 1.0))
-                    {
-                        open_texture_handle.sprite = Sprite::new(Vec2::new(
-                            SPRITE_SIZE_MAZE as f32,
-                            SPRITE_SIZE_MAZE as f32,
-                        ));
-
-                        if maze_tile.part_of_solution {
-                            // Both get SolutionTile
-                            commands
-                                .spawn_bundle(open_texture_handle.clone())
-                                .insert(GameTile)
-                                .insert(SolutionTile);
-
-                            // Only different material
-                            let mut solution_texture_handle = open_texture_handle.clone();
-                            solution_texture_handle.material =
-                                amazing_data.game_tile_handlers.solution.clone();
-                            solution_texture_handle.visible.is_visible = false;
-                            commands
-                                .spawn_bundle(solution_texture_handle)
-                                .insert(GameTile)
-                                .insert(SolutionTile);
-                        } else {
-                            // Only GameTile if this is not the solution path
-                            commands
-                                .spawn_bundle(open_texture_handle.clone())
-                                .insert(GameTile);
-                        }
-                    }
+    // Every tile the player can stop on can be reached and there is only one way to it.
+    // A BRIDGE is jumped over, so the crossing corridors don't count as a loop.
+    fn assert_perfect_maze(maze: &Maze, start: &(usize, usize), name: &str) {
+        let tiles: Vec<(usize, usize)> = maze
+            .positions()
+            .filter(|(x, y)| maze.is_passable(*x, *y) && !maze.is_bridge(*x, *y))
+            .collect();
+        let connection_count: usize = tiles
+            .iter()
+            .map(|(x, y)| maze.passable_neighbours(*x, *y).len())
+            .sum::<usize>()
+            / 2;
+        assert_eq!(connection_count, tiles.len() - 1, "{} has loops", name);
+
+        let mut reached = HashSet::from([*start]);
+        let mut to_visit = vec![*start];
+        while let Some((x, y)) = to_visit.pop() {
+            for next in maze.passable_neighbours(x, y) {
+                if reached.insert(next) {
+                    to_visit.push(next);
                 }
             }
+        }
+        assert_eq!(reached.len(), tiles.len(), "{} is not connected", name);
+    }
 
-            pos_x += SPRITE_SIZE_MAZE as f32;
+    #[test]
+    fn every_algorithm_gives_a_perfect_maze() {
+        for maze_algorithm in all_algorithms() {
+            let mut rng = StdRng::seed_from_u64(SEED);
+            let (maze, solution) = create_maze(
+                21,
+                21,
+                &None,
+                &maze_algorithm,
+                0,
+                &PlacementStrategy::FarthestExit,
+                &mut rng,
+            );
+
+            let name = maze_algorithm.get_algorithm().name();
+            assert_perfect_maze(&maze, &solution[0], name);
+            assert_eq!(
+                maze.tile_type(solution[0].0, solution[0].1),
+                Some(&TileType::Start)
+            );
+            let exit = solution.last().unwrap();
+            assert_eq!(maze.tile_type(exit.0, exit.1), Some(&TileType::Exit));
         }
-        pos_y += SPRITE_SIZE_MAZE as f32;
     }
-}
 
-fn place_player_in_maze(commands: &mut Commands, amazing_data: &mut ResMut<AmazinglyLostData>) {
-    if let Some(mut player_texture_handle) =
-        amazing_data.game_tile_handlers.get_game_player(&Vec3::new(
-            // Place the player at the starting point
-            amazing_data.starting_point_sprites.0 as f32,
-            amazing_data.starting_point_sprites.1 as f32,
-            1.0,
-        ))
-    {
-        player_texture_handle.sprite =
-            Sprite::new(Vec2::new(SPRITE_SIZE_MAZE as f32, SPRITE_SIZE_MAZE as f32));
-        amazing_data.entity_player = commands
-            .spawn_bundle(player_texture_handle)
-            .insert(GameTile)
-            .insert(PlayerTile)
-            .insert(amazing_data.player.clone())
-            .id();
+    #[test]
+    fn every_algorithm_gives_a_perfect_maze_with_a_mask() {
+        // A ring, the middle and the corners are outside of the maze
+        let carvable = (0..25)
+            .map(|pixel| {
+                let (x, y) = (pixel % 5, pixel / 5);
+                (x == 0 || x == 4 || y == 0 || y == 4) && (x, y) != (0, 0)
+            })
+            .collect();
+        let maze_mask = Some(MazeMask::new(5, 5, carvable));
+
+        for maze_algorithm in all_algorithms() {
+            let mut rng = StdRng::seed_from_u64(SEED);
+            let (maze, start) = create_random_maze(31, 31, &maze_mask, &maze_algorithm, &mut rng);
+
+            assert_perfect_maze(&maze, &start, maze_algorithm.get_algorithm().name());
+        }
     }
-}
 
-fn place_camera_on_starting_tile(
-    camera_query: &mut Query<(&mut Transform, &Camera)>,
-    amazing_data: &mut ResMut<AmazinglyLostData>,
-) {
-    for mut camera in camera_query.iter_mut() {
-        // Place Camera on the same x, y as the Player
-        camera.0.translation.x = amazing_data.starting_point_sprites.0 as f32;
-        camera.0.translation.y = amazing_data.starting_point_sprites.1 as f32;
+    #[test]
+    fn same_seed_gives_the_same_maze() {
+        for maze_algorithm in all_algorithms() {
+            let create = || {
+                create_maze(
+                    25,
+                    21,
+                    &None,
+                    &maze_algorithm,
+                    30,
+                    &PlacementStrategy::DecoyExits(3),
+                    &mut StdRng::seed_from_u64(SEED),
+                )
+            };
+
+            assert_eq!(create(), create());
+        }
+    }
+
+    #[test]
+    fn every_ladder_up_has_a_ladder_down_above_it() {
+        let mut rng = StdRng::seed_from_u64(SEED);
+        let (maze, solution) = create_multi_floor_maze(
+            21,
+            21,
+            3,
+            &None,
+            &MazeAlgorithmType::RecursiveBacktracker,
+            0,
+            &mut rng,
+        );
+
+        let ladders: Vec<FloorPosition> = maze
+            .positions()
+            .filter(|position| maze.tile_type(position) == Some(&TileType::LadderUp))
+            .collect();
+        assert_eq!(ladders.len(), 2);
+        for (x, y, floor) in ladders {
+            assert_eq!(
+                maze.tile_type(&(x, y, floor + 1)),
+                Some(&TileType::LadderDown)
+            );
+        }
+
+        // Every tile the player can stop on is reached from the START, on every floor
+        let start = solution[0];
+        assert_eq!(start.2, 0);
+        assert_eq!(maze.tile_type(&start), Some(&TileType::Start));
+        let open_tiles = maze
+            .positions()
+            .filter(|position| maze.is_passable(position))
+            .count();
+        let mut seen = HashSet::from([start]);
+        let mut to_visit = vec![start];
+        while let Some(position) = to_visit.pop() {
+            for next in maze.passable_neighbours(&position) {
+                if seen.insert(next) {
+                    to_visit.push(next);
+                }
+            }
+        }
+        assert_eq!(seen.len(), open_tiles);
+
+        // The solution only changes floor on a ladder
+        for pair in solution.windows(2) {
+            if pair[0].2 != pair[1].2 {
+                assert_eq!(maze.ladder_destination(&pair[0]), Some(pair[1]));
+            }
+        }
     }
-}
 
-pub fn clear_maze_tiles(
-    commands: &mut Commands,
-    game_tile_query: &mut Query<(Entity, (With<GameTile>, Without<Camera>))>,
-) {
-    // Despawn every GameTile, but NOT the camera. Or we will see a black screen
-    for (entity, _game_tile) in game_tile_query.iter_mut() {
-        commands.entity(entity).despawn();
+    #[test]
+    fn floors_with_one_cell_give_a_maze_without_ladders() {
+        // One pixel for every cell, only the top left one is white
+        let carvable = (0..25).map(|pixel| pixel == 0).collect();
+        let maze_mask = Some(MazeMask::new(5, 5, carvable));
+
+        for floor_count in 1..=MAX_FLOORS {
+            let mut rng = StdRng::seed_from_u64(SEED);
+            let (maze, solution) = create_multi_floor_maze(
+                11,
+                11,
+                floor_count,
+                &maze_mask,
+                &MazeAlgorithmType::RecursiveBacktracker,
+                0,
+                &mut rng,
+            );
+
+            assert_eq!(maze.floor_count(), floor_count);
+            // The only cell of the first floor is the START, so no LADDER goes up from it
+            assert_eq!(solution.len(), 1);
+            assert_eq!(solution[0].2, 0);
+            assert_ne!(maze.tile_type(&solution[0]), Some(&TileType::LadderUp));
+        }
     }
 }
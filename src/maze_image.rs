@@ -0,0 +1,278 @@
+use crate::maze::Maze;
+use crate::maze_tile::TileType;
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+// Pixels for one tile when nothing else is asked
+pub const DEFAULT_TILE_SIZE: u32 = 10;
+
+type Rgb = [u8; 3];
+
+const COLOR_WALL: Rgb = [0x20, 0x20, 0x20];
+const COLOR_OPEN: Rgb = [0xFF, 0xFF, 0xFF];
+const COLOR_START: Rgb = [0x2E, 0xA0, 0x43];
+const COLOR_EXIT: Rgb = [0xD0, 0x30, 0x30];
+const COLOR_ROOM: Rgb = [0xF5, 0xE6, 0xC8];
+const COLOR_BRIDGE: Rgb = [0xF0, 0x90, 0x20];
+const COLOR_LADDER: Rgb = [0x30, 0x90, 0xA0];
+const COLOR_SOLUTION: Rgb = [0x80, 0xB0, 0xF0];
+
+#[derive(Debug)]
+pub enum ImageError {
+    Io(std::io::Error),
+    Png(png::EncodingError),
+    // Width or height of the image would be 0 or too big for a PNG
+    InvalidSize,
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageError::Io(error) => write!(f, "Can't write the image: {}", error),
+            ImageError::Png(error) => write!(f, "Can't create the PNG image: {}", error),
+            ImageError::InvalidSize => write!(f, "The image would be too small or too big"),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {}
+
+impl From<std::io::Error> for ImageError {
+    fn from(error: std::io::Error) -> Self {
+        ImageError::Io(error)
+    }
+}
+
+impl From<png::EncodingError> for ImageError {
+    fn from(error: png::EncodingError) -> Self {
+        ImageError::Png(error)
+    }
+}
+
+// The bytes of a PNG image. Every tile is a square of tile_size pixels, the north side
+// is at the top. With a solution the way from the start to the exit is also colored.
+pub fn maze_to_png(
+    maze: &Maze,
+    solution: Option<&[(usize, usize)]>,
+    tile_size: u32,
+) -> Result<Vec<u8>, ImageError> {
+    let (width, height) = get_image_size(maze, tile_size)?;
+    let pixels = get_pixels(maze, solution, tile_size as usize);
+    let mut png_bytes = Vec::<u8>::new();
+
+    let mut encoder = png::Encoder::new(&mut png_bytes, width, height);
+    encoder.set_color(png::ColorType::RGB);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&pixels)?;
+    Ok(png_bytes)
+}
+
+pub fn save_png_file<P: AsRef<Path>>(
+    maze: &Maze,
+    solution: Option<&[(usize, usize)]>,
+    tile_size: u32,
+    path: P,
+) -> Result<(), ImageError> {
+    fs::write(path, maze_to_png(maze, solution, tile_size)?)?;
+    Ok(())
+}
+
+// Same as save_png_file, but as an SVG. Walls are rectangles and the solution is one line,
+// so it can be printed at any size.
+pub fn maze_to_svg(
+    maze: &Maze,
+    solution: Option<&[(usize, usize)]>,
+    tile_size: u32,
+) -> Result<String, ImageError> {
+    let (width, height) = get_image_size(maze, tile_size)?;
+    let tile_size = tile_size as usize;
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" viewBox=\"0 0 {} {}\">\n",
+        width, height, width, height
+    );
+    svg.push_str(&format!(
+        "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>\n",
+        to_hex(&COLOR_OPEN)
+    ));
+
+    for y in 0..maze.height() {
+        // Tiles with the same color next to each other on a row become one rectangle
+        let mut x = 0;
+        while x < maze.width() {
+            let color = maze.tile_type(x, y).and_then(get_color);
+            let run = (x..maze.width())
+                .take_while(|next_x| maze.tile_type(*next_x, y).and_then(get_color) == color)
+                .count();
+
+            if let Some(color) = color {
+                svg.push_str(&format!(
+                    "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>\n",
+                    x * tile_size,
+                    (maze.height() - 1 - y) * tile_size,
+                    run * tile_size,
+                    tile_size,
+                    to_hex(&color)
+                ));
+            }
+            x += run;
+        }
+    }
+
+    if let Some(solution) = solution.filter(|solution| !solution.is_empty()) {
+        let points: Vec<String> = solution
+            .iter()
+            .map(|(x, y)| {
+                format!(
+                    "{},{}",
+                    x * tile_size + tile_size / 2,
+                    (maze.height() - 1 - y) * tile_size + tile_size / 2
+                )
+            })
+            .collect();
+        svg.push_str(&format!(
+            "<polyline points=\"{}\" fill=\"none\" stroke=\"{}\" stroke-width=\"{}\" stroke-linecap=\"round\" stroke-linejoin=\"round\"/>\n",
+            points.join(" "),
+            to_hex(&COLOR_SOLUTION),
+            (tile_size / 2).max(1)
+        ));
+    }
+
+    svg.push_str("</svg>\n");
+    Ok(svg)
+}
+
+pub fn save_svg_file<P: AsRef<Path>>(
+    maze: &Maze,
+    solution: Option<&[(usize, usize)]>,
+    tile_size: u32,
+    path: P,
+) -> Result<(), ImageError> {
+    fs::write(path, maze_to_svg(maze, solution, tile_size)?)?;
+    Ok(())
+}
+
+fn get_image_size(maze: &Maze, tile_size: u32) -> Result<(u32, u32), ImageError> {
+    let width = (maze.width() as u32).checked_mul(tile_size);
+    let height = (maze.height() as u32).checked_mul(tile_size);
+
+    match (width, height) {
+        (Some(width), Some(height)) if width > 0 && height > 0 => Ok((width, height)),
+        _ => Err(ImageError::InvalidSize),
+    }
+}
+
+// RGB values row by row from the top of the image
+fn get_pixels(maze: &Maze, solution: Option<&[(usize, usize)]>, tile_size: usize) -> Vec<u8> {
+    let mut tile_colors: Vec<Rgb> = maze
+        .positions()
+        .map(|(x, y)| {
+            maze.tile_type(x, y)
+                .and_then(get_color)
+                .unwrap_or(COLOR_OPEN)
+        })
+        .collect();
+
+    // A step over a BRIDGE skips the BRIDGE, so color every tile between two steps
+    for step in solution.unwrap_or_default().windows(2) {
+        let ((from_x, from_y), (to_x, to_y)) = (step[0], step[1]);
+        for y in from_y.min(to_y)..=from_y.max(to_y) {
+            for x in from_x.min(to_x)..=from_x.max(to_x) {
+                if matches!(
+                    maze.tile_type(x, y),
+                    Some(TileType::Open) | Some(TileType::Room)
+                ) {
+                    tile_colors[y * maze.width() + x] = COLOR_SOLUTION;
+                }
+            }
+        }
+    }
+
+    let row_size = maze.width() * tile_size * 3;
+    let mut pixels = Vec::<u8>::with_capacity(row_size * maze.height() * tile_size);
+    for y in (0..maze.height()).rev() {
+        let mut row = Vec::<u8>::with_capacity(row_size);
+        for color in &tile_colors[y * maze.width()..(y + 1) * maze.width()] {
+            for _ in 0..tile_size {
+                row.extend_from_slice(color);
+            }
+        }
+        for _ in 0..tile_size {
+            pixels.extend_from_slice(&row);
+        }
+    }
+    pixels
+}
+
+// None for tiles that have the color of the ground
+fn get_color(tile_type: &TileType) -> Option<Rgb> {
+    match tile_type {
+        TileType::Wall | TileType::Border => Some(COLOR_WALL),
+        TileType::Open => None,
+        TileType::Start => Some(COLOR_START),
+        // A fake exit should look like a real one, also on paper
+        TileType::Exit | TileType::DecoyExit => Some(COLOR_EXIT),
+        TileType::Room => Some(COLOR_ROOM),
+        TileType::Bridge => Some(COLOR_BRIDGE),
+        TileType::LadderUp | TileType::LadderDown => Some(COLOR_LADDER),
+    }
+}
+
+fn to_hex(color: &Rgb) -> String {
+    format!("#{:02X}{:02X}{:02X}", color[0], color[1], color[2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze_generator::{self, MazeAlgorithmType};
+    use crate::maze_placement::PlacementStrategy;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn small_maze() -> (Maze, Vec<(usize, usize)>) {
+        let mut rng = StdRng::seed_from_u64(5);
+        maze_generator::create_maze(
+            11,
+            9,
+            &None,
+            &MazeAlgorithmType::RecursiveBacktracker,
+            0,
+            &PlacementStrategy::FarthestExit,
+            &mut rng,
+        )
+    }
+
+    #[test]
+    fn png_has_tile_size_pixels_for_every_tile() {
+        let (maze, solution) = small_maze();
+
+        let png_bytes = maze_to_png(&maze, Some(&solution), 4).unwrap();
+        let (info, _) = png::Decoder::new(png_bytes.as_slice()).read_info().unwrap();
+        assert_eq!((info.width, info.height), (44, 36));
+    }
+
+    #[test]
+    fn svg_has_tile_size_pixels_for_every_tile() {
+        let (maze, solution) = small_maze();
+
+        let svg = maze_to_svg(&maze, Some(&solution), 4).unwrap();
+        assert!(svg.contains("width=\"44\" height=\"36\" viewBox=\"0 0 44 36\""));
+        assert!(svg.contains("<polyline"));
+    }
+
+    #[test]
+    fn tile_size_0_is_rejected() {
+        let (maze, _) = small_maze();
+
+        assert!(matches!(
+            maze_to_png(&maze, None, 0),
+            Err(ImageError::InvalidSize)
+        ));
+        assert!(matches!(
+            maze_to_svg(&maze, None, 0),
+            Err(ImageError::InvalidSize)
+        ));
+    }
+}
@@ -0,0 +1,315 @@
+use crate::maze::Maze;
+use crate::maze_ascii::{self, AsciiError};
+use crate::maze_generator::MazeAlgorithmType;
+use crate::maze_solver;
+use crate::maze_tile::TileType;
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug)]
+pub enum LevelError {
+    Io(std::io::Error),
+    Json(serde_json::Error),
+    Ron(ron::Error),
+    // The tiles of the level are not a valid maze
+    Tiles(AsciiError),
+    // The start or exit in the level are not on the START or EXIT tile
+    WrongStart((usize, usize)),
+    WrongExit((usize, usize)),
+    // The solution doesn't go from the start to the exit, one tile at a time
+    WrongSolution,
+    // An item is on a tile the player can't walk on
+    ItemNotPassable((usize, usize)),
+    // Only .json and .ron files
+    UnknownFormat,
+}
+
+impl fmt::Display for LevelError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LevelError::Io(error) => write!(f, "Can't read or write the level: {}", error),
+            LevelError::Json(error) => write!(f, "Not a valid JSON level: {}", error),
+            LevelError::Ron(error) => write!(f, "Not a valid RON level: {}", error),
+            LevelError::Tiles(error) => write!(f, "Not a valid level: {}", error),
+            LevelError::WrongStart((x, y)) => {
+                write!(f, "The start ({}, {}) is not on the S tile", x, y)
+            }
+            LevelError::WrongExit((x, y)) => {
+                write!(f, "The exit ({}, {}) is not on the E tile", x, y)
+            }
+            LevelError::WrongSolution => {
+                write!(f, "The solution is not a way from the start to the exit")
+            }
+            LevelError::ItemNotPassable((x, y)) => {
+                write!(
+                    f,
+                    "The item on ({}, {}) is not on a tile you can walk on",
+                    x, y
+                )
+            }
+            LevelError::UnknownFormat => write!(f, "A level file needs to be .json or .ron"),
+        }
+    }
+}
+
+impl std::error::Error for LevelError {}
+
+impl From<std::io::Error> for LevelError {
+    fn from(error: std::io::Error) -> Self {
+        LevelError::Io(error)
+    }
+}
+
+impl From<serde_json::Error> for LevelError {
+    fn from(error: serde_json::Error) -> Self {
+        LevelError::Json(error)
+    }
+}
+
+impl From<ron::Error> for LevelError {
+    fn from(error: ron::Error) -> Self {
+        LevelError::Ron(error)
+    }
+}
+
+impl From<AsciiError> for LevelError {
+    fn from(error: AsciiError) -> Self {
+        LevelError::Tiles(error)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum LevelFormat {
+    Json,
+    Ron,
+}
+
+impl LevelFormat {
+    // From the extension of the file
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_lowercase();
+
+        match extension.as_str() {
+            "json" => Some(LevelFormat::Json),
+            "ron" => Some(LevelFormat::Ron),
+            _ => None,
+        }
+    }
+}
+
+// Something placed on a tile of the level, like a key or a torch.
+// The name tells the game what it is, so new items don't change the file format.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct LevelItem {
+    pub name: String,
+    pub position: (usize, usize),
+}
+
+// A maze with everything that is needed to play it again, without generating it.
+// The tiles are stored as text (see maze_ascii), one line for every row from the
+// north side, so a level file can still be read and changed by hand.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MazeLevel {
+    #[serde(default)]
+    pub name: String,
+    // Seed and algorithm the maze was generated with, the tiles don't come from these
+    pub seed: u64,
+    pub algorithm: MazeAlgorithmType,
+    pub theme: String,
+    pub start: (usize, usize),
+    pub exit: (usize, usize),
+    #[serde(default)]
+    pub items: Vec<LevelItem>,
+    // Positions from the start to the exit, empty if the game should find it
+    #[serde(default)]
+    pub solution: Vec<(usize, usize)>,
+    pub tiles: Vec<String>,
+}
+
+impl MazeLevel {
+    pub fn from_maze(
+        maze: &Maze,
+        solution: &[(usize, usize)],
+        seed: u64,
+        algorithm: MazeAlgorithmType,
+        theme: &str,
+    ) -> Self {
+        let start = maze_solver::find_tile(maze, &TileType::Start).unwrap_or_default();
+        let exit = maze_solver::find_tile(maze, &TileType::Exit).unwrap_or_default();
+
+        Self {
+            name: String::new(),
+            seed,
+            algorithm,
+            theme: theme.to_string(),
+            start,
+            exit,
+            items: Vec::new(),
+            solution: solution.to_vec(),
+            tiles: maze_ascii::maze_to_ascii(maze)
+                .lines()
+                .map(String::from)
+                .collect(),
+        }
+    }
+
+    // The maze and the solution, also checks that start, exit, solution and items fit the tiles
+    pub fn to_maze(&self) -> Result<(Maze, Vec<(usize, usize)>), LevelError> {
+        let maze = maze_ascii::maze_from_ascii(&self.tiles.join("\n"))?;
+
+        if maze.tile_type(self.start.0, self.start.1) != Some(&TileType::Start) {
+            return Err(LevelError::WrongStart(self.start));
+        }
+        if maze.tile_type(self.exit.0, self.exit.1) != Some(&TileType::Exit) {
+            return Err(LevelError::WrongExit(self.exit));
+        }
+        if let Some(item) = self
+            .items
+            .iter()
+            .find(|item| !maze.is_passable(item.position.0, item.position.1))
+        {
+            return Err(LevelError::ItemNotPassable(item.position));
+        }
+
+        let solution = if self.solution.is_empty() {
+            // maze_from_ascii already checked there is a way to the exit
+            maze_solver::a_star_path(&maze, &self.start, &self.exit).unwrap_or_default()
+        } else if is_walkable_path(&maze, &self.solution, &self.start, &self.exit) {
+            self.solution.clone()
+        } else {
+            return Err(LevelError::WrongSolution);
+        };
+        Ok((maze, solution))
+    }
+
+    pub fn to_text(&self, format: &LevelFormat) -> Result<String, LevelError> {
+        let text = match format {
+            LevelFormat::Json => serde_json::to_string_pretty(self)?,
+            LevelFormat::Ron => ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::new())?,
+        };
+        Ok(text)
+    }
+
+    pub fn from_text(text: &str, format: &LevelFormat) -> Result<Self, LevelError> {
+        let level = match format {
+            LevelFormat::Json => serde_json::from_str(text)?,
+            LevelFormat::Ron => ron::de::from_str(text)?,
+        };
+        Ok(level)
+    }
+}
+
+// Every step goes to a tile next to it that the player can walk on.
+// A BRIDGE can only be crossed in a straight line.
+fn is_walkable_path(
+    maze: &Maze,
+    path: &[(usize, usize)],
+    start: &(usize, usize),
+    exit: &(usize, usize),
+) -> bool {
+    if path.first() != Some(start) || path.last() != Some(exit) {
+        return false;
+    }
+
+    let is_every_step_walkable = path.windows(2).all(|step| {
+        maze_solver::get_manhattan_distance(&step[0], &step[1]) == 1
+            && maze.is_passable(step[1].0, step[1].1)
+    });
+    let is_every_bridge_crossed = path.windows(3).all(|steps| {
+        !maze.is_bridge(steps[1].0, steps[1].1)
+            || (steps[0].0 + steps[2].0 == 2 * steps[1].0
+                && steps[0].1 + steps[2].1 == 2 * steps[1].1)
+    });
+    is_every_step_walkable && is_every_bridge_crossed
+}
+
+// The format comes from the extension of the file (.json or .ron)
+pub fn load_level_file<P: AsRef<Path>>(path: P) -> Result<MazeLevel, LevelError> {
+    let format = LevelFormat::from_path(&path).ok_or(LevelError::UnknownFormat)?;
+
+    MazeLevel::from_text(&fs::read_to_string(path)?, &format)
+}
+
+pub fn save_level_file<P: AsRef<Path>>(level: &MazeLevel, path: P) -> Result<(), LevelError> {
+    let format = LevelFormat::from_path(&path).ok_or(LevelError::UnknownFormat)?;
+
+    fs::write(path, level.to_text(&format)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze_generator;
+    use crate::maze_placement::PlacementStrategy;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    fn weave_level() -> MazeLevel {
+        let mut rng = StdRng::seed_from_u64(7);
+        let (maze, solution) = maze_generator::create_maze(
+            15,
+            15,
+            &None,
+            &MazeAlgorithmType::Weave,
+            10,
+            &PlacementStrategy::FarthestExit,
+            &mut rng,
+        );
+        MazeLevel::from_maze(&maze, &solution, 7, MazeAlgorithmType::Weave, "default")
+    }
+
+    #[test]
+    fn level_survives_json_and_ron() {
+        let mut level = weave_level();
+        level.name = "Weave".to_string();
+        level.items = vec![LevelItem {
+            name: "torch".to_string(),
+            position: level.solution[1],
+        }];
+
+        for format in [LevelFormat::Json, LevelFormat::Ron] {
+            let text = level.to_text(&format).unwrap();
+            assert_eq!(MazeLevel::from_text(&text, &format).unwrap(), level);
+        }
+    }
+
+    #[test]
+    fn solution_over_bridges_is_accepted() {
+        let level = weave_level();
+
+        let (_, solution) = level.to_maze().unwrap();
+        assert_eq!(solution, level.solution);
+    }
+
+    #[test]
+    fn broken_solution_is_rejected() {
+        let mut level = weave_level();
+        level.solution.pop();
+        assert!(matches!(level.to_maze(), Err(LevelError::WrongSolution)));
+
+        let mut level = weave_level();
+        level.solution.remove(1);
+        assert!(matches!(level.to_maze(), Err(LevelError::WrongSolution)));
+    }
+
+    #[test]
+    fn item_in_a_wall_is_rejected() {
+        let mut level = weave_level();
+        level.items = vec![LevelItem {
+            name: "key".to_string(),
+            position: level.solution[1],
+        }];
+        assert!(level.to_maze().is_ok());
+
+        // The corner of the maze is always BORDER
+        level.items[0].position = (0, 0);
+        assert!(matches!(
+            level.to_maze(),
+            Err(LevelError::ItemNotPassable((0, 0)))
+        ));
+    }
+}
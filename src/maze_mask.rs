@@ -0,0 +1,333 @@
+use crate::maze::Maze;
+use crate::maze_tile::TileType;
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+// Pixels with a brightness from this value are white (carvable)
+const WHITE_THRESHOLD: u8 = 128;
+
+#[derive(Debug)]
+pub enum MaskError {
+    Io(std::io::Error),
+    Png(png::DecodingError),
+    // The image has no width or height, or only has a bit depth we can't read
+    UnsupportedImage,
+}
+
+impl fmt::Display for MaskError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MaskError::Io(error) => write!(f, "Can't read the mask: {}", error),
+            MaskError::Png(error) => write!(f, "Not a valid PNG mask: {}", error),
+            MaskError::UnsupportedImage => write!(f, "Unsupported PNG mask"),
+        }
+    }
+}
+
+impl std::error::Error for MaskError {}
+
+impl From<std::io::Error> for MaskError {
+    fn from(error: std::io::Error) -> Self {
+        MaskError::Io(error)
+    }
+}
+
+impl From<png::DecodingError> for MaskError {
+    fn from(error: png::DecodingError) -> Self {
+        MaskError::Png(error)
+    }
+}
+
+// A black and white image that gives the shape of the maze.
+// Only the cells on white pixels can be carved, everything else becomes BORDER.
+//
+// The mask is stretched over the whole maze, so the image doesn't need to have the
+// same size as the maze. The top of the image is the north side of the maze.
+#[derive(Clone, Debug, PartialEq)]
+pub struct MazeMask {
+    width: usize,
+    height: usize,
+    // Row by row from the top of the image
+    carvable: Vec<bool>,
+}
+
+impl MazeMask {
+    // Every value in carvable is one pixel, row by row from the top of the image
+    pub fn new(width: usize, height: usize, carvable: Vec<bool>) -> Self {
+        assert_eq!(width * height, carvable.len());
+
+        Self {
+            width,
+            height,
+            carvable,
+        }
+    }
+
+    pub fn from_png_file<P: AsRef<Path>>(path: P) -> Result<Self, MaskError> {
+        let mut bytes = Vec::<u8>::new();
+        File::open(path)?.read_to_end(&mut bytes)?;
+
+        Self::from_png_bytes(&bytes)
+    }
+
+    pub fn from_png_bytes(bytes: &[u8]) -> Result<Self, MaskError> {
+        // By default the decoder gives 8 bits per sample and no palette
+        let (info, mut reader) = png::Decoder::new(bytes).read_info()?;
+        let mut buffer = vec![0; reader.output_buffer_size()];
+        reader.next_frame(&mut buffer)?;
+
+        let (color_type, bit_depth) = reader.output_color_type();
+        if info.width == 0 || info.height == 0 || bit_depth != png::BitDepth::Eight {
+            return Err(MaskError::UnsupportedImage);
+        }
+
+        let width = info.width as usize;
+        let height = info.height as usize;
+        let samples = color_type.samples();
+        let line_size = reader.output_line_size(info.width);
+
+        let mut carvable = Vec::<bool>::with_capacity(width * height);
+        for row in buffer.chunks(line_size).take(height) {
+            for pixel in row.chunks(samples).take(width) {
+                carvable.push(is_white(color_type, pixel));
+            }
+        }
+
+        Ok(Self::new(width, height, carvable))
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    // Pixel (x, y) from the top left of the image, outside of the image is never carvable
+    pub fn is_carvable(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height && self.carvable[y * self.width + x]
+    }
+
+    // Is the tile (x, y) of a maze with this size on a white pixel
+    pub fn is_carvable_tile(
+        &self,
+        x: usize,
+        y: usize,
+        maze_width: usize,
+        maze_height: usize,
+    ) -> bool {
+        if x >= maze_width || y >= maze_height {
+            return false;
+        }
+
+        // Use the middle of the tile and turn y around, north (higher y) is the top of the image
+        let pixel_x = (2 * x + 1) * self.width / (2 * maze_width);
+        let pixel_y = (2 * (maze_height - 1 - y) + 1) * self.height / (2 * maze_height);
+        self.is_carvable(pixel_x, pixel_y)
+    }
+
+    // Turn every cell that is not on a white pixel into BORDER.
+    // Only the biggest group of cells that are next to each other is kept, so the
+    // player can always reach every cell. WALLS and pillars that are only next to
+    // BORDER cells become BORDER too, this gives the maze the shape of the mask.
+    pub fn apply(&self, maze: &mut Maze) {
+        let (cells_x, cells_y) = maze.cell_count();
+        let mut usable = vec![vec![false; cells_x]; cells_y];
+
+        for (cell_y, row) in usable.iter_mut().enumerate() {
+            for (cell_x, is_usable) in row.iter_mut().enumerate() {
+                let (tile_x, tile_y) = Maze::cell_to_tile(&(cell_x, cell_y));
+                *is_usable = self.is_carvable_tile(tile_x, tile_y, maze.width(), maze.height());
+            }
+        }
+        // Without any white cells there would be no maze left, so don't use the mask
+        if !usable.iter().flatten().any(|is_usable| *is_usable) {
+            return;
+        }
+        keep_biggest_region(maze, &mut usable);
+
+        for (x, y) in maze.positions().collect::<Vec<(usize, usize)>>() {
+            if !is_next_to_usable_cell(&usable, x, y) {
+                maze.set_tile_type(x, y, TileType::Border);
+            }
+        }
+    }
+}
+
+// White enough and not see-through
+fn is_white(color_type: png::ColorType, pixel: &[u8]) -> bool {
+    let (colors, alpha) = match color_type {
+        png::ColorType::Grayscale => (&pixel[..1], None),
+        png::ColorType::GrayscaleAlpha => (&pixel[..1], Some(pixel[1])),
+        png::ColorType::RGBA => (&pixel[..3], Some(pixel[3])),
+        _ => (&pixel[..3], None),
+    };
+
+    colors.iter().all(|color| *color >= WHITE_THRESHOLD)
+        && alpha.is_none_or(|alpha| alpha >= WHITE_THRESHOLD)
+}
+
+// A mask can have separate white areas. The player can't walk from one to the other,
+// so we only keep the area with the most cells.
+fn keep_biggest_region(maze: &Maze, usable: &mut [Vec<bool>]) {
+    let (cells_x, cells_y) = maze.cell_count();
+    let mut region_ids = vec![vec![None; cells_x]; cells_y];
+    let mut region_sizes = Vec::<usize>::new();
+
+    for start_y in 0..cells_y {
+        for start_x in 0..cells_x {
+            if !usable[start_y][start_x] || region_ids[start_y][start_x].is_some() {
+                continue;
+            }
+
+            let region_id = region_sizes.len();
+            let mut region_size = 0;
+            let mut to_visit = VecDeque::from(vec![(start_x, start_y)]);
+            region_ids[start_y][start_x] = Some(region_id);
+
+            while let Some((x, y)) = to_visit.pop_front() {
+                region_size += 1;
+
+                for (next_x, next_y) in maze.cell_neighbours(&(x, y)) {
+                    if usable[next_y][next_x] && region_ids[next_y][next_x].is_none() {
+                        region_ids[next_y][next_x] = Some(region_id);
+                        to_visit.push_back((next_x, next_y));
+                    }
+                }
+            }
+            region_sizes.push(region_size);
+        }
+    }
+
+    let biggest_region = (0..region_sizes.len()).max_by_key(|region_id| region_sizes[*region_id]);
+
+    for (y, row) in usable.iter_mut().enumerate() {
+        for (x, is_usable) in row.iter_mut().enumerate() {
+            *is_usable = region_ids[y][x].is_some() && region_ids[y][x] == biggest_region;
+        }
+    }
+}
+
+// Cells are on odd tiles, so a tile touches the cells on the odd positions next to
+// (or on) it. A WALL touches two cells, a pillar four.
+fn is_next_to_usable_cell(usable: &[Vec<bool>], x: usize, y: usize) -> bool {
+    touching_cells(y).iter().any(|cell_y| {
+        touching_cells(x).iter().any(|cell_x| {
+            usable
+                .get(*cell_y)
+                .and_then(|row| row.get(*cell_x))
+                .copied()
+                .unwrap_or(false)
+        })
+    })
+}
+
+// Cells in one direction (x or y) that are on or next to this tile position
+fn touching_cells(position: usize) -> Vec<usize> {
+    if position % 2 == 1 {
+        vec![position / 2]
+    } else if position > 0 {
+        vec![position / 2 - 1, position / 2]
+    } else {
+        vec![]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A '#' is a white pixel, rows from the top of the image
+    fn mask_from_rows(rows: &[&str]) -> MazeMask {
+        let carvable = rows
+            .iter()
+            .flat_map(|row| row.chars().map(|pixel| pixel == '#'))
+            .collect();
+        MazeMask::new(rows[0].len(), rows.len(), carvable)
+    }
+
+    #[test]
+    fn only_cells_on_white_pixels_are_usable() {
+        let maze_mask = mask_from_rows(&["###..", "#....", "#...."]);
+        let mut maze = Maze::new(11, 7);
+        maze_mask.apply(&mut maze);
+
+        // The top of the image is the north side, which has the highest y
+        assert_eq!(
+            maze.usable_cells(),
+            vec![(0, 0), (0, 1), (0, 2), (1, 2), (2, 2)]
+        );
+        assert_eq!(maze.tile_type(7, 1), Some(&TileType::Border));
+        // A WALL next to a usable cell stays, one between two BORDER cells goes
+        assert_eq!(maze.tile_type(1, 2), Some(&TileType::Wall));
+        assert_eq!(maze.tile_type(2, 1), Some(&TileType::Wall));
+        assert_eq!(maze.tile_type(4, 1), Some(&TileType::Border));
+    }
+
+    #[test]
+    fn only_the_biggest_white_area_is_kept() {
+        let maze_mask = mask_from_rows(&["##..#", "....#", "##..#"]);
+        let mut maze = Maze::new(11, 7);
+        maze_mask.apply(&mut maze);
+
+        assert_eq!(maze.usable_cells(), vec![(4, 0), (4, 1), (4, 2)]);
+    }
+
+    #[test]
+    fn mask_without_white_is_not_used() {
+        let maze_mask = mask_from_rows(&["....."]);
+        let mut maze = Maze::new(11, 7);
+        maze_mask.apply(&mut maze);
+
+        assert_eq!(maze, Maze::new(11, 7));
+    }
+
+    #[test]
+    fn mask_is_stretched_over_the_maze() {
+        // The left half of the image is white
+        let maze_mask = mask_from_rows(&["#.", "#."]);
+        let mut maze = Maze::new(9, 9);
+        maze_mask.apply(&mut maze);
+
+        assert_eq!(maze.usable_cells().len(), 8);
+        assert!(maze.usable_cells().iter().all(|(x, _)| *x < 2));
+    }
+
+    #[test]
+    fn white_opaque_pixels_of_a_png_are_carvable() {
+        // 2 x 2 RGBA: white, black, see-through white and light gray
+        let pixels = [
+            255, 255, 255, 255, 0, 0, 0, 255, //
+            255, 255, 255, 0, 200, 200, 200, 255,
+        ];
+        let mut png_bytes = Vec::<u8>::new();
+        {
+            let mut encoder = png::Encoder::new(&mut png_bytes, 2, 2);
+            encoder.set_color(png::ColorType::RGBA);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder.write_header().unwrap();
+            writer.write_image_data(&pixels).unwrap();
+        }
+
+        let maze_mask = MazeMask::from_png_bytes(&png_bytes).unwrap();
+        assert_eq!((maze_mask.width(), maze_mask.height()), (2, 2));
+        assert!(maze_mask.is_carvable(0, 0));
+        assert!(!maze_mask.is_carvable(1, 0));
+        assert!(!maze_mask.is_carvable(0, 1));
+        assert!(maze_mask.is_carvable(1, 1));
+        assert!(!maze_mask.is_carvable(2, 0));
+    }
+
+    #[test]
+    fn not_a_png_is_an_error() {
+        assert!(matches!(
+            MazeMask::from_png_bytes(b"not a png"),
+            Err(MaskError::Png(_))
+        ));
+    }
+}
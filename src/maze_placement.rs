@@ -0,0 +1,259 @@
+use crate::maze::Maze;
+use crate::maze_braid;
+use crate::maze_solver;
+use crate::maze_tile::TileType;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use serde::{Deserialize, Serialize};
+
+// Tiles of cells on the side with the START and on the side with the EXIT
+type OppositeSides = (Vec<(usize, usize)>, Vec<(usize, usize)>);
+
+// Number of fake exits for DecoyExits when we go through the strategies in the game
+pub const DEFAULT_DECOY_EXITS: usize = 3;
+
+// Where the START and EXIT go in a maze
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum PlacementStrategy {
+    // Random START, the EXIT is the tile that is the farthest away from it
+    FarthestExit,
+    // START on the west side and EXIT on the east side of the maze.
+    // For a maze that is higher than it is wide, south and north.
+    OppositeBorders,
+    // START in the south west corner, the EXIT is the farthest away from it
+    FixedCorner,
+    // Same as FarthestExit, but some dead ends far away get a fake EXIT
+    DecoyExits(usize),
+}
+
+impl PlacementStrategy {
+    pub fn name(&self) -> &'static str {
+        match self {
+            PlacementStrategy::FarthestExit => "Farthest exit",
+            PlacementStrategy::OppositeBorders => "Opposite borders",
+            PlacementStrategy::FixedCorner => "Fixed corner",
+            PlacementStrategy::DecoyExits(_) => "Decoy exits",
+        }
+    }
+
+    // Used to go through all strategies in the game
+    pub fn next(&self) -> Self {
+        match self {
+            PlacementStrategy::FarthestExit => PlacementStrategy::OppositeBorders,
+            PlacementStrategy::OppositeBorders => PlacementStrategy::FixedCorner,
+            PlacementStrategy::FixedCorner => PlacementStrategy::DecoyExits(DEFAULT_DECOY_EXITS),
+            PlacementStrategy::DecoyExits(_) => PlacementStrategy::FarthestExit,
+        }
+    }
+}
+
+// Put the START and EXIT in a carved maze (with its loops) and give the path between them.
+// start is where the START goes, unless the strategy chooses another place for it.
+pub fn place_start_and_exit(
+    maze: &mut Maze,
+    start: &(usize, usize),
+    placement: &PlacementStrategy,
+    rng: &mut StdRng,
+) -> Vec<(usize, usize)> {
+    let start = match placement {
+        PlacementStrategy::FarthestExit | PlacementStrategy::DecoyExits(_) => *start,
+        PlacementStrategy::FixedCorner => {
+            // The first cell row by row is the one closest to the south west corner
+            get_free_cells(maze).first().copied().unwrap_or(*start)
+        }
+        PlacementStrategy::OppositeBorders => {
+            let (first_cells, last_cells) = get_opposite_border_cells(maze);
+
+            match (first_cells.choose(rng), last_cells.choose(rng)) {
+                (Some(first), Some(last)) if first != last => {
+                    if let Some(solution) = maze_solver::breadth_first_path(maze, first, last) {
+                        maze.set_tile_type(first.0, first.1, TileType::Start);
+                        maze.set_tile_type(last.0, last.1, TileType::Exit);
+                        return solution;
+                    }
+                    *first
+                }
+                _ => *start,
+            }
+        }
+    };
+    // Only the chosen START is changed, so a ROOM stays a ROOM where the START could have been
+    maze.set_tile_type(start.0, start.1, TileType::Start);
+
+    let solution = maze_solver::path_to_farthest_tile(maze, &start);
+    let (exit_x, exit_y) = *solution.last().unwrap();
+    maze.set_tile_type(exit_x, exit_y, TileType::Exit);
+
+    if let PlacementStrategy::DecoyExits(decoy_count) = placement {
+        place_decoy_exits(maze, &start, *decoy_count);
+    }
+    solution
+}
+
+// Tiles of the cells where the START and EXIT can go, row by row
+fn get_free_cells(maze: &Maze) -> Vec<(usize, usize)> {
+    maze.usable_cells()
+        .iter()
+        .map(Maze::cell_to_tile)
+        .filter(|(x, y)| {
+            matches!(
+                maze.tile_type(*x, *y),
+                Some(TileType::Open) | Some(TileType::Room)
+            )
+        })
+        .collect()
+}
+
+// Free cells in the first and last column, or the first and last row for a high maze.
+// With a mask these are the cells the most to the west and east (or south and north).
+fn get_opposite_border_cells(maze: &Maze) -> OppositeSides {
+    let free_cells = get_free_cells(maze);
+    let side_of = |(x, y): &(usize, usize)| {
+        if maze.width() >= maze.height() {
+            *x
+        } else {
+            *y
+        }
+    };
+
+    let first_side = free_cells.iter().map(side_of).min();
+    let last_side = free_cells.iter().map(side_of).max();
+
+    (
+        free_cells
+            .iter()
+            .filter(|cell| Some(side_of(cell)) == first_side)
+            .copied()
+            .collect(),
+        free_cells
+            .iter()
+            .filter(|cell| Some(side_of(cell)) == last_side)
+            .copied()
+            .collect(),
+    )
+}
+
+// The dead ends the farthest away from the start look the most like a real exit
+fn place_decoy_exits(maze: &mut Maze, start: &(usize, usize), decoy_count: usize) {
+    let distances = maze_solver::get_distances(maze, start);
+
+    let mut dead_ends: Vec<(usize, usize)> = maze_braid::get_dead_ends(maze)
+        .iter()
+        .map(Maze::cell_to_tile)
+        .filter(|(x, y)| maze.tile_type(*x, *y) == Some(&TileType::Open))
+        .collect();
+    dead_ends.sort_by_key(|(x, y)| std::cmp::Reverse(distances[*y][*x]));
+
+    for (x, y) in dead_ends.into_iter().take(decoy_count) {
+        maze.set_tile_type(x, y, TileType::DecoyExit);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze_generator::{self, MazeAlgorithmType};
+    use rand::SeedableRng;
+
+    fn place(
+        width: usize,
+        height: usize,
+        placement: &PlacementStrategy,
+    ) -> (Maze, Vec<(usize, usize)>) {
+        let mut rng = StdRng::seed_from_u64(21);
+        maze_generator::create_maze(
+            width,
+            height,
+            &None,
+            &MazeAlgorithmType::HuntAndKill,
+            20,
+            placement,
+            &mut rng,
+        )
+    }
+
+    fn count_tiles(maze: &Maze, tile_type: &TileType) -> usize {
+        maze.positions()
+            .filter(|(x, y)| maze.tile_type(*x, *y) == Some(tile_type))
+            .count()
+    }
+
+    #[test]
+    fn every_strategy_has_one_start_and_one_exit() {
+        let mut placement = PlacementStrategy::FarthestExit;
+        loop {
+            let (maze, solution) = place(25, 25, &placement);
+
+            assert_eq!(
+                count_tiles(&maze, &TileType::Start),
+                1,
+                "{}",
+                placement.name()
+            );
+            assert_eq!(
+                count_tiles(&maze, &TileType::Exit),
+                1,
+                "{}",
+                placement.name()
+            );
+            let (start, exit) = (solution[0], *solution.last().unwrap());
+            assert_eq!(maze.tile_type(start.0, start.1), Some(&TileType::Start));
+            assert_eq!(maze.tile_type(exit.0, exit.1), Some(&TileType::Exit));
+            assert_eq!(
+                maze_solver::breadth_first_path(&maze, &start, &exit).map(|path| path.len()),
+                Some(solution.len())
+            );
+
+            placement = placement.next();
+            if placement == PlacementStrategy::FarthestExit {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn farthest_exit_is_the_farthest_tile() {
+        let (maze, solution) = place(25, 25, &PlacementStrategy::FarthestExit);
+
+        let farthest = maze_solver::get_distances(&maze, &solution[0])
+            .into_iter()
+            .flatten()
+            .flatten()
+            .max();
+        assert_eq!(farthest, Some(solution.len() - 1));
+    }
+
+    #[test]
+    fn opposite_borders_go_from_west_to_east() {
+        let (maze, solution) = place(31, 21, &PlacementStrategy::OppositeBorders);
+        assert_eq!(solution[0].0, 1);
+        assert_eq!(solution.last().unwrap().0, maze.width() - 2);
+
+        // Higher than it is wide, from south to north
+        let (maze, solution) = place(21, 31, &PlacementStrategy::OppositeBorders);
+        assert_eq!(solution[0].1, 1);
+        assert_eq!(solution.last().unwrap().1, maze.height() - 2);
+    }
+
+    #[test]
+    fn fixed_corner_starts_in_the_south_west() {
+        let (_, solution) = place(25, 25, &PlacementStrategy::FixedCorner);
+        assert_eq!(solution[0], (1, 1));
+    }
+
+    #[test]
+    fn decoy_exits_are_dead_ends_off_the_solution() {
+        let (maze, solution) = place(25, 25, &PlacementStrategy::DecoyExits(3));
+
+        let decoys: Vec<(usize, usize)> = maze
+            .positions()
+            .filter(|(x, y)| maze.tile_type(*x, *y) == Some(&TileType::DecoyExit))
+            .collect();
+        assert_eq!(decoys.len(), 3);
+        for (x, y) in decoys {
+            assert_eq!(maze.passable_neighbours(x, y).len(), 1);
+            assert!(!solution.contains(&(x, y)));
+        }
+    }
+}
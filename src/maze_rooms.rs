@@ -0,0 +1,246 @@
+use crate::maze::Maze;
+use crate::maze_tile::TileType;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+// When rooms keep overlapping we stop trying, so a small maze can get less rooms
+const PLACE_TRIES_PER_ROOM: usize = 20;
+
+// Sizes are in cells, a room of 3 by 2 cells is 5 by 3 tiles
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct RoomSettings {
+    pub room_count: usize,
+    pub min_room_size: usize,
+    pub max_room_size: usize,
+}
+
+impl Default for RoomSettings {
+    fn default() -> Self {
+        Self {
+            room_count: 8,
+            min_room_size: 2,
+            max_room_size: 5,
+        }
+    }
+}
+
+// A rectangle of cells, (x, y) is the cell in the south west corner
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Room {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Room {
+    pub fn contains_cell(&self, cell: &(usize, usize)) -> bool {
+        cell.0 >= self.x
+            && cell.0 < self.x + self.width
+            && cell.1 >= self.y
+            && cell.1 < self.y + self.height
+    }
+
+    // All cells in the room, row by row
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize)> {
+        let (x, width) = (self.x, self.width);
+        (self.y..self.y + self.height).flat_map(move |y| (x..x + width).map(move |x| (x, y)))
+    }
+
+    // There needs to be at least one cell between two rooms for a corridor
+    fn is_too_close(&self, other: &Room) -> bool {
+        self.x < other.x + other.width + 1
+            && other.x < self.x + self.width + 1
+            && self.y < other.y + other.height + 1
+            && other.y < self.y + self.height + 1
+    }
+}
+
+// Place rooms at random places where they don't touch each other.
+// Every tile inside a room becomes ROOM, the WALLS around it stay closed.
+pub fn place_rooms(maze: &mut Maze, room_settings: &RoomSettings, rng: &mut StdRng) -> Vec<Room> {
+    let (cells_x, cells_y) = maze.cell_count();
+    let min_room_size = room_settings.min_room_size.max(1);
+    let max_room_size = room_settings.max_room_size.max(min_room_size);
+    let mut rooms = Vec::<Room>::new();
+
+    for _ in 0..room_settings.room_count * PLACE_TRIES_PER_ROOM {
+        if rooms.len() >= room_settings.room_count {
+            break;
+        }
+
+        let width = rng.gen_range(min_room_size..=max_room_size);
+        let height = rng.gen_range(min_room_size..=max_room_size);
+        if width > cells_x || height > cells_y {
+            continue;
+        }
+
+        let room = Room {
+            x: rng.gen_range(0..=cells_x - width),
+            y: rng.gen_range(0..=cells_y - height),
+            width,
+            height,
+        };
+
+        // Rooms also need to stay inside a mask
+        if room.cells().all(|cell| maze.is_usable_cell(&cell))
+            && !rooms.iter().any(|other_room| room.is_too_close(other_room))
+        {
+            carve_room(maze, &room);
+            rooms.push(room);
+        }
+    }
+    rooms
+}
+
+pub fn is_room_cell(maze: &Maze, cell: &(usize, usize)) -> bool {
+    let (x, y) = Maze::cell_to_tile(cell);
+
+    maze.tile_type(x, y) == Some(&TileType::Room)
+}
+
+// The recursive backtracker through every cell that is not in a room.
+// Rooms can cut the corridors in separate parts, so we start again in every part we
+// didn't reach yet. The parts and the rooms still need to be connected afterwards.
+pub fn carve_corridors(maze: &mut Maze, rng: &mut StdRng) {
+    let (cells_x, cells_y) = maze.cell_count();
+    let mut visited = vec![vec![false; cells_x]; cells_y];
+
+    let mut start_cells = maze.usable_cells();
+    for (x, y) in start_cells.iter() {
+        visited[*y][*x] = is_room_cell(maze, &(*x, *y));
+    }
+    start_cells.shuffle(rng);
+
+    for start in start_cells {
+        if visited[start.1][start.0] {
+            continue;
+        }
+        visited[start.1][start.0] = true;
+        let mut stack = vec![start];
+
+        while let Some(&cell) = stack.last() {
+            let unvisited: Vec<(usize, usize)> = maze
+                .cell_neighbours(&cell)
+                .into_iter()
+                .filter(|(x, y)| !visited[*y][*x])
+                .collect();
+
+            if let Some(&next_cell) = unvisited.choose(rng) {
+                maze.carve_passage(&cell, &next_cell);
+                visited[next_cell.1][next_cell.0] = true;
+                stack.push(next_cell);
+            } else {
+                // Dead end, go back one step
+                stack.pop();
+            }
+        }
+    }
+}
+
+fn carve_room(maze: &mut Maze, room: &Room) {
+    let (first_x, first_y) = Maze::cell_to_tile(&(room.x, room.y));
+    let (last_x, last_y) = Maze::cell_to_tile(&(room.x + room.width - 1, room.y + room.height - 1));
+
+    for y in first_y..=last_y {
+        for x in first_x..=last_x {
+            maze.set_tile_type(x, y, TileType::Room);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    const SEED: u64 = 42;
+
+    #[test]
+    fn room_cells_are_inside_of_the_room() {
+        let room = Room {
+            x: 2,
+            y: 1,
+            width: 3,
+            height: 2,
+        };
+
+        assert_eq!(room.cells().count(), 6);
+        assert!(room.cells().all(|cell| room.contains_cell(&cell)));
+        assert!(room.contains_cell(&(4, 2)));
+        assert!(!room.contains_cell(&(5, 2)));
+        assert!(!room.contains_cell(&(2, 0)));
+    }
+
+    #[test]
+    fn rooms_are_open_inside_and_never_touch() {
+        let mut maze = Maze::new(41, 41);
+        let room_settings = RoomSettings::default();
+        let rooms = place_rooms(&mut maze, &room_settings, &mut StdRng::seed_from_u64(SEED));
+
+        assert!(!rooms.is_empty() && rooms.len() <= room_settings.room_count);
+        for (index, room) in rooms.iter().enumerate() {
+            let sizes = room_settings.min_room_size..=room_settings.max_room_size;
+            assert!(sizes.contains(&room.width) && sizes.contains(&room.height));
+
+            // The WALLS between the cells of a room are gone, the ones around it stay
+            let (first_x, first_y) = Maze::cell_to_tile(&(room.x, room.y));
+            let (last_x, last_y) =
+                Maze::cell_to_tile(&(room.x + room.width - 1, room.y + room.height - 1));
+            for (x, y) in maze.positions() {
+                let is_inside = (first_x..=last_x).contains(&x) && (first_y..=last_y).contains(&y);
+                if is_inside {
+                    assert_eq!(maze.tile_type(x, y), Some(&TileType::Room));
+                } else if (first_x - 1..=last_x + 1).contains(&x)
+                    && (first_y - 1..=last_y + 1).contains(&y)
+                {
+                    assert!(!maze.is_passable(x, y));
+                }
+            }
+
+            for other_room in rooms.iter().skip(index + 1) {
+                assert!(!room.is_too_close(other_room));
+            }
+        }
+    }
+
+    #[test]
+    fn rooms_stay_inside_of_a_mask() {
+        // Only the west half of the maze is left
+        let mut maze = Maze::new(21, 21);
+        for (x, y) in maze.positions().collect::<Vec<(usize, usize)>>() {
+            if x > 10 {
+                maze.set_tile_type(x, y, TileType::Border);
+            }
+        }
+
+        let rooms = place_rooms(
+            &mut maze,
+            &RoomSettings::default(),
+            &mut StdRng::seed_from_u64(SEED),
+        );
+        assert!(!rooms.is_empty());
+        assert!(rooms
+            .iter()
+            .flat_map(|room| room.cells())
+            .all(|cell| maze.is_usable_cell(&cell)));
+    }
+
+    #[test]
+    fn corridors_go_through_every_cell_outside_of_the_rooms() {
+        let mut maze = Maze::new(31, 31);
+        let mut rng = StdRng::seed_from_u64(SEED);
+        place_rooms(&mut maze, &RoomSettings::default(), &mut rng);
+        carve_corridors(&mut maze, &mut rng);
+
+        for cell in maze.usable_cells() {
+            if !is_room_cell(&maze, &cell) {
+                let (x, y) = Maze::cell_to_tile(&cell);
+                assert!(!maze.passable_neighbours(x, y).is_empty(), "{:?}", cell);
+            }
+        }
+    }
+}
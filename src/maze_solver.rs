@@ -0,0 +1,359 @@
+use crate::maze::Maze;
+use crate::maze_floors::{FloorPosition, MultiFloorMaze};
+use crate::maze_tile::TileType;
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+
+// Everything the solvers can walk through, a Maze or a MultiFloorMaze
+pub trait Walkable {
+    type Position: Copy + Eq + Ord;
+
+    // Every position has its own index, from 0 up to the number of positions
+    fn position_count(&self) -> usize;
+    fn position_index(&self, position: &Self::Position) -> usize;
+    // Outside of the maze is never passable
+    fn is_passable_position(&self, position: &Self::Position) -> bool;
+    fn passable_neighbours_of(&self, position: &Self::Position) -> Vec<Self::Position>;
+    // Can never be more than the real number of steps (with max_step_distance 1), A* needs this
+    fn estimated_steps(&self, position: &Self::Position, other_position: &Self::Position) -> usize;
+    // Most tiles one step can go, a step over a BRIDGE goes two tiles
+    fn max_step_distance(&self) -> usize {
+        1
+    }
+    // Tiles a step goes over without stopping on them (the BRIDGE), in walking order
+    fn tiles_between(
+        &self,
+        _position: &Self::Position,
+        _other_position: &Self::Position,
+    ) -> Vec<Self::Position> {
+        Vec::new()
+    }
+}
+
+impl Walkable for Maze {
+    type Position = (usize, usize);
+
+    fn position_count(&self) -> usize {
+        self.width() * self.height()
+    }
+
+    fn position_index(&self, position: &(usize, usize)) -> usize {
+        position.1 * self.width() + position.0
+    }
+
+    fn is_passable_position(&self, position: &(usize, usize)) -> bool {
+        self.is_passable(position.0, position.1)
+    }
+
+    fn passable_neighbours_of(&self, position: &(usize, usize)) -> Vec<(usize, usize)> {
+        self.passable_neighbours(position.0, position.1)
+    }
+
+    fn estimated_steps(&self, position: &(usize, usize), other_position: &(usize, usize)) -> usize {
+        get_manhattan_distance(position, other_position)
+    }
+
+    fn max_step_distance(&self) -> usize {
+        if self.positions().any(|(x, y)| self.is_bridge(x, y)) {
+            2
+        } else {
+            1
+        }
+    }
+
+    fn tiles_between(
+        &self,
+        position: &(usize, usize),
+        other_position: &(usize, usize),
+    ) -> Vec<(usize, usize)> {
+        // Only a straight step over a BRIDGE goes two tiles
+        let middle = (
+            (position.0 + other_position.0) / 2,
+            (position.1 + other_position.1) / 2,
+        );
+        if get_manhattan_distance(position, other_position) == 2
+            && (position.0 == other_position.0 || position.1 == other_position.1)
+            && self.is_bridge(middle.0, middle.1)
+        {
+            vec![middle]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+// For every position (by index), the position we came from. None if we never got there.
+type CameFrom<P> = Vec<Option<P>>;
+
+// Both solvers give a shortest path, A* is faster because it looks at less tiles
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SolverType {
+    BreadthFirst,
+    AStar,
+}
+
+// Find the START and EXIT tile and give the shortest path between them
+pub fn solve_maze(maze: &Maze, solver_type: &SolverType) -> Option<Vec<(usize, usize)>> {
+    let start = find_tile(maze, &TileType::Start)?;
+    let exit = find_tile(maze, &TileType::Exit)?;
+
+    match solver_type {
+        SolverType::BreadthFirst => breadth_first_path(maze, &start, &exit),
+        SolverType::AStar => a_star_path(maze, &start, &exit),
+    }
+}
+
+// Same as solve_maze, but the way to the exit can go up and down ladders
+pub fn solve_multi_floor_maze(
+    maze: &MultiFloorMaze,
+    solver_type: &SolverType,
+) -> Option<Vec<FloorPosition>> {
+    let start = maze.find_tile(&TileType::Start)?;
+    let exit = maze.find_tile(&TileType::Exit)?;
+
+    match solver_type {
+        SolverType::BreadthFirst => breadth_first_path(maze, &start, &exit),
+        SolverType::AStar => a_star_path(maze, &start, &exit),
+    }
+}
+
+// Position of the first tile with this TileType
+pub fn find_tile(maze: &Maze, tile_type: &TileType) -> Option<(usize, usize)> {
+    maze.positions()
+        .find(|(x, y)| maze.tile_type(*x, *y) == Some(tile_type))
+}
+
+// Shortest path from start to exit (both included), None if the exit can't be reached
+pub fn breadth_first_path<W: Walkable>(
+    maze: &W,
+    start: &W::Position,
+    exit: &W::Position,
+) -> Option<Vec<W::Position>> {
+    let (came_from, _) = walk_breadth_first(maze, start);
+
+    if maze.is_passable_position(exit) && came_from[maze.position_index(exit)].is_some() {
+        Some(get_path_from(maze, &came_from, start, exit))
+    } else {
+        None
+    }
+}
+
+// Same as breadth_first_path, but goes to the tiles closest to the exit first
+pub fn a_star_path<W: Walkable>(
+    maze: &W,
+    start: &W::Position,
+    exit: &W::Position,
+) -> Option<Vec<W::Position>> {
+    if !maze.is_passable_position(start) {
+        return None;
+    }
+
+    let mut came_from = vec![None; maze.position_count()];
+    let mut steps = vec![usize::MAX; maze.position_count()];
+    // Lowest (steps + distance to the exit) first
+    let mut to_visit = BinaryHeap::<Reverse<(usize, usize, W::Position)>>::new();
+    // With long steps the exit can be closer than it looks
+    let max_step_distance = maze.max_step_distance();
+    let estimated_steps = |position: &W::Position| {
+        maze.estimated_steps(position, exit)
+            .div_ceil(max_step_distance)
+    };
+
+    came_from[maze.position_index(start)] = Some(*start);
+    steps[maze.position_index(start)] = 0;
+    to_visit.push(Reverse((estimated_steps(start), 0, *start)));
+
+    while let Some(Reverse((_, current_steps, position))) = to_visit.pop() {
+        if position == *exit {
+            return Some(get_path_from(maze, &came_from, start, exit));
+        }
+
+        // We already found a shorter way to this tile
+        if current_steps > steps[maze.position_index(&position)] {
+            continue;
+        }
+
+        for next_position in maze.passable_neighbours_of(&position) {
+            let next_steps = current_steps + 1;
+            let next_index = maze.position_index(&next_position);
+
+            if next_steps < steps[next_index] {
+                steps[next_index] = next_steps;
+                came_from[next_index] = Some(position);
+                to_visit.push(Reverse((
+                    next_steps + estimated_steps(&next_position),
+                    next_steps,
+                    next_position,
+                )));
+            }
+        }
+    }
+    None
+}
+
+// Number of steps from the start to every tile, None if the tile can't be reached
+pub fn get_distances(maze: &Maze, start: &(usize, usize)) -> Vec<Vec<Option<usize>>> {
+    let mut distances = vec![vec![None; maze.width()]; maze.height()];
+    let mut to_visit = VecDeque::<(usize, usize)>::new();
+
+    if maze.is_passable(start.0, start.1) {
+        distances[start.1][start.0] = Some(0);
+        to_visit.push_back(*start);
+    }
+
+    while let Some((pos_x, pos_y)) = to_visit.pop_front() {
+        let next_distance = distances[pos_y][pos_x].map(|distance| distance + 1);
+
+        for (next_x, next_y) in maze.passable_neighbours(pos_x, pos_y) {
+            if distances[next_y][next_x].is_none() {
+                distances[next_y][next_x] = next_distance;
+                to_visit.push_back((next_x, next_y));
+            }
+        }
+    }
+    distances
+}
+
+// Path to the tile that is the farthest away from the start.
+// For a maze without loops this is the longest path in the maze from this start.
+pub fn path_to_farthest_tile<W: Walkable>(maze: &W, start: &W::Position) -> Vec<W::Position> {
+    let (came_from, farthest) = walk_breadth_first(maze, start);
+
+    get_path_from(maze, &came_from, start, &farthest)
+}
+
+// For every tile we can reach from the start, where we came from.
+// Also gives the last tile we reached, which is the farthest away from the start.
+fn walk_breadth_first<W: Walkable>(
+    maze: &W,
+    start: &W::Position,
+) -> (CameFrom<W::Position>, W::Position) {
+    let mut came_from = vec![None; maze.position_count()];
+    let mut to_visit = VecDeque::<W::Position>::new();
+    let mut farthest = *start;
+
+    if maze.is_passable_position(start) {
+        came_from[maze.position_index(start)] = Some(*start);
+        to_visit.push_back(*start);
+    }
+
+    while let Some(position) = to_visit.pop_front() {
+        farthest = position;
+
+        for next_position in maze.passable_neighbours_of(&position) {
+            let next_index = maze.position_index(&next_position);
+
+            if came_from[next_index].is_none() {
+                came_from[next_index] = Some(position);
+                to_visit.push_back(next_position);
+            }
+        }
+    }
+    (came_from, farthest)
+}
+
+// Go back from the end to the start and turn it around.
+// The path has every tile the player walks on, also the BRIDGES that a step goes over.
+fn get_path_from<W: Walkable>(
+    maze: &W,
+    came_from: &CameFrom<W::Position>,
+    start: &W::Position,
+    end: &W::Position,
+) -> Vec<W::Position> {
+    let mut path = vec![*end];
+    let mut current = *end;
+
+    while current != *start {
+        let previous = came_from[maze.position_index(&current)].unwrap();
+        path.extend(maze.tiles_between(&previous, &current).into_iter().rev());
+        path.push(previous);
+        current = previous;
+    }
+    path.reverse();
+    path
+}
+
+pub fn get_manhattan_distance(position: &(usize, usize), other_position: &(usize, usize)) -> usize {
+    position.0.abs_diff(other_position.0) + position.1.abs_diff(other_position.1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::maze_generator::{self, MazeAlgorithmType};
+    use crate::maze_placement::PlacementStrategy;
+    use rand::rngs::StdRng;
+    use rand::seq::SliceRandom;
+    use rand::SeedableRng;
+
+    fn loop_maze(maze_algorithm: &MazeAlgorithmType) -> Maze {
+        let mut rng = StdRng::seed_from_u64(11);
+        let (maze, _) = maze_generator::create_maze(
+            25,
+            25,
+            &None,
+            maze_algorithm,
+            50,
+            &PlacementStrategy::FarthestExit,
+            &mut rng,
+        );
+        maze
+    }
+
+    fn walkable_tiles(maze: &Maze) -> Vec<(usize, usize)> {
+        maze.positions()
+            .filter(|(x, y)| maze.is_passable(*x, *y) && !maze.is_bridge(*x, *y))
+            .collect()
+    }
+
+    #[test]
+    fn a_star_is_as_short_as_breadth_first() {
+        for maze_algorithm in [MazeAlgorithmType::Kruskal, MazeAlgorithmType::Weave] {
+            let maze = loop_maze(&maze_algorithm);
+            let tiles = walkable_tiles(&maze);
+            let mut rng = StdRng::seed_from_u64(5);
+
+            for _ in 0..50 {
+                let start = tiles.choose(&mut rng).unwrap();
+                let exit = tiles.choose(&mut rng).unwrap();
+
+                let a_star = a_star_path(&maze, start, exit).unwrap();
+                let breadth_first = breadth_first_path(&maze, start, exit).unwrap();
+                assert_eq!(a_star.len(), breadth_first.len());
+                assert_eq!(a_star.first(), Some(start));
+                assert_eq!(a_star.last(), Some(exit));
+            }
+        }
+    }
+
+    #[test]
+    fn path_length_is_the_distance() {
+        let maze = loop_maze(&MazeAlgorithmType::Prim);
+        let tiles = walkable_tiles(&maze);
+        let start = tiles[0];
+        let distances = get_distances(&maze, &start);
+
+        for exit in &tiles {
+            let path = a_star_path(&maze, &start, exit).unwrap();
+            assert_eq!(Some(path.len() - 1), distances[exit.1][exit.0]);
+        }
+    }
+
+    #[test]
+    fn path_goes_over_the_bridge_tile() {
+        let maze = loop_maze(&MazeAlgorithmType::Weave);
+        let bridges: Vec<(usize, usize)> = maze
+            .positions()
+            .filter(|(x, y)| maze.is_bridge(*x, *y))
+            .collect();
+        assert!(!bridges.is_empty());
+
+        for (x, y) in bridges {
+            let over = breadth_first_path(&maze, &(x - 1, y), &(x + 1, y)).unwrap();
+            assert_eq!(over, vec![(x - 1, y), (x, y), (x + 1, y)]);
+            let under = a_star_path(&maze, &(x, y - 1), &(x, y + 1)).unwrap();
+            assert_eq!(under, vec![(x, y - 1), (x, y), (x, y + 1)]);
+        }
+    }
+}
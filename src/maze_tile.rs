@@ -1,5 +1,3 @@
-use bevy::prelude::*;
-
 #[derive(Clone, Debug, PartialEq)]
 pub enum TileType {
     Start,
@@ -7,6 +5,16 @@ pub enum TileType {
     Wall,
     Border,
     Open,
+    // Ladders connect two floors, a LadderUp always has a LadderDown right above it
+    LadderUp,
+    LadderDown,
+    // Two corridors cross here, one over and one under the other.
+    // The player can only walk straight over it, not turn on it.
+    Bridge,
+    // Open tile inside a room, the player can walk here just like on OPEN
+    Room,
+    // Looks just like the EXIT, but it's only a dead end
+    DecoyExit,
 }
 
 // impl<T> PartialEq<T> for TileType {
@@ -0,0 +1,457 @@
+use crate::maze_braid::MAX_LOOPINESS;
+use crate::maze_solver::Walkable;
+
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+// The shape of the cells in the maze.
+// Square mazes use the normal Maze with WALL tiles, hexagonal and triangular mazes
+// use a CellMaze where the walls are the sides of the cells.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum GridTopology {
+    Square,
+    Hexagonal,
+    Triangular,
+}
+
+impl GridTopology {
+    pub fn name(&self) -> &'static str {
+        match self {
+            GridTopology::Square => "Square",
+            GridTopology::Hexagonal => "Hexagonal",
+            GridTopology::Triangular => "Triangular",
+        }
+    }
+
+    // Used to go through all topologies in the game
+    pub fn next(&self) -> Self {
+        match self {
+            GridTopology::Square => GridTopology::Hexagonal,
+            GridTopology::Hexagonal => GridTopology::Triangular,
+            GridTopology::Triangular => GridTopology::Square,
+        }
+    }
+}
+
+// North is up on the screen, which is a higher row
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub enum GridDirection {
+    North,
+    South,
+    East,
+    West,
+    NorthEast,
+    NorthWest,
+    SouthEast,
+    SouthWest,
+}
+
+impl GridDirection {
+    pub fn opposite(&self) -> Self {
+        match self {
+            GridDirection::North => GridDirection::South,
+            GridDirection::South => GridDirection::North,
+            GridDirection::East => GridDirection::West,
+            GridDirection::West => GridDirection::East,
+            GridDirection::NorthEast => GridDirection::SouthWest,
+            GridDirection::NorthWest => GridDirection::SouthEast,
+            GridDirection::SouthEast => GridDirection::NorthWest,
+            GridDirection::SouthWest => GridDirection::NorthEast,
+        }
+    }
+}
+
+const SQUARE_DIRECTIONS: [GridDirection; 4] = [
+    GridDirection::North,
+    GridDirection::South,
+    GridDirection::East,
+    GridDirection::West,
+];
+
+const HEXAGONAL_DIRECTIONS: [GridDirection; 6] = [
+    GridDirection::East,
+    GridDirection::NorthEast,
+    GridDirection::NorthWest,
+    GridDirection::West,
+    GridDirection::SouthWest,
+    GridDirection::SouthEast,
+];
+
+// A maze of cells without WALL tiles, a passage is open or closed between two cells.
+// Positions are (column, row).
+//
+// Hexagonal cells have a point at the top. Every odd row is moved half a cell to the
+// east, so a cell has neighbours to the east, west and diagonally above and below it.
+//
+// Triangular cells take turns pointing up and down. A triangle pointing up has a flat
+// side at the bottom (south), one pointing down has its flat side at the top (north).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CellMaze {
+    topology: GridTopology,
+    columns: usize,
+    rows: usize,
+    // Open passages of every cell, row by row
+    passages: Vec<Vec<GridDirection>>,
+}
+
+impl CellMaze {
+    // All passages are closed
+    pub fn new(topology: GridTopology, columns: usize, rows: usize) -> Self {
+        Self {
+            topology,
+            columns,
+            rows,
+            passages: vec![Vec::new(); columns * rows],
+        }
+    }
+
+    pub fn topology(&self) -> GridTopology {
+        self.topology
+    }
+
+    pub fn columns(&self) -> usize {
+        self.columns
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn is_in_bounds(&self, cell: &(usize, usize)) -> bool {
+        cell.0 < self.columns && cell.1 < self.rows
+    }
+
+    pub fn is_odd_row(row: usize) -> bool {
+        row % 2 == 1
+    }
+
+    pub fn is_triangle_up(cell: &(usize, usize)) -> bool {
+        (cell.0 + cell.1) % 2 != 1
+    }
+
+    // The directions a cell has sides in, with or without a neighbour there
+    pub fn directions(&self, cell: &(usize, usize)) -> Vec<GridDirection> {
+        match self.topology {
+            GridTopology::Square => SQUARE_DIRECTIONS.to_vec(),
+            GridTopology::Hexagonal => HEXAGONAL_DIRECTIONS.to_vec(),
+            GridTopology::Triangular if CellMaze::is_triangle_up(cell) => {
+                vec![
+                    GridDirection::East,
+                    GridDirection::West,
+                    GridDirection::South,
+                ]
+            }
+            GridTopology::Triangular => {
+                vec![
+                    GridDirection::East,
+                    GridDirection::West,
+                    GridDirection::North,
+                ]
+            }
+        }
+    }
+
+    // Cell next to this cell in the given direction, if the cell has a side there
+    // and the other cell is inside the maze
+    pub fn neighbour(
+        &self,
+        cell: &(usize, usize),
+        direction: &GridDirection,
+    ) -> Option<(usize, usize)> {
+        if !self.is_in_bounds(cell) || !self.directions(cell).contains(direction) {
+            return None;
+        }
+
+        let (column, row) = (cell.0 as isize, cell.1 as isize);
+        // Hexagonal cells in odd rows are half a cell more to the east
+        let shift = if CellMaze::is_odd_row(cell.1) { 0 } else { -1 };
+
+        let (next_column, next_row) = match direction {
+            GridDirection::North => (column, row + 1),
+            GridDirection::South => (column, row - 1),
+            GridDirection::East => (column + 1, row),
+            GridDirection::West => (column - 1, row),
+            GridDirection::NorthEast => (column + shift + 1, row + 1),
+            GridDirection::NorthWest => (column + shift, row + 1),
+            GridDirection::SouthEast => (column + shift + 1, row - 1),
+            GridDirection::SouthWest => (column + shift, row - 1),
+        };
+
+        if next_column < 0 || next_row < 0 {
+            return None;
+        }
+
+        let next_cell = (next_column as usize, next_row as usize);
+        if self.is_in_bounds(&next_cell) {
+            Some(next_cell)
+        } else {
+            None
+        }
+    }
+
+    pub fn neighbours(&self, cell: &(usize, usize)) -> Vec<(usize, usize)> {
+        self.directions(cell)
+            .iter()
+            .filter_map(|direction| self.neighbour(cell, direction))
+            .collect()
+    }
+
+    pub fn is_open(&self, cell: &(usize, usize), direction: &GridDirection) -> bool {
+        self.is_in_bounds(cell) && self.passages[self.cell_index(cell)].contains(direction)
+    }
+
+    // Open the passage on both sides, does nothing if there is no cell in that direction
+    pub fn carve_passage(&mut self, cell: &(usize, usize), direction: &GridDirection) {
+        if let Some(next_cell) = self.neighbour(cell, direction) {
+            let index = self.cell_index(cell);
+            let next_index = self.cell_index(&next_cell);
+
+            if !self.passages[index].contains(direction) {
+                self.passages[index].push(*direction);
+                self.passages[next_index].push(direction.opposite());
+            }
+        }
+    }
+
+    // Cells we can walk to from this cell
+    pub fn open_neighbours(&self, cell: &(usize, usize)) -> Vec<(usize, usize)> {
+        self.directions(cell)
+            .iter()
+            .filter(|direction| self.is_open(cell, direction))
+            .filter_map(|direction| self.neighbour(cell, direction))
+            .collect()
+    }
+
+    // All cells (column, row) row by row
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize)> {
+        let columns = self.columns;
+        (0..self.rows).flat_map(move |row| (0..columns).map(move |column| (column, row)))
+    }
+
+    fn cell_index(&self, cell: &(usize, usize)) -> usize {
+        cell.1 * self.columns + cell.0
+    }
+}
+
+impl Walkable for CellMaze {
+    type Position = (usize, usize);
+
+    fn position_count(&self) -> usize {
+        self.columns * self.rows
+    }
+
+    fn position_index(&self, position: &(usize, usize)) -> usize {
+        self.cell_index(position)
+    }
+
+    fn is_passable_position(&self, position: &(usize, usize)) -> bool {
+        self.is_in_bounds(position)
+    }
+
+    fn passable_neighbours_of(&self, position: &(usize, usize)) -> Vec<(usize, usize)> {
+        self.open_neighbours(position)
+    }
+
+    fn estimated_steps(&self, position: &(usize, usize), other_position: &(usize, usize)) -> usize {
+        match self.topology {
+            GridTopology::Hexagonal => get_hexagonal_distance(position, other_position),
+            // Every step changes the column or the row by one
+            GridTopology::Square | GridTopology::Triangular => {
+                position.0.abs_diff(other_position.0) + position.1.abs_diff(other_position.1)
+            }
+        }
+    }
+}
+
+// Walks randomly to unvisited cells and goes back one step when it gets stuck,
+// the same as the RecursiveBacktracker for square mazes
+pub fn carve_cell_maze(maze: &mut CellMaze, rng: &mut StdRng) {
+    if maze.columns == 0 || maze.rows == 0 {
+        return;
+    }
+
+    let mut visited = vec![false; maze.columns * maze.rows];
+    let start = (rng.gen_range(0..maze.columns), rng.gen_range(0..maze.rows));
+    visited[maze.cell_index(&start)] = true;
+    let mut stack = vec![start];
+
+    while let Some(&cell) = stack.last() {
+        let unvisited: Vec<GridDirection> = maze
+            .directions(&cell)
+            .into_iter()
+            .filter(|direction| {
+                maze.neighbour(&cell, direction)
+                    .is_some_and(|next_cell| !visited[maze.cell_index(&next_cell)])
+            })
+            .collect();
+
+        if let Some(direction) = unvisited.choose(rng) {
+            let next_cell = maze.neighbour(&cell, direction).unwrap();
+            maze.carve_passage(&cell, direction);
+            visited[maze.cell_index(&next_cell)] = true;
+            stack.push(next_cell);
+        } else {
+            // Dead end, go back one step
+            stack.pop();
+        }
+    }
+}
+
+// Same as maze_braid::braid_maze, loopiness is the chance in % that a dead end is removed
+pub fn braid_cell_maze(maze: &mut CellMaze, loopiness: u8, rng: &mut StdRng) {
+    let loopiness = loopiness.min(MAX_LOOPINESS);
+    if loopiness == 0 {
+        return;
+    }
+
+    let mut dead_ends: Vec<(usize, usize)> = maze
+        .cells()
+        .filter(|cell| maze.open_neighbours(cell).len() == 1)
+        .collect();
+    dead_ends.shuffle(rng);
+
+    for dead_end in dead_ends {
+        if maze.open_neighbours(&dead_end).len() != 1
+            || rng.gen_range(0..MAX_LOOPINESS) >= loopiness
+        {
+            continue;
+        }
+
+        let closed_directions: Vec<GridDirection> = maze
+            .directions(&dead_end)
+            .into_iter()
+            .filter(|direction| {
+                !maze.is_open(&dead_end, direction)
+                    && maze.neighbour(&dead_end, direction).is_some()
+            })
+            .collect();
+
+        if let Some(direction) = closed_directions.choose(rng) {
+            maze.carve_passage(&dead_end, direction);
+        }
+    }
+}
+
+// Number of steps between two hexagonal cells, using cube coordinates
+fn get_hexagonal_distance(cell: &(usize, usize), other_cell: &(usize, usize)) -> usize {
+    let to_cube = |(column, row): (usize, usize)| -> (isize, isize, isize) {
+        let x = column as isize - (row as isize - (row as isize & 1)) / 2;
+        let z = row as isize;
+        (x, -x - z, z)
+    };
+
+    let (x, y, z) = to_cube(*cell);
+    let (other_x, other_y, other_z) = to_cube(*other_cell);
+
+    (x - other_x)
+        .abs()
+        .max((y - other_y).abs())
+        .max((z - other_z).abs()) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::SeedableRng;
+
+    #[test]
+    fn hexagonal_neighbours_depend_on_the_row() {
+        let maze = CellMaze::new(GridTopology::Hexagonal, 5, 5);
+
+        // Even rows are half a cell to the west of the odd rows
+        assert_eq!(
+            maze.neighbours(&(2, 2)),
+            vec![(3, 2), (2, 3), (1, 3), (1, 2), (1, 1), (2, 1)]
+        );
+        assert_eq!(
+            maze.neighbours(&(2, 1)),
+            vec![(3, 1), (3, 2), (2, 2), (1, 1), (2, 0), (3, 0)]
+        );
+        assert_eq!(maze.neighbours(&(0, 0)), vec![(1, 0), (0, 1)]);
+        assert_eq!(maze.neighbour(&(2, 2), &GridDirection::North), None);
+    }
+
+    #[test]
+    fn triangular_neighbours_depend_on_the_point() {
+        let maze = CellMaze::new(GridTopology::Triangular, 4, 3);
+
+        // Pointing up, the flat side is at the bottom
+        assert!(CellMaze::is_triangle_up(&(1, 1)));
+        assert_eq!(maze.neighbours(&(1, 1)), vec![(2, 1), (0, 1), (1, 0)]);
+        // Pointing down, the flat side is at the top
+        assert!(!CellMaze::is_triangle_up(&(1, 0)));
+        assert_eq!(maze.neighbours(&(1, 0)), vec![(2, 0), (0, 0), (1, 1)]);
+        assert_eq!(maze.neighbour(&(1, 1), &GridDirection::North), None);
+        assert_eq!(maze.neighbours(&(0, 0)), vec![(1, 0)]);
+    }
+
+    #[test]
+    fn neighbours_are_neighbours_of_each_other() {
+        for topology in [
+            GridTopology::Square,
+            GridTopology::Hexagonal,
+            GridTopology::Triangular,
+        ] {
+            let maze = CellMaze::new(topology, 6, 5);
+
+            for cell in maze.cells() {
+                for direction in maze.directions(&cell) {
+                    if let Some(next_cell) = maze.neighbour(&cell, &direction) {
+                        assert_eq!(
+                            maze.neighbour(&next_cell, &direction.opposite()),
+                            Some(cell),
+                            "{} {:?} {:?}",
+                            topology.name(),
+                            cell,
+                            direction
+                        );
+                        assert_eq!(maze.estimated_steps(&cell, &next_cell), 1);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn carved_passage_is_open_on_both_sides() {
+        let mut maze = CellMaze::new(GridTopology::Hexagonal, 3, 3);
+        maze.carve_passage(&(1, 1), &GridDirection::NorthWest);
+
+        assert!(maze.is_open(&(1, 1), &GridDirection::NorthWest));
+        assert!(maze.is_open(&(1, 2), &GridDirection::SouthEast));
+        assert_eq!(maze.open_neighbours(&(1, 1)), vec![(1, 2)]);
+
+        // No cell to the west of the first column
+        maze.carve_passage(&(0, 0), &GridDirection::West);
+        assert!(!maze.is_open(&(0, 0), &GridDirection::West));
+    }
+
+    #[test]
+    fn carved_cell_maze_is_perfect() {
+        for topology in [GridTopology::Hexagonal, GridTopology::Triangular] {
+            let mut maze = CellMaze::new(topology, 9, 7);
+            carve_cell_maze(&mut maze, &mut StdRng::seed_from_u64(42));
+
+            let connection_count: usize = maze
+                .cells()
+                .map(|cell| maze.open_neighbours(&cell).len())
+                .sum::<usize>()
+                / 2;
+            assert_eq!(connection_count, 9 * 7 - 1, "{} has loops", topology.name());
+
+            let mut reached = vec![(0, 0)];
+            let mut to_visit = vec![(0, 0)];
+            while let Some(cell) = to_visit.pop() {
+                for next_cell in maze.open_neighbours(&cell) {
+                    if !reached.contains(&next_cell) {
+                        reached.push(next_cell);
+                        to_visit.push(next_cell);
+                    }
+                }
+            }
+            assert_eq!(reached.len(), 9 * 7, "{} is not connected", topology.name());
+        }
+    }
+}
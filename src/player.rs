@@ -1,8 +1,16 @@
+use crate::amazingly_lost_data::AmazinglyLostData;
+use crate::game_maze::{
+    self, ChangeFloorEvent, CollisionTile, CollisionType, ItemTile, PlayerTile, CELL_SIZE_MAZE,
+    SPRITE_SIZE_MAZE,
+};
 use crate::game_state::{ChangeGameStateEvent, GameState};
-use crate::maze_generator::{CollisionTile, CollisionType, PlayerTile, SPRITE_SIZE_MAZE};
+use crate::sound_factory::{PlaySoundEvent, Sound};
+use amazingly_lost::maze_floors::FloorPosition;
+use amazingly_lost::maze_topology::{CellMaze, GridDirection};
 
 use bevy::render::camera::Camera;
 use bevy::render::draw::OutsideFrustum;
+use serde::{Deserialize, Serialize};
 
 use bevy::{
     prelude::*,
@@ -14,21 +22,63 @@ use bevy::{
 pub const MOVEMENT_ACCELERATION: f32 = 20.0f32;
 pub const MOVEMENT: f32 = SPRITE_SIZE_MAZE as f32 / MOVEMENT_ACCELERATION; //This was default -> 3.0f32;
 
-#[derive(Clone, Debug, Eq, PartialEq, Copy)]
+// Hexagonal and triangular cells are not all the same distance apart, so we can't count steps
+pub const CELL_MOVEMENT: f32 = CELL_SIZE_MAZE / MOVEMENT_ACCELERATION;
+
+#[derive(Clone, Debug, Eq, PartialEq, Copy, Serialize, Deserialize)]
 pub enum Directions {
     North,
     South,
     East,
     West,
+    // Only used in mazes with hexagonal cells
+    NorthEast,
+    NorthWest,
+    SouthEast,
+    SouthWest,
     None,
 }
 
+impl Directions {
+    // The player sprite looks to the west, so it's flipped for these
+    pub fn is_eastward(self) -> bool {
+        matches!(
+            self,
+            Directions::East | Directions::NorthEast | Directions::SouthEast
+        )
+    }
+
+    pub fn to_grid_direction(self) -> Option<GridDirection> {
+        match self {
+            Directions::North => Some(GridDirection::North),
+            Directions::South => Some(GridDirection::South),
+            Directions::East => Some(GridDirection::East),
+            Directions::West => Some(GridDirection::West),
+            Directions::NorthEast => Some(GridDirection::NorthEast),
+            Directions::NorthWest => Some(GridDirection::NorthWest),
+            Directions::SouthEast => Some(GridDirection::SouthEast),
+            Directions::SouthWest => Some(GridDirection::SouthWest),
+            Directions::None => None,
+        }
+    }
+
+    // Going north and then east (or west) is a turn, going north and then south is not
+    pub fn is_turn_from(self, other: Directions) -> bool {
+        let is_vertical = |direction: Directions| {
+            direction == Directions::North || direction == Directions::South
+        };
+        is_vertical(self) != is_vertical(other)
+    }
+}
+
 pub struct CollisionWith {
     left: bool,
     right: bool,
     up: bool,
     down: bool,
     exit: bool,
+    ladder: Option<FloorPosition>,
+    bridge: bool,
 }
 
 impl CollisionWith {
@@ -39,6 +89,8 @@ impl CollisionWith {
             up: false,
             down: false,
             exit: false,
+            ladder: None,
+            bridge: false,
         }
     }
 }
@@ -49,7 +101,72 @@ pub struct ChangeDirectionPlugin;
 
 impl Plugin for ChangeDirectionPlugin {
     fn build(&self, app: &mut AppBuilder) {
-        app.add_system(check_direction_change.system());
+        app.add_system(check_direction_change.system())
+            .add_system(track_visited_positions.system())
+            .add_system(collect_items.system());
+    }
+}
+
+// Remember every tile (or cell) the player stood on, these are saved with the game
+fn track_visited_positions(
+    player_query: Query<(&Transform, &Player), With<PlayerTile>>,
+    mut amazing_data: ResMut<AmazinglyLostData>,
+    game_state: Res<State<GameState>>,
+) {
+    if *game_state.current() != GameState::PlayingGame {
+        return;
+    }
+
+    if let Ok((player_transform, player)) = player_query.single() {
+        if player.moving != Directions::None {
+            return;
+        }
+
+        let (x, y) = if amazing_data.cell_maze.is_some() {
+            (player.cell_position.0 as i64, player.cell_position.1 as i64)
+        } else {
+            game_maze::get_world_position(&player_transform.translation)
+        };
+        let floor = amazing_data.current_floor;
+        // Only a new position changes the data
+        if !amazing_data.visited.contains(&(x, y, floor)) {
+            amazing_data.visited.insert((x, y, floor));
+        }
+    }
+}
+
+// Pick up the item on the tile the player stands on
+fn collect_items(
+    mut commands: Commands,
+    player_query: Query<(&Transform, &Player), With<PlayerTile>>,
+    item_query: Query<(Entity, &ItemTile)>,
+    mut amazing_data: ResMut<AmazinglyLostData>,
+    game_state: Res<State<GameState>>,
+    mut play_sound: EventWriter<PlaySoundEvent>,
+) {
+    if *game_state.current() != GameState::PlayingGame
+        || amazing_data.items.is_empty()
+        || amazing_data.current_floor != 0
+    {
+        return;
+    }
+
+    if let Ok((player_transform, player)) = player_query.single() {
+        if player.moving != Directions::None {
+            return;
+        }
+
+        let (x, y) = game_maze::get_world_position(&player_transform.translation);
+        for (entity, item_tile) in item_query.iter() {
+            let item = &item_tile.0;
+            if (item.position.0 as i64, item.position.1 as i64) == (x, y) {
+                println!("Picked up: {}", item.name);
+                play_sound.send(PlaySoundEvent(Sound::Item));
+                commands.entity(entity).despawn();
+                amazing_data.items.retain(|maze_item| maze_item != item);
+                amazing_data.collected_items.push(item.clone());
+            }
+        }
     }
 }
 
@@ -75,21 +192,44 @@ pub fn check_direction_change(
     )>,
     mut change_direction: EventReader<ChangeDirectionEvent>,
     mut change_game_state: EventWriter<ChangeGameStateEvent>,
+    mut change_floor: EventWriter<ChangeFloorEvent>,
     game_state: ResMut<State<GameState>>,
+    mut amazing_data: ResMut<AmazinglyLostData>,
 ) {
+    let amazing_data = &mut *amazing_data;
     let mut new_direction = Directions::None;
 
     for direction_event in change_direction.iter() {
         new_direction = direction_event.0;
     }
 
+    // Hexagonal and triangular mazes have no WALL tiles to collide with
+    if let Some(cell_maze) = &amazing_data.cell_maze {
+        move_to_next_cell(
+            &mut camera_query,
+            &mut player_query,
+            &mut change_game_state,
+            cell_maze,
+            &(
+                amazing_data.exit_point_game.0,
+                amazing_data.exit_point_game.1,
+            ),
+            &new_direction,
+            game_state.current(),
+            &mut amazing_data.steps_walked,
+        );
+        return;
+    }
+
     move_to_next_maze_tile(
         &mut camera_query,
         &mut player_query,
         &mut collision_query,
         &mut change_game_state,
+        &mut change_floor,
         &new_direction,
         &game_state.current(),
+        &mut amazing_data.steps_walked,
     );
 }
 
@@ -99,9 +239,17 @@ pub struct Player {
     pub position_y: f32,
     pub moving: Directions,
     // TODO:RG Maybe items that can be used to help. Show maze_solution, flares if it's dark, etc.
+    // Direction of the last step, the player keeps looking that way
     pub direction: Directions,
     pub next_position_x: f32,
     pub next_position_y: f32,
+    // Set when the player walks onto a ladder, we go there after the move
+    pub ladder_destination: Option<FloorPosition>,
+    // Set while the player is on a bridge, the direction we went onto it
+    pub crossing_bridge: Option<Directions>,
+    // Only used in mazes with hexagonal or triangular cells
+    pub cell_position: (usize, usize),
+    pub next_cell_position: (usize, usize),
 }
 
 impl Player {
@@ -113,10 +261,15 @@ impl Player {
             direction: Directions::North,
             next_position_x: 0.0,
             next_position_y: 0.0,
+            ladder_destination: None,
+            crossing_bridge: None,
+            cell_position: (0, 0),
+            next_cell_position: (0, 0),
         }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub fn move_to_next_maze_tile(
     camera_query: &mut Query<(
         &mut Transform,
@@ -138,15 +291,25 @@ pub fn move_to_next_maze_tile(
         ),
     )>,
     change_game_state: &mut EventWriter<ChangeGameStateEvent>,
+    change_floor: &mut EventWriter<ChangeFloorEvent>,
     new_direction: &Directions,
     game_state: &GameState,
+    steps_walked: &mut usize,
 ) {
     // Only usefull while playing the game
     if *game_state == GameState::PlayingGame {
         if let Ok((mut player_transform, mut player_sprite, mut player, _player_tile)) =
             player_query.single_mut()
         {
-            if *new_direction != Directions::None && player.moving == Directions::None {
+            // We can't turn on a bridge, only go on or back
+            let is_turn_on_bridge = player
+                .crossing_bridge
+                .is_some_and(|bridge_direction| new_direction.is_turn_from(bridge_direction));
+
+            if *new_direction != Directions::None
+                && player.moving == Directions::None
+                && !is_turn_on_bridge
+            {
                 let mut collision_with = CollisionWith::new();
                 let mut collision_count = 0;
                 // check collision with walls
@@ -171,15 +334,30 @@ pub fn move_to_next_maze_tile(
 
                     if let Some(collision_side) = collision {
                         // println!("Collider: {:?}", collider);
-                        if collider.collision_type == CollisionType::CollisionWall {
+                        if collider.collision_type == CollisionType::Wall {
                             match collision_side {
                                 Collision::Left => collision_with.left = true,
                                 Collision::Right => collision_with.right = true,
                                 Collision::Top => collision_with.up = true,
                                 Collision::Bottom => collision_with.down = true,
                             }
-                        } else if collider.collision_type == CollisionType::CollisionExit {
+                        } else if collider.collision_type == CollisionType::Exit {
                             collision_with.exit = true;
+                        } else if let CollisionType::Ladder(destination) = collider.collision_type {
+                            // The ladder we are standing on also collides, we only want
+                            // the one we walk to
+                            if collider_transform.translation.truncate()
+                                != player_transform.translation.truncate()
+                            {
+                                collision_with.ladder = Some(destination);
+                            }
+                        } else if collider.collision_type == CollisionType::Bridge {
+                            // Same as the ladder, the bridge we are standing on doesn't count
+                            if collider_transform.translation.truncate()
+                                != player_transform.translation.truncate()
+                            {
+                                collision_with.bridge = true;
+                            }
                         }
                     }
                 }
@@ -195,10 +373,19 @@ pub fn move_to_next_maze_tile(
                         &mut player,
                         &mut player_transform.translation,
                     );
+                    player.direction = *new_direction;
+                    *steps_walked += 1;
+                    player.ladder_destination = collision_with.ladder;
+                    player.crossing_bridge = if collision_with.bridge {
+                        Some(*new_direction)
+                    } else {
+                        None
+                    };
                 } else if collision_with.exit {
                     println!("EXIT");
-                    // TODO:RG for now we generate a new maze
-                    change_game_state.send(ChangeGameStateEvent(GameState::GenerateNewGame));
+                    // The last step is onto the exit
+                    *steps_walked += 1;
+                    change_game_state.send(ChangeGameStateEvent(GameState::Win));
                 }
             } else {
                 // println!("Collisino size: {:?}", collision_count);
@@ -208,6 +395,7 @@ pub fn move_to_next_maze_tile(
                         &mut player,
                         &mut player_transform.translation,
                         camera_query,
+                        change_floor,
                     );
                 }
             }
@@ -215,6 +403,96 @@ pub fn move_to_next_maze_tile(
     }
 }
 
+// Same as move_to_next_maze_tile, but we look in the CellMaze if the passage is open
+#[allow(clippy::too_many_arguments)]
+pub fn move_to_next_cell(
+    camera_query: &mut Query<(
+        &mut Transform,
+        (With<Camera>, (Without<CollisionTile>, Without<PlayerTile>)),
+    )>,
+    player_query: &mut Query<(
+        &mut Transform,
+        &mut Sprite,
+        &mut Player,
+        (With<PlayerTile>, (Without<CollisionTile>, Without<Camera>)),
+    )>,
+    change_game_state: &mut EventWriter<ChangeGameStateEvent>,
+    cell_maze: &CellMaze,
+    exit_cell: &(usize, usize),
+    new_direction: &Directions,
+    game_state: &GameState,
+    steps_walked: &mut usize,
+) {
+    // Only usefull while playing the game
+    if *game_state != GameState::PlayingGame {
+        return;
+    }
+
+    if let Ok((mut player_transform, mut player_sprite, mut player, _player_tile)) =
+        player_query.single_mut()
+    {
+        if player.moving == Directions::None {
+            let grid_direction = match new_direction.to_grid_direction() {
+                Some(grid_direction) => grid_direction,
+                None => return,
+            };
+            // Player Sprite is default looking to the West
+            match grid_direction {
+                GridDirection::West | GridDirection::NorthWest | GridDirection::SouthWest => {
+                    player_sprite.flip_x = false
+                }
+                GridDirection::East | GridDirection::NorthEast | GridDirection::SouthEast => {
+                    player_sprite.flip_x = true
+                }
+                _ => {}
+            }
+
+            if !cell_maze.is_open(&player.cell_position, &grid_direction) {
+                return;
+            }
+            if let Some(next_cell) = cell_maze.neighbour(&player.cell_position, &grid_direction) {
+                let (next_x, next_y) =
+                    game_maze::get_cell_position(&cell_maze.topology(), &next_cell);
+                player.next_cell_position = next_cell;
+                player.next_position_x = next_x;
+                player.next_position_y = next_y;
+                player.moving = *new_direction;
+                player.direction = *new_direction;
+                *steps_walked += 1;
+            }
+        } else {
+            let distance = Vec2::new(
+                player.next_position_x - player_transform.translation.x,
+                player.next_position_y - player_transform.translation.y,
+            );
+
+            if distance.length() <= CELL_MOVEMENT {
+                // Finished moving
+                player_transform.translation.x = player.next_position_x;
+                player_transform.translation.y = player.next_position_y;
+                player.position_x = player.next_position_x;
+                player.position_y = player.next_position_y;
+                player.cell_position = player.next_cell_position;
+                player.moving = Directions::None;
+
+                if player.cell_position == *exit_cell {
+                    println!("EXIT");
+                    change_game_state.send(ChangeGameStateEvent(GameState::Win));
+                }
+            } else {
+                let step = distance.normalize() * CELL_MOVEMENT;
+                player_transform.translation.x += step.x;
+                player_transform.translation.y += step.y;
+            }
+
+            // Keep camera's on the same position as the player
+            for (mut camera_transform, _camera) in camera_query.iter_mut() {
+                camera_transform.translation = player_transform.translation;
+            }
+        }
+    }
+}
+
 fn set_next_player_position(
     new_direction: &Directions,
     player: &mut Player,
@@ -255,6 +533,7 @@ fn handle_movement_by_collision(
         &mut Transform,
         (With<Camera>, (Without<CollisionTile>, Without<PlayerTile>)),
     )>,
+    change_floor: &mut EventWriter<ChangeFloorEvent>,
 ) {
     if player.moving == Directions::West {
         player_translation.x -= MOVEMENT;
@@ -281,6 +560,11 @@ fn handle_movement_by_collision(
         player.moving = Directions::None;
         player.position_x = player.next_position_x;
         player.position_y = player.next_position_y;
+
+        // We are on the ladder now, so go to the other floor
+        if let Some(destination) = player.ladder_destination.take() {
+            change_floor.send(ChangeFloorEvent(destination));
+        }
     }
     // Keep camera's on the same position as the player
     for (mut camera_transform, _camera) in camera_query.iter_mut() {
@@ -1,14 +1,135 @@
-// TODO:RG need to implement music and sounds
+use crate::game_maze::ChangeFloorEvent;
+use crate::game_settings::{GameSettings, MAX_VOLUME};
+use crate::game_state::GameState;
 
-// Load and play all the sounds and music
-// Need some system that connects certain tiles to sounds
-// So we can trigger them if we get close to them -> like a snoring bear
+use bevy::audio::{play_queued_audio_system, AudioOutput, Decodable};
+use bevy::prelude::*;
+use bevy::reflect::TypeUuid;
+use rodio::source::{Amplify, SineWave, Source, TakeDuration};
+use std::time::Duration;
 
-// Also, use music like with the game_flow with certain game states.
-// Use events for the ambient sounds
+// TODO:RG music and ambient sounds, like a snoring bear when we get close to certain tiles.
+// Those need sound files, they can be played with Audio and the AssetServer:
+//     audio.play(asset_server.load("sounds/Windless Slopes.mp3"));
 
-// Use it like this?
-// fn setup(asset_server: Res<AssetServer>, audio: Res<Audio>) {
-//     let music = asset_server.load("sounds/Windless Slopes.mp3");
-//     audio.play(music);
-// }
+// A sine at full volume is very loud, this is the loudest a sound gets at 100%
+const MAX_AMPLITUDE: f32 = 0.25;
+
+// The game has no sound files yet, so the sounds are short tones that are made while playing
+#[derive(Debug, Clone, TypeUuid)]
+#[uuid = "ab6a6a7b-7c9d-4d32-9cd4-162fbfbf3fdd"]
+pub struct Tone {
+    frequency: u32,
+    duration: Duration,
+    amplitude: f32,
+}
+
+impl Decodable for Tone {
+    type Decoder = Amplify<TakeDuration<SineWave>>;
+
+    fn decoder(&self) -> Self::Decoder {
+        SineWave::new(self.frequency)
+            .take_duration(self.duration)
+            .amplify(self.amplitude)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Sound {
+    // A button in a menu was used
+    MenuButton,
+    Ladder,
+    // The player picked up an item of the level
+    Item,
+    Exit,
+}
+
+impl Sound {
+    // Frequency in Hz and duration in milliseconds
+    fn tone(&self) -> (u32, u64) {
+        match self {
+            Sound::MenuButton => (660, 60),
+            Sound::Ladder => (330, 150),
+            Sound::Item => (880, 120),
+            Sound::Exit => (523, 400),
+        }
+    }
+}
+
+// Plays the sound with the volume from the settings
+pub struct PlaySoundEvent(pub Sound);
+
+pub struct SoundPlugin;
+
+impl Plugin for SoundPlugin {
+    fn build(&self, app: &mut AppBuilder) {
+        // Same as the AudioPlugin of Bevy does for sound files
+        app.init_non_send_resource::<AudioOutput<Tone>>()
+            .add_asset::<Tone>()
+            .init_resource::<Audio<Tone>>()
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                play_queued_audio_system::<Tone>.exclusive_system(),
+            )
+            .add_event::<PlaySoundEvent>()
+            .add_system(play_sounds.system())
+            .add_system(play_ladder_sound.system())
+            .add_system_set(
+                SystemSet::on_enter(GameState::Win).with_system(play_exit_sound.system()),
+            );
+    }
+}
+
+// Every sound gets its own Tone, it's removed again after it was played
+fn play_sounds(
+    mut play_sound: EventReader<PlaySoundEvent>,
+    game_settings: Res<GameSettings>,
+    mut tones: ResMut<Assets<Tone>>,
+    audio: Res<Audio<Tone>>,
+) {
+    for PlaySoundEvent(sound) in play_sound.iter() {
+        if game_settings.volume == 0 {
+            continue;
+        }
+
+        let (frequency, milliseconds) = sound.tone();
+        audio.play(tones.add(Tone {
+            frequency,
+            duration: Duration::from_millis(milliseconds),
+            amplitude: MAX_AMPLITUDE * game_settings.volume as f32 / MAX_VOLUME as f32,
+        }));
+    }
+}
+
+fn play_ladder_sound(
+    mut change_floor: EventReader<ChangeFloorEvent>,
+    mut play_sound: EventWriter<PlaySoundEvent>,
+) {
+    if change_floor.iter().next().is_some() {
+        play_sound.send(PlaySoundEvent(Sound::Ladder));
+    }
+}
+
+fn play_exit_sound(mut play_sound: EventWriter<PlaySoundEvent>) {
+    play_sound.send(PlaySoundEvent(Sound::Exit));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tone_is_no_louder_than_its_amplitude() {
+        let tone = Tone {
+            frequency: 440,
+            duration: Duration::from_millis(10),
+            amplitude: 0.1,
+        };
+
+        // A sine always has 48000 samples a second
+        let samples: Vec<f32> = tone.decoder().collect();
+        assert_eq!(samples.len(), 480);
+        assert!(samples.iter().all(|sample| sample.abs() <= 0.1));
+        assert!(samples.iter().any(|sample| sample.abs() > 0.09));
+    }
+}
@@ -1,17 +1,19 @@
 use bevy::{asset::HandleId, prelude::*};
+use rand::rngs::StdRng;
 use rand::Rng;
 use std::path::Path;
 use std::{env, path::PathBuf};
 
 use crate::amazingly_lost_data::AmazinglyLostData;
 
-use crate::maze_tile::TileType;
+use amazingly_lost::maze_tile::TileType;
 
 // Paths to the textures
 pub const WALLS_NORMAL: &str = "walls/normal/wall_";
 pub const WALLS_SPECIAL: &str = "walls/special/wall_";
 pub const GROUNDS_NORMAL: &str = "grounds/normal/ground_";
 pub const GROUNDS_SPECIAL: &str = "grounds/special/ground_";
+pub const GROUNDS_ROOM: &str = "grounds/room/ground_";
 
 // Max per ground, wall -> normal, special
 pub const MAX_ASSETS: u8 = 50;
@@ -54,12 +56,21 @@ pub struct GameTileHandlers {
     // Multiple open places for randomness
     pub normal_grounds: Vec<Handle<ColorMaterial>>,
     pub special_grounds: Vec<Handle<ColorMaterial>>,
+    // Grounds inside the rooms of a dungeon
+    pub room_grounds: Vec<Handle<ColorMaterial>>,
     // Unique tiles
     pub player: Handle<ColorMaterial>,
     pub start: Handle<ColorMaterial>,
     pub exit: Handle<ColorMaterial>,
     pub region: Handle<ColorMaterial>,
     pub solution: Handle<ColorMaterial>,
+    pub ladder_up: Handle<ColorMaterial>,
+    pub ladder_down: Handle<ColorMaterial>,
+    pub bridge: Handle<ColorMaterial>,
+    // Items of a level, every item looks the same for now
+    pub item: Handle<ColorMaterial>,
+    // Theme these handlers are loaded from
+    pub theme: String,
 }
 
 impl GameTileHandlers {
@@ -72,6 +83,7 @@ impl GameTileHandlers {
             normal_grounds: Vec::<Handle<ColorMaterial>>::new(),
             // Should contain funny grounds or something like that.
             special_grounds: Vec::<Handle<ColorMaterial>>::new(),
+            room_grounds: Vec::<Handle<ColorMaterial>>::new(),
             // We should have only a unique player, start and exit tile
             // So, no list
             player: Handle::weak(HandleId::default::<ColorMaterial>()),
@@ -79,6 +91,11 @@ impl GameTileHandlers {
             exit: Handle::weak(HandleId::default::<ColorMaterial>()),
             region: Handle::weak(HandleId::default::<ColorMaterial>()),
             solution: Handle::weak(HandleId::default::<ColorMaterial>()),
+            ladder_up: Handle::weak(HandleId::default::<ColorMaterial>()),
+            ladder_down: Handle::weak(HandleId::default::<ColorMaterial>()),
+            bridge: Handle::weak(HandleId::default::<ColorMaterial>()),
+            item: Handle::weak(HandleId::default::<ColorMaterial>()),
+            theme: String::new(),
         }
     }
 
@@ -86,29 +103,32 @@ impl GameTileHandlers {
         &self,
         tile_type: &TileType,
         position: &Vec3,
+        rng: &mut StdRng,
     ) -> Option<SpriteBundle> {
         // +1 because we start at 1
-        let random_number = rand::thread_rng().gen_range(1..=MAX_CHANCE_WALLS);
+        let random_number = rng.gen_range(1..=MAX_CHANCE_WALLS);
 
         match tile_type {
             TileType::Wall => match random_number {
                 // Highest chance to get a normal wall
-                1..=CHANCE_NORMAL_WALL => self.get_game_tile(tile_type, true, position),
+                1..=CHANCE_NORMAL_WALL => self.get_game_tile(tile_type, true, position, rng),
                 // Special walls should be so special that we don't want too many of them
-                _ => self.get_game_tile(tile_type, false, position),
+                _ => self.get_game_tile(tile_type, false, position, rng),
             },
             TileType::Border => match random_number {
                 // Highest chance to get a normal wall
-                1..=CHANCE_NORMAL_BORDER => self.get_game_tile(tile_type, true, position),
+                1..=CHANCE_NORMAL_BORDER => self.get_game_tile(tile_type, true, position, rng),
                 // Special walls should be so special that we don't want too many of them
-                _ => self.get_game_tile(tile_type, false, position),
+                _ => self.get_game_tile(tile_type, false, position, rng),
             },
             TileType::Open => match random_number {
                 // Highest chance to get a normal wall
-                1..=CHANCE_NORMAL_GROUND => self.get_game_tile(tile_type, true, position),
+                1..=CHANCE_NORMAL_GROUND => self.get_game_tile(tile_type, true, position, rng),
                 // Special walls should be so special that we don't want too many of them
-                _ => self.get_game_tile(tile_type, false, position),
+                _ => self.get_game_tile(tile_type, false, position, rng),
             },
+            // No special grounds in rooms, they should look the same everywhere
+            TileType::Room => self.get_game_tile(tile_type, true, position, rng),
             _ => None,
         }
     }
@@ -118,8 +138,9 @@ impl GameTileHandlers {
         tile_type: &TileType,
         is_normal: bool,
         position: &Vec3,
+        rng: &mut StdRng,
     ) -> Option<SpriteBundle> {
-        if let Some(handle) = self.get_handle(tile_type, is_normal) {
+        if let Some(handle) = self.get_handle(tile_type, is_normal, rng) {
             Some(SpriteBundle {
                 material: handle.clone(),
                 transform: Transform::from_translation(*position),
@@ -178,6 +199,36 @@ impl GameTileHandlers {
         }
     }
 
+    pub fn get_game_ladder(&self, tile_type: &TileType, position: &Vec3) -> Option<SpriteBundle> {
+        let handle = match tile_type {
+            TileType::LadderUp => &self.ladder_up,
+            TileType::LadderDown => &self.ladder_down,
+            _ => return None,
+        };
+
+        Some(SpriteBundle {
+            material: handle.clone(),
+            transform: Transform::from_translation(*position),
+            ..Default::default()
+        })
+    }
+
+    pub fn get_game_bridge(&self, position: &Vec3) -> Option<SpriteBundle> {
+        Some(SpriteBundle {
+            material: self.bridge.clone(),
+            transform: Transform::from_translation(*position),
+            ..Default::default()
+        })
+    }
+
+    pub fn get_game_item(&self, position: &Vec3) -> Option<SpriteBundle> {
+        Some(SpriteBundle {
+            material: self.item.clone(),
+            transform: Transform::from_translation(*position),
+            ..Default::default()
+        })
+    }
+
     pub fn get_game_region(&self, position: &Vec3) -> Option<SpriteBundle> {
         if let handle = &self.region {
             Some(SpriteBundle {
@@ -190,22 +241,31 @@ impl GameTileHandlers {
         }
     }
 
-    fn get_handle(&self, tile_type: &TileType, is_normal: bool) -> Option<&Handle<ColorMaterial>> {
+    fn get_handle(
+        &self,
+        tile_type: &TileType,
+        is_normal: bool,
+        rng: &mut StdRng,
+    ) -> Option<&Handle<ColorMaterial>> {
         match tile_type {
-            TileType::Start => self.get_ground(is_normal),
-            TileType::Exit => self.get_a_wall(is_normal),
-            TileType::Wall => self.get_a_wall(is_normal),
-            TileType::Border => self.get_a_wall(is_normal),
-            TileType::Open => self.get_ground(is_normal),
-            TileType::Open => self.get_ground(is_normal),
+            TileType::Start => self.get_ground(is_normal, rng),
+            TileType::Exit => self.get_a_wall(is_normal, rng),
+            TileType::DecoyExit => self.get_a_wall(is_normal, rng),
+            TileType::Wall => self.get_a_wall(is_normal, rng),
+            TileType::Border => self.get_a_wall(is_normal, rng),
+            TileType::Open => self.get_ground(is_normal, rng),
+            TileType::Open => self.get_ground(is_normal, rng),
+            TileType::LadderUp | TileType::LadderDown => self.get_ground(is_normal, rng),
+            TileType::Bridge => self.get_ground(is_normal, rng),
+            TileType::Room => self.get_room_ground(rng),
         }
     }
 
-    fn get_a_wall(&self, is_normal: bool) -> Option<&Handle<ColorMaterial>> {
+    fn get_a_wall(&self, is_normal: bool, rng: &mut StdRng) -> Option<&Handle<ColorMaterial>> {
         if is_normal {
             if self.normal_walls.len() > 0 {
                 self.normal_walls
-                    .get(rand::thread_rng().gen_range(0..self.normal_walls.len()))
+                    .get(rng.gen_range(0..self.normal_walls.len()))
             } else {
                 // If there is no normal wall then don't go further
                 None
@@ -213,19 +273,29 @@ impl GameTileHandlers {
         } else {
             if self.special_walls.len() > 0 {
                 self.special_walls
-                    .get(rand::thread_rng().gen_range(0..self.special_walls.len()))
+                    .get(rng.gen_range(0..self.special_walls.len()))
             } else {
                 // If there are no special walls, then try to get a normal
-                self.get_a_wall(true)
+                self.get_a_wall(true, rng)
             }
         }
     }
 
-    fn get_ground(&self, is_normal: bool) -> Option<&Handle<ColorMaterial>> {
+    fn get_room_ground(&self, rng: &mut StdRng) -> Option<&Handle<ColorMaterial>> {
+        if self.room_grounds.len() > 0 {
+            self.room_grounds
+                .get(rng.gen_range(0..self.room_grounds.len()))
+        } else {
+            // If there are no room grounds, then use a normal ground
+            self.get_ground(true, rng)
+        }
+    }
+
+    fn get_ground(&self, is_normal: bool, rng: &mut StdRng) -> Option<&Handle<ColorMaterial>> {
         if is_normal {
             if self.normal_grounds.len() > 0 {
                 self.normal_grounds
-                    .get(rand::thread_rng().gen_range(0..self.normal_grounds.len()))
+                    .get(rng.gen_range(0..self.normal_grounds.len()))
             } else {
                 // If there is no normal ground then don't go further
                 None
@@ -233,10 +303,10 @@ impl GameTileHandlers {
         } else {
             if self.special_grounds.len() > 0 {
                 self.special_grounds
-                    .get(rand::thread_rng().gen_range(0..self.special_grounds.len()))
+                    .get(rng.gen_range(0..self.special_grounds.len()))
             } else {
                 // If there are no special grounds, then try to get a normal
-                self.get_ground(true)
+                self.get_ground(true, rng)
             }
         }
     }
@@ -280,6 +350,13 @@ pub fn load_all_assets(
         &tile_theme,
         &GROUNDS_SPECIAL.to_string(),
     );
+    amazing_data.game_tile_handlers.room_grounds = load_game_tiles(
+        &current_dir,
+        &mut asset_server,
+        &mut materials,
+        &tile_theme,
+        &GROUNDS_ROOM.to_string(),
+    );
 
     load_game_uniques(
         &current_dir,
@@ -287,6 +364,35 @@ pub fn load_all_assets(
         &mut materials,
         &mut amazing_data,
     );
+    amazing_data.game_tile_handlers.theme = tile_theme.clone();
+}
+
+// Every directory in assets/theme is a theme, sorted so the menu always has the same order
+pub fn get_theme_names() -> Vec<String> {
+    let theme_dir = env::current_dir().unwrap().join("assets").join("theme");
+    let mut theme_names: Vec<String> = match std::fs::read_dir(theme_dir) {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    theme_names.sort();
+    theme_names
+}
+
+// The theme after this one, after the last one we start again with the first
+pub fn get_next_theme(theme: &str) -> Option<String> {
+    let theme_names = get_theme_names();
+    let next_theme = theme_names
+        .iter()
+        .position(|theme_name| theme_name == theme)
+        .map_or(0, |index| index + 1);
+    theme_names
+        .get(next_theme)
+        .or_else(|| theme_names.first())
+        .cloned()
 }
 
 fn load_game_tiles(
@@ -321,6 +427,8 @@ fn load_game_tiles(
                     game_tiles_vec.push(materials.add(Color::DARK_GREEN.into()));
                 } else if *tile_dir == GROUNDS_NORMAL.to_string() {
                     game_tiles_vec.push(materials.add(Color::LIME_GREEN.into()));
+                } else if *tile_dir == GROUNDS_ROOM.to_string() {
+                    game_tiles_vec.push(materials.add(Color::BEIGE.into()));
                 }
             }
             // println!("Path DOESN'T exits");
@@ -357,6 +465,21 @@ fn load_game_uniques(
         amazing_data.theme
     );
     // println!("path: {}", player_png_file);
+    let ladder_up_png_file = format!(
+        "{}/assets/theme/{}/uniques/ladder_up.png",
+        current_dir.display(),
+        amazing_data.theme
+    );
+    let ladder_down_png_file = format!(
+        "{}/assets/theme/{}/uniques/ladder_down.png",
+        current_dir.display(),
+        amazing_data.theme
+    );
+    let bridge_png_file = format!(
+        "{}/assets/theme/{}/uniques/bridge.png",
+        current_dir.display(),
+        amazing_data.theme
+    );
 
     if Path::new(start_png_file.as_str()).exists() {
         // println!("Path exits");
@@ -385,6 +508,36 @@ fn load_game_uniques(
         amazing_data.game_tile_handlers.player = materials.add(Color::YELLOW.into());
     }
 
+    if Path::new(ladder_up_png_file.as_str()).exists() {
+        amazing_data.game_tile_handlers.ladder_up = materials.add(
+            asset_server
+                .load(ladder_up_png_file.as_str())
+                .clone()
+                .into(),
+        );
+    } else {
+        amazing_data.game_tile_handlers.ladder_up = materials.add(Color::GOLD.into());
+    }
+
+    if Path::new(ladder_down_png_file.as_str()).exists() {
+        amazing_data.game_tile_handlers.ladder_down = materials.add(
+            asset_server
+                .load(ladder_down_png_file.as_str())
+                .clone()
+                .into(),
+        );
+    } else {
+        amazing_data.game_tile_handlers.ladder_down = materials.add(Color::TEAL.into());
+    }
+
+    if Path::new(bridge_png_file.as_str()).exists() {
+        amazing_data.game_tile_handlers.bridge =
+            materials.add(asset_server.load(bridge_png_file.as_str()).clone().into());
+    } else {
+        amazing_data.game_tile_handlers.bridge = materials.add(Color::ORANGE.into());
+    }
+
     // For now we just use RED
     amazing_data.game_tile_handlers.solution = materials.add(Color::RED.into());
+    amazing_data.game_tile_handlers.item = materials.add(Color::PINK.into());
 }